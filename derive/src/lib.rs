@@ -0,0 +1,356 @@
+//! Derive macros for `bevy_save_manager`, re-exported by its `derive` feature
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{
+    Parse,
+    ParseStream,
+};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input,
+    Data,
+    DeriveInput,
+    Expr,
+    Ident,
+    LitBool,
+    LitInt,
+    LitStr,
+    Token,
+    Type,
+};
+
+/// Implement `EncryptSave`, configured by a `#[save(...)]` attribute:
+///
+/// - `key = "..."`: encryption key
+/// - `key_env = "NAME"`: encryption key read from the environment variable `NAME` at compile time
+/// - `version = 3`: `EncryptSave::VERSION`
+/// - `migrate(1 => SaveV1, 2 => SaveV2)`: slots of an older version are decoded as the given type,
+///   then converted with `Into`
+#[proc_macro_derive(EncryptSave, attributes(save))]
+pub fn derive_encrypt_save(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encrypt_save(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `GameSetting`, configured by a `#[setting(...)]` attribute:
+///
+/// - `file = "audio.conf"`: `GameSetting::DEFAULT_CONF`
+/// - `dir = "config"`: `GameSetting::DIR`
+/// - `format = "toml"`: `GameSetting::FORMAT`, one of `ron`, `toml` or `json`, the last two need the feature of the same name
+/// - `per_profile = false`: `GameSetting::PER_PROFILE`
+/// - `debounce_ms = 500`: `GameSetting::DEBOUNCE`, in milliseconds
+/// - `lenient`: `GameSetting::parse` with `SettingFormat::deserialize_lenient`, the type must derive `Reflect`
+/// - `version = 2`: `GameSetting::VERSION`
+/// - `migrate(0 => AudioV0, 1 => AudioV1)`: files of an older version are deserialized as the given type,
+///   then converted with `Into`
+///
+/// and `#[setting(section = "video.conf")]` on every field, to store each field in its own file with
+/// `GameSetting::SECTIONS`. `#[setting_ui(label = "Music", min = 0.0, max = 1.0, step = 0.05)]` on a field
+/// adds it to `GameSetting::UI`.
+#[proc_macro_derive(GameSetting, attributes(setting, setting_ui))]
+pub fn derive_game_setting(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_game_setting(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Migration {
+    version: LitInt,
+    ty: Type,
+}
+
+impl Parse for Migration {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let version = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let ty = input.parse()?;
+        Ok(Self { version, ty })
+    }
+}
+
+fn expand_encrypt_save(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut key: Option<Expr> = None;
+    let mut version: Option<LitInt> = None;
+    let mut migrations = Vec::new();
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("save")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("key") {
+                let value: LitStr = meta.value()?.parse()?;
+                key = Some(syn::parse_quote!(#value));
+            } else if meta.path.is_ident("key_env") {
+                let value: LitStr = meta.value()?.parse()?;
+                key = Some(syn::parse_quote!(::core::env!(#value)));
+            } else if meta.path.is_ident("version") {
+                version = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("migrate") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                migrations.extend(Punctuated::<Migration, Token![,]>::parse_terminated(&content)?);
+            } else {
+                return Err(meta.error("expected `key`, `key_env`, `version` or `migrate`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let key = key.map(|key| quote!(const ENCR_KEY: &'static str = #key;));
+    let version = version.map(|version| quote!(const VERSION: u32 = #version;));
+    let migrate = (!migrations.is_empty()).then(|| {
+        let arms = migrations.iter().map(|Migration { version, ty }| {
+            quote!(#version => encoding.decode::<#ty>(data).map(::core::convert::Into::into),)
+        });
+        quote! {
+            fn migrate(
+                version: u32,
+                data: &[u8],
+                encoding: ::bevy_save_manager::save::SaveEncoding,
+            ) -> ::core::result::Result<Self, ::bevy_save_manager::error::SaveError> {
+                match version {
+                    #(#arms)*
+                    _ => ::core::result::Result::Err(::bevy_save_manager::error::SaveError::VersionMismatch {
+                        saved: version.to_string(),
+                        current: <Self as ::bevy_save_manager::save::EncryptSave>::VERSION.to_string(),
+                    }),
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::bevy_save_manager::save::EncryptSave for #name #ty_generics #where_clause {
+            #key
+            #version
+            #migrate
+        }
+    })
+}
+
+fn expand_game_setting(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut items = Vec::new();
+    let mut migrations = Vec::new();
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("setting")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("file") {
+                let value: LitStr = meta.value()?.parse()?;
+                items.push(quote!(const DEFAULT_CONF: &'static str = #value;));
+            } else if meta.path.is_ident("dir") {
+                let value: LitStr = meta.value()?.parse()?;
+                items.push(quote!(const DIR: &'static str = #value;));
+            } else if meta.path.is_ident("format") {
+                let value: LitStr = meta.value()?.parse()?;
+                let variant = match value.value().as_str() {
+                    "ron" => "Ron",
+                    "toml" => "Toml",
+                    "json" => "Json",
+                    _ => return Err(syn::Error::new(value.span(), "expected `ron`, `toml` or `json`")),
+                };
+                let variant = Ident::new(variant, value.span());
+                items.push(quote!(
+                    const FORMAT: ::bevy_save_manager::setting::SettingFormat =
+                        ::bevy_save_manager::setting::SettingFormat::#variant;
+                ));
+            } else if meta.path.is_ident("per_profile") {
+                let value: LitBool = meta.value()?.parse()?;
+                items.push(quote!(const PER_PROFILE: bool = #value;));
+            } else if meta.path.is_ident("debounce_ms") {
+                let value: LitInt = meta.value()?.parse()?;
+                items.push(quote!(
+                    const DEBOUNCE: ::core::time::Duration = ::core::time::Duration::from_millis(#value);
+                ));
+            } else if meta.path.is_ident("lenient") {
+                items.push(quote!(
+                    fn parse(data: &[u8]) -> ::core::result::Result<Self, ::bevy_save_manager::error::SettingError> {
+                        <Self as ::bevy_save_manager::setting::GameSetting>::FORMAT.deserialize_lenient(data)
+                    }
+                ));
+            } else if meta.path.is_ident("version") {
+                let value: LitInt = meta.value()?.parse()?;
+                items.push(quote!(const VERSION: u32 = #value;));
+            } else if meta.path.is_ident("migrate") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                migrations.extend(Punctuated::<Migration, Token![,]>::parse_terminated(&content)?);
+            } else {
+                return Err(meta.error(
+                    "expected `file`, `dir`, `format`, `per_profile`, `debounce_ms`, `lenient`, `version` or `migrate`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    if !migrations.is_empty() {
+        let arms = migrations.iter().map(|Migration { version, ty }| {
+            quote!(#version => <Self as ::bevy_save_manager::setting::GameSetting>::FORMAT
+                .deserialize::<#ty>(data)
+                .map(::core::convert::Into::into),)
+        });
+        items.push(quote! {
+            fn migrate(
+                version: u32,
+                data: &[u8],
+            ) -> ::core::result::Result<Self, ::bevy_save_manager::error::SettingError> {
+                match version {
+                    #(#arms)*
+                    _ => ::core::result::Result::Err(::bevy_save_manager::error::SettingError::VersionMismatch {
+                        saved: version,
+                        current: <Self as ::bevy_save_manager::setting::GameSetting>::VERSION,
+                    }),
+                }
+            }
+        });
+    }
+
+    items.extend(expand_sections(&input)?);
+    items.extend(expand_ui(&input)?);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_save_manager::setting::GameSetting for #name #ty_generics #where_clause {
+            #(#items)*
+        }
+    })
+}
+
+/// `GameSetting::SECTIONS` and its functions, from the `#[setting(section = "...")]` of the fields
+fn expand_sections(input: &DeriveInput) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let Data::Struct(data) = &input.data else {
+        return Ok(None);
+    };
+    let mut sections = Vec::new();
+    for field in &data.fields {
+        let mut section: Option<LitStr> = None;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("setting")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("section") {
+                    section = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `section`"))
+                }
+            })?;
+        }
+        sections.push((field, section));
+    }
+    if sections.iter().all(|(_, section)| section.is_none()) {
+        return Ok(None);
+    }
+
+    let mut files = Vec::new();
+    let mut encode = Vec::new();
+    let mut decode = Vec::new();
+    for (field, section) in sections {
+        let Some(ident) = &field.ident else {
+            return Err(syn::Error::new_spanned(field, "sections need named fields"));
+        };
+        let Some(file) = section else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "every field needs a `#[setting(section = \"...\")]`",
+            ));
+        };
+        encode.push(quote!(
+            #file => <Self as ::bevy_save_manager::setting::GameSetting>::FORMAT.serialize(&self.#ident),
+        ));
+        decode.push(quote!(
+            #file => {
+                self.#ident = <Self as ::bevy_save_manager::setting::GameSetting>::FORMAT.deserialize(data)?;
+                ::core::result::Result::Ok(())
+            }
+        ));
+        files.push(file);
+    }
+    let unknown = quote!(::core::result::Result::Err(
+        ::bevy_save_manager::error::SettingError::Format(::std::format!("Unknown setting section {}", section).into())
+    ));
+
+    Ok(Some(quote! {
+        const SECTIONS: &'static [&'static str] = &[#(#files),*];
+
+        fn encode_section(
+            &self,
+            section: &str,
+        ) -> ::core::result::Result<::std::vec::Vec<u8>, ::bevy_save_manager::error::SettingError> {
+            match section {
+                #(#encode)*
+                _ => #unknown,
+            }
+        }
+
+        fn decode_section(
+            &mut self,
+            section: &str,
+            data: &[u8],
+        ) -> ::core::result::Result<(), ::bevy_save_manager::error::SettingError> {
+            match section {
+                #(#decode)*
+                _ => #unknown,
+            }
+        }
+    }))
+}
+
+/// `GameSetting::UI`, from the `#[setting_ui(...)]` of the fields
+fn expand_ui(input: &DeriveInput) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let Data::Struct(data) = &input.data else {
+        return Ok(None);
+    };
+    let mut hints = Vec::new();
+    for field in &data.fields {
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("setting_ui")) {
+            let Some(ident) = &field.ident else {
+                return Err(syn::Error::new_spanned(field, "`setting_ui` needs named fields"));
+            };
+            let mut label: Option<LitStr> = None;
+            let mut min: Option<Expr> = None;
+            let mut max: Option<Expr> = None;
+            let mut step: Option<Expr> = None;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("label") {
+                    label = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min") {
+                    min = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max") {
+                    max = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("step") {
+                    step = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `label`, `min`, `max` or `step`"));
+                }
+                Ok(())
+            })?;
+            let name = ident.to_string();
+            let label = match label {
+                Some(label) => quote!(::core::option::Option::Some(#label)),
+                None => quote!(::core::option::Option::None),
+            };
+            let [min, max, step] = [min, max, step].map(|value| match value {
+                Some(value) => quote!(::core::option::Option::Some((#value) as f64)),
+                None => quote!(::core::option::Option::None),
+            });
+            hints.push(quote!(::bevy_save_manager::options::SettingUi {
+                field: #name,
+                label: #label,
+                min: #min,
+                max: #max,
+                step: #step,
+            }));
+        }
+    }
+    if hints.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(quote! {
+        const UI: &'static [::bevy_save_manager::options::SettingUi] = &[#(#hints),*];
+    }))
+}
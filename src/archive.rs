@@ -0,0 +1,337 @@
+//! Single-file archives of a slot, to move saves between machines or attach them to bug reports
+use crate::backend::SaveStorage;
+use crate::cipher::{
+    open,
+    Cipher,
+    PlainCipher,
+    SaveCipher,
+    SaveHeader,
+    SaveKey,
+    SavePassword,
+};
+use crate::delta::{
+    DeltaFlattener,
+    DeltaSaves,
+};
+use crate::error::SaveError;
+use crate::io::PendingWrites;
+use crate::meta::SlotMeta;
+use crate::naming::{
+    new_save_file,
+    NamingRng,
+};
+use crate::paths::SaveDirs;
+use crate::profile::CurrentProfile;
+use crate::registry::SaveRegistry;
+use crate::save::{
+    save_key,
+    EncryptSave,
+    LoadLimits,
+    SaveConfig,
+    SaveEncoding,
+    SaveOptions,
+    SaveSlot,
+    SaveVersionMismatch,
+};
+use crate::setting::GameSettingChanged;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    Message,
+    MessageReader,
+    MessageWriter,
+    Res,
+    ResMut,
+    Resource,
+};
+#[cfg(feature = "drag-and-drop")]
+use bevy::window::FileDragAndDrop;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// Start of archive files
+const MAGIC: &[u8; 4] = b"BSMA";
+
+/// Write slot `slot` with its metadata and thumbnail to `dest`, on the local file system
+#[derive(Message)]
+pub struct ExportSave {
+    pub slot: u32,
+    pub dest: PathBuf,
+}
+
+/// Add the archive at `src` as a new slot. The save must be readable with the current key and password, and signed
+/// with the signing key if there is one. Unencrypted saves are only imported when the [`SaveCipher`] is [`PlainCipher`].
+#[derive(Message)]
+pub struct ImportSave {
+    pub src: PathBuf,
+}
+
+#[derive(Message)]
+pub struct SaveExported {
+    pub slot: u32,
+    pub dest: PathBuf,
+}
+
+#[derive(Message)]
+pub struct SaveImported {
+    pub slot: u32,
+    pub src: PathBuf,
+}
+
+/// An [`ExportSave`] or [`ImportSave`] failed, `path` is the archive
+#[derive(Message, Debug)]
+pub struct ArchiveFailed {
+    pub path: PathBuf,
+    pub error: SaveError,
+}
+
+/// Whether the file at `path` starts like an archive written by [`ExportSave`]
+pub fn is_save_archive(path: &Path) -> bool {
+    let mut magic = [0; MAGIC.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|_| magic == *MAGIC)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveArchive {
+    meta: SlotMeta,
+    /// Save file as stored on disk, still encrypted
+    data: Vec<u8>,
+    /// PNG thumbnail
+    thumbnail: Option<Vec<u8>>,
+}
+
+pub(crate) fn on_export<T>(
+    mut exports: MessageReader<ExportSave>,
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    storage: Res<SaveStorage>,
+    writes: Res<PendingWrites>,
+    delta_saves: DeltaSaves,
+    mut exported: MessageWriter<SaveExported>,
+    mut failed: MessageWriter<ArchiveFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    // Files still being written would be exported half done
+    writes.flush();
+
+    let flattener = delta_saves.flattener::<T>();
+    for msg in exports.read() {
+        match export(&save_config, &dirs, &storage, &flattener, msg.slot, &msg.dest) {
+            Ok(()) => {
+                exported.write(SaveExported {
+                    slot: msg.slot,
+                    dest: msg.dest.clone(),
+                });
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Failed to export slot {} to {}: {}",
+                    msg.slot,
+                    msg.dest.display(),
+                    error
+                );
+                failed.write(ArchiveFailed {
+                    path: msg.dest.clone(),
+                    error,
+                });
+            }
+        }
+    }
+}
+
+fn export(
+    save_config: &SaveConfig,
+    dirs: &SaveDirs,
+    storage: &SaveStorage,
+    flattener: &DeltaFlattener,
+    id: u32,
+    dest: &Path,
+) -> Result<(), SaveError> {
+    fs::write(dest, encode_archive(save_config, dirs, storage, flattener, id)?)?;
+    Ok(())
+}
+
+/// Archive of slot `id`, as written by [`ExportSave`]. Delta slots are archived as full saves.
+pub(crate) fn encode_archive(
+    save_config: &SaveConfig,
+    dirs: &SaveDirs,
+    storage: &SaveStorage,
+    flattener: &DeltaFlattener,
+    id: u32,
+) -> Result<Vec<u8>, SaveError> {
+    let slot = save_config.slot(id).ok_or(SaveError::NotFound(id))?;
+    let save_dir = save_config.save_dir(dirs);
+    let archive = SaveArchive {
+        meta: SlotMeta::from(slot),
+        data: flattener.full_save(storage, &save_dir, slot)?,
+        thumbnail: slot
+            .thumbnail
+            .as_ref()
+            .and_then(|thumbnail| storage.read(&save_dir.join(thumbnail)).ok()),
+    };
+    Ok([MAGIC.as_slice(), &SaveEncoding::Legacy.encode(&archive)?].concat())
+}
+
+pub(crate) fn on_import<T>(
+    mut imports: MessageReader<ImportSave>,
+    mut importer: ArchiveImporter,
+    mut imported: MessageWriter<SaveImported>,
+    mut failed: MessageWriter<ArchiveFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    let max_file_size = importer.options.load_limits.max_file_size;
+    for msg in imports.read() {
+        match fs::metadata(&msg.src)
+            .map_err(SaveError::from)
+            .and_then(|metadata| LoadLimits::check(metadata.len(), max_file_size))
+            .and_then(|_| Ok(fs::read(&msg.src)?))
+            .and_then(|data| importer.import::<T>(&data))
+        {
+            Ok(slot) => {
+                imported.write(SaveImported {
+                    slot,
+                    src: msg.src.clone(),
+                });
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to import save {}: {}", msg.src.display(), error);
+                failed.write(ArchiveFailed {
+                    path: msg.src.clone(),
+                    error,
+                });
+            }
+        }
+    }
+}
+
+/// Add archives as new slots
+#[derive(SystemParam)]
+pub(crate) struct ArchiveImporter<'w> {
+    save_config: ResMut<'w, SaveConfig>,
+    dirs: Res<'w, SaveDirs>,
+    storage: Res<'w, SaveStorage>,
+    writes: Res<'w, PendingWrites>,
+    cipher: Res<'w, SaveCipher>,
+    password: Res<'w, SavePassword>,
+    key: Res<'w, SaveKey>,
+    registry: Res<'w, SaveRegistry>,
+    options: Res<'w, SaveOptions>,
+    rng: Res<'w, NamingRng>,
+    profile: Res<'w, CurrentProfile>,
+    #[cfg(feature = "signing")]
+    signing: Option<Res<'w, crate::signing::SigningKey>>,
+    mismatch: MessageWriter<'w, SaveVersionMismatch>,
+    setting_changed: MessageWriter<'w, GameSettingChanged>,
+}
+
+impl ArchiveImporter<'_> {
+    /// Validate `data` and add it as a new slot, returning its id
+    pub fn import<T>(&mut self, data: &[u8]) -> Result<u32, SaveError>
+    where
+        T: EncryptSave,
+    {
+        let archive = data
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| SaveError::Corrupted("Not a save archive".to_string()))
+            .and_then(|data| SaveEncoding::Legacy.decode::<SaveArchive>(data))?;
+        // Checked before anything else, plain saves included
+        #[cfg(feature = "signing")]
+        crate::signing::verify_with(self.signing.as_deref(), &archive.data)?;
+        // The header of the archive can't be trusted to allow a save without encryption
+        let allow_plain = self.cipher.0.id() == PlainCipher.id();
+        let key = save_key::<T>(&self.key);
+        let decrypted = open(
+            self.cipher.0.as_ref(),
+            &archive.data,
+            &key,
+            self.password.get(),
+            allow_plain,
+        )?;
+        LoadLimits::check(decrypted.len() as u64, self.options.load_limits.max_allocation)?;
+        self.registry.check_version(&decrypted)?;
+
+        let id = self
+            .save_config
+            .next_id(self.options.id_allocation)
+            .ok_or(SaveError::NoFreeSlot)?;
+        let file = new_save_file(&self.options.naming, &self.rng, &self.profile, id, |file| {
+            self.save_config
+                .file_taken(&self.dirs, &self.storage, &self.writes, file)
+        });
+        let save_dir = self.save_config.save_dir(&self.dirs).to_path_buf();
+        self.storage.write(&save_dir.join(&file), &archive.data)?;
+        let thumbnail = archive.thumbnail.and_then(|png| {
+            let thumbnail = file.with_extension("png");
+            self.storage
+                .write(&save_dir.join(&thumbnail), &png)
+                .ok()
+                .map(|_| thumbnail)
+        });
+
+        if let Some(current) = &self.options.game_version {
+            let saved = SaveHeader::decode(&archive.data).and_then(|(header, _)| header.game_version);
+            if saved.as_ref() != Some(current) {
+                self.mismatch.write(SaveVersionMismatch {
+                    slot: Some(id),
+                    saved,
+                    current: current.clone(),
+                });
+            }
+        }
+
+        let plain =
+            allow_plain && SaveHeader::decode(&archive.data).is_some_and(|(header, _)| header.cipher == PlainCipher.id());
+        let meta = archive.meta;
+        self.save_config.insert_slot(
+            id,
+            SaveSlot {
+                file,
+                name: meta.name,
+                description: meta.description,
+                thumbnail,
+                playtime: meta.playtime,
+                created_at: meta.created_at,
+                saved_at: meta.saved_at,
+                revision: 1,
+                kind: meta.kind,
+                tags: meta.tags,
+                game_version: meta.game_version,
+                fields: meta.fields,
+                custom: meta.custom,
+                plain,
+                ..Default::default()
+            },
+        );
+        self.setting_changed.write(GameSettingChanged);
+        Ok(id)
+    }
+}
+
+/// Import archives dropped onto a window, other files are ignored
+#[cfg(feature = "drag-and-drop")]
+pub(crate) fn import_dropped(mut drops: MessageReader<FileDragAndDrop>, mut imports: MessageWriter<ImportSave>) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else {
+            continue;
+        };
+        if is_save_archive(path_buf) {
+            imports.write(ImportSave { src: path_buf.clone() });
+        }
+    }
+}
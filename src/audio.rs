@@ -0,0 +1,156 @@
+//! Volumes of the audio buses, kept in a settings file by [`AudioSettingsPlugin`]
+use crate::options::SettingUi;
+use crate::setting::{
+    GameSetting,
+    GameSettingSupportPlugin,
+    SettingIssue,
+};
+use bevy::app::App;
+#[cfg(feature = "audio")]
+use bevy::audio::{
+    AudioSink,
+    AudioSinkPlayback,
+    GlobalVolume,
+    Volume,
+};
+#[cfg(feature = "audio")]
+use bevy::prelude::{
+    resource_changed,
+    DetectChanges,
+    IntoScheduleConfigs,
+    Query,
+    Res,
+    ResMut,
+    Update,
+};
+use bevy::prelude::{
+    Component,
+    Plugin,
+    Reflect,
+    Resource,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::time::Duration;
+
+/// Bus of an audio entity, its [`AudioSink`](bevy::audio::AudioSink) follows the volume of the bus
+/// with the `audio` feature
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    Music,
+    Effects,
+    Voice,
+    Interface,
+}
+
+/// Volumes between 0 and 1, the volume of each bus is multiplied by [`Self::master`]
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+    pub voice: f32,
+    pub interface: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            effects: 1.0,
+            voice: 1.0,
+            interface: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Volume of the sounds of `bus`, 0 when muted
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let volume = match bus {
+            AudioBus::Music => self.music,
+            AudioBus::Effects => self.effects,
+            AudioBus::Voice => self.voice,
+            AudioBus::Interface => self.interface,
+        };
+        self.master * volume
+    }
+}
+
+impl GameSetting for AudioSettings {
+    const DEFAULT_CONF: &'static str = "audio.conf";
+    /// Sliders change the volume every frame while dragged
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+    const UI: &'static [SettingUi] = &[
+        SettingUi::new("master").with_range(0.0, 1.0).with_step(0.05),
+        SettingUi::new("music").with_range(0.0, 1.0).with_step(0.05),
+        SettingUi::new("effects").with_range(0.0, 1.0).with_step(0.05),
+        SettingUi::new("voice").with_range(0.0, 1.0).with_step(0.05),
+        SettingUi::new("interface").with_range(0.0, 1.0).with_step(0.05),
+    ];
+
+    fn validate(&mut self) -> Result<(), Vec<SettingIssue>> {
+        let mut issues = Vec::new();
+        for (field, volume) in [
+            ("master", &mut self.master),
+            ("music", &mut self.music),
+            ("effects", &mut self.effects),
+            ("voice", &mut self.voice),
+            ("interface", &mut self.interface),
+        ] {
+            if !(0.0..=1.0).contains(volume) {
+                issues.push(SettingIssue::new(field, format!("volume {} is out of 0..=1", volume)));
+                *volume = if volume.is_nan() { 1.0 } else { volume.clamp(0.0, 1.0) };
+            }
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// Load the [`AudioSettings`]. With the `audio` feature, the master volume is applied to the
+/// [`GlobalVolume`](bevy::audio::GlobalVolume) and the volume of each bus to the sinks of its [`AudioBus`].
+pub struct AudioSettingsPlugin;
+
+impl Plugin for AudioSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GameSettingSupportPlugin::<AudioSettings>::default());
+
+        #[cfg(feature = "audio")]
+        app.add_systems(
+            Update,
+            (
+                apply_global_volume.run_if(resource_changed::<AudioSettings>),
+                apply_bus_volumes,
+            ),
+        );
+    }
+}
+
+#[cfg(feature = "audio")]
+fn apply_global_volume(settings: Res<AudioSettings>, mut global_volume: ResMut<GlobalVolume>) {
+    let master = if settings.muted { 0.0 } else { settings.master };
+    *global_volume = GlobalVolume::new(Volume::Linear(master));
+}
+
+/// Sinks are only added once their sound has been decoded
+#[cfg(feature = "audio")]
+fn apply_bus_volumes(settings: Res<AudioSettings>, mut sinks: Query<(&AudioBus, &mut AudioSink)>) {
+    for (bus, mut sink) in &mut sinks {
+        if settings.is_changed() || sink.is_added() {
+            sink.set_volume(Volume::Linear(settings.volume(*bus)));
+        }
+    }
+}
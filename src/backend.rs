@@ -0,0 +1,217 @@
+use bevy::prelude::{
+    Deref,
+    Resource,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::{
+    BufReader,
+    BufWriter,
+    Read,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+};
+
+/// Where save files and settings are stored. Paths are the ones the plugin would use on the local file system,
+/// a backend is free to map them to its own namespace.
+pub trait SaveBackend: Send + Sync + 'static {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Files directly inside `dir`
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let data = self.read(from)?;
+        self.write(to, &data)
+    }
+
+    /// Flush `path` to durable storage, for backends which buffer writes
+    fn sync(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|data| data.len() as u64)
+    }
+
+    /// Bytes that can still be written in `dir`, `None` if unknown
+    fn available_space(&self, _dir: &Path) -> Option<u64> {
+        None
+    }
+
+    /// Read `path` progressively, for streamed saves. The whole file is read at once if not implemented.
+    fn reader(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    /// Write what `write` produces to `path` progressively, for streamed saves.
+    /// The data is collected and written at once if not implemented.
+    fn write_with(&self, path: &Path, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        let mut data = Vec::new();
+        write(&mut data)?;
+        self.write(path, &data)
+    }
+}
+
+/// Local file system, the default backend
+#[derive(Default, Clone, Copy)]
+pub struct FsBackend;
+
+impl SaveBackend for FsBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        write_atomically(path, |file| file.write_all(data))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(fs::read_dir(dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::copy(from, to).map(|_| ())
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        fs::File::open(path)?.sync_all()?;
+        // The directory entry of a new file is only durable once its directory is synced
+        #[cfg(unix)]
+        if let Some(parent_dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::File::open(parent_dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.len())
+    }
+
+    /// Free space of the disk mounted the deepest above `dir`
+    #[cfg(feature = "disk-space")]
+    fn available_space(&self, dir: &Path) -> Option<u64> {
+        let dir = dir.ancestors().find_map(|dir| dir.canonicalize().ok())?;
+        sysinfo::Disks::new_with_refreshed_list()
+            .iter()
+            .filter(|disk| dir.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space())
+    }
+
+    fn reader(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(BufReader::new(fs::File::open(path)?)))
+    }
+
+    fn write_with(&self, path: &Path, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        write_atomically(path, |file| write(file))
+    }
+}
+
+/// Write `path` into a sibling file renamed over it once synced, so a failed write or a crash leaves the previous
+/// file as it was. The rename itself is only durable once the directory is synced, see [`SaveBackend::sync`].
+fn write_atomically(path: &Path, write: impl FnOnce(&mut BufWriter<fs::File>) -> io::Result<()>) -> io::Result<()> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let result = fs::File::create(&temp)
+        .and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write(&mut file)?;
+            file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Files kept in memory, for tests or platforms without storage. Clones share the same files.
+#[derive(Default, Clone)]
+pub struct MemoryBackend(Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>);
+
+impl MemoryBackend {
+    fn files(&self) -> MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Every stored path
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files().keys().cloned().collect()
+    }
+}
+
+impl SaveBackend for MemoryBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.files().insert(path.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.files()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files().contains_key(path)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files()
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Backend used by the plugins. Set it with [`EncryptSavePlugin::with_backend`](crate::save::EncryptSavePlugin::with_backend).
+#[derive(Resource, Clone, Deref)]
+pub struct SaveStorage(pub Arc<dyn SaveBackend>);
+
+impl Default for SaveStorage {
+    fn default() -> Self {
+        Self(Arc::new(FsBackend))
+    }
+}
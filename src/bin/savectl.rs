@@ -0,0 +1,201 @@
+//! Inspect and repair save files outside of the game
+//!
+//! ```text
+//! savectl list <save_setting.conf>
+//! savectl verify <save_setting.conf> --key <key> [--save-dir <dir>]
+//! savectl dump <save.dat> --key <key>
+//! savectl meta <save.dat>
+//! savectl reencrypt <save.dat> --key <key> --new-key <key> [--output <file>]
+//! ```
+//!
+//! `verify` checks that every slot decrypts, which also authenticates its content, and splits into sections.
+//! Save files are looked up in the `save_dir` of the config, relative to the working directory, unless `--save-dir` is given.
+//! `meta` prints the `.meta` sidecar of a save, written with `EncryptSavePlugin::with_meta_sidecars`.
+//! `dump` can't decode resources without their types, sections are printed as hex, and as text when they are UTF-8.
+use bevy_save_manager::cipher::SecretKey;
+use bevy_save_manager::inspect::{
+    decrypt_save,
+    encrypt_save,
+    save_sections,
+};
+use bevy_save_manager::backend::FsBackend;
+use bevy_save_manager::meta::read_meta;
+use bevy_save_manager::paths::SaveDirs;
+use bevy_save_manager::save::SaveConfig;
+use bevy_save_manager::setting::GameSetting;
+use serde_json::{
+    json,
+    Value,
+};
+use std::error::Error;
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage:
+    savectl list <save_setting.conf>
+    savectl verify <save_setting.conf> --key <key> [--save-dir <dir>]
+    savectl dump <save.dat> --key <key>
+    savectl meta <save.dat>
+    savectl reencrypt <save.dat> --key <key> --new-key <key> [--output <file>]";
+
+struct Args {
+    command: String,
+    path: PathBuf,
+    key: Option<String>,
+    new_key: Option<String>,
+    output: Option<PathBuf>,
+    save_dir: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let Some(args) = parse_args(std::env::args().skip(1)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let result = match args.command.as_str() {
+        "list" => list(&args.path),
+        "verify" => required(&args.key, "--key").and_then(|key| verify(&args.path, key, args.save_dir.as_deref())),
+        "dump" => required(&args.key, "--key").and_then(|key| dump(&args.path, key)),
+        "meta" => meta(&args.path),
+        "reencrypt" => required(&args.key, "--key").and_then(|key| {
+            let new_key = required(&args.new_key, "--new-key")?;
+            reencrypt(&args.path, key, new_key, args.output.as_deref().unwrap_or(&args.path))
+        }),
+        _ => Err(USAGE.into()),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Args> {
+    let command = args.next()?;
+    let mut path = None;
+    let (mut key, mut new_key, mut output, mut save_dir) = (None, None, None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => key = Some(args.next()?),
+            "--new-key" => new_key = Some(args.next()?),
+            "--output" | "-o" => output = Some(PathBuf::from(args.next()?)),
+            "--save-dir" => save_dir = Some(PathBuf::from(args.next()?)),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return None,
+        }
+    }
+    Some(Args {
+        command,
+        path: path?,
+        key,
+        new_key,
+        output,
+        save_dir,
+    })
+}
+
+fn required<'a>(value: &'a Option<String>, flag: &str) -> Result<&'a str, Box<dyn Error>> {
+    value.as_deref().ok_or_else(|| format!("{} is required", flag).into())
+}
+
+fn load_config(path: &Path) -> Result<SaveConfig, Box<dyn Error>> {
+    let mut save_config = SaveConfig::default();
+    save_config.load_from(path)?;
+    Ok(save_config)
+}
+
+fn list(config_path: &Path) -> Result<bool, Box<dyn Error>> {
+    let save_config = load_config(config_path)?;
+    println!("save_dir: {}", save_config.save_dir(&SaveDirs::default()).display());
+    println!("last_saved: {}", save_config.last_saved());
+    for (id, slot) in save_config.sorted_by_recency() {
+        println!(
+            "{:>4}  {:<24} {:<32} saved_at={} playtime={}s revision={}",
+            id,
+            slot.name,
+            slot.file.display(),
+            slot.saved_at,
+            slot.playtime.as_secs(),
+            slot.revision
+        );
+    }
+    for checkpoint in save_config.checkpoints() {
+        println!(
+            "   -  checkpoint               {:<32} saved_at={}",
+            checkpoint.file.display(),
+            checkpoint.saved_at
+        );
+    }
+    Ok(true)
+}
+
+fn verify(config_path: &Path, key: &str, save_dir: Option<&Path>) -> Result<bool, Box<dyn Error>> {
+    let save_config = load_config(config_path)?;
+    let config_save_dir = save_config.save_dir(&SaveDirs::default());
+    let save_dir = save_dir.unwrap_or(&config_save_dir);
+    let mut all_valid = true;
+    for (id, slot) in save_config.sorted_by_recency() {
+        let path = save_dir.join(&slot.file);
+        let result = fs::read(&path)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|data| Ok(decrypt_save(&data, &SecretKey::from(key))?));
+        match result {
+            Ok(data) => match save_sections(&data) {
+                Some(sections) => println!("{:>4}  OK      {} sections", id, sections.len()),
+                None => println!("{:>4}  OK      legacy layout", id),
+            },
+            Err(e) => {
+                all_valid = false;
+                println!("{:>4}  FAILED  {}: {}", id, path.display(), e);
+            }
+        }
+    }
+    Ok(all_valid)
+}
+
+fn dump(path: &Path, key: &str) -> Result<bool, Box<dyn Error>> {
+    let data = decrypt_save(&fs::read(path)?, &SecretKey::from(key))?;
+    let sections = match save_sections(&data) {
+        Some(sections) => sections,
+        None => vec![("legacy".to_string(), data.to_vec())],
+    };
+
+    let sections: Vec<Value> = sections
+        .iter()
+        .map(|(name, bytes)| {
+            let mut section = json!({
+                "name": name,
+                "size": bytes.len(),
+                "hex": bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            });
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                section["text"] = Value::String(text.to_string());
+            }
+            section
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&json!({ "sections": sections }))?);
+    Ok(true)
+}
+
+fn meta(path: &Path) -> Result<bool, Box<dyn Error>> {
+    let meta = read_meta(&FsBackend, path)?;
+    println!("{}", serde_json::to_string_pretty(&meta)?);
+    Ok(true)
+}
+
+fn reencrypt(path: &Path, key: &str, new_key: &str, output: &Path) -> Result<bool, Box<dyn Error>> {
+    let data = decrypt_save(&fs::read(path)?, &SecretKey::from(key))?;
+    fs::write(output, encrypt_save(&data, &SecretKey::from(new_key))?)?;
+    println!("Wrote {}", output.display());
+    Ok(true)
+}
@@ -0,0 +1,973 @@
+use crate::error::SaveError;
+use bevy::prelude::{
+    Deref,
+    Message,
+    MessageReader,
+    ResMut,
+    Resource,
+};
+use std::fmt;
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Start of files with a [`SaveHeader`]. Files without it are legacy saves.
+const MAGIC: &[u8; 4] = b"BSMH";
+const TAG_END: u8 = 0;
+const TAG_CIPHER: u8 = 1;
+const TAG_SALT: u8 = 2;
+const TAG_KEY_ID: u8 = 3;
+const TAG_GAME_VERSION: u8 = 4;
+const TAG_CHUNK_SIZE: u8 = 5;
+const TAG_SIGNATURE: u8 = 6;
+const TAG_KDF: u8 = 7;
+const SALT_LEN: usize = 16;
+pub(crate) const SIGNATURE_LEN: usize = 32;
+/// [`Cipher::id`] of [`PlainCipher`]
+const PLAIN_CIPHER: u8 = u8::MAX;
+/// Index and last flag in front of the data of each chunk
+const CHUNK_PREFIX_LEN: usize = 5;
+/// Upper bound of what a cipher adds to a chunk, like its nonce and tag
+const CHUNK_OVERHEAD: usize = 1024;
+
+/// Encryption of save files. The nonce, if any, is part of the returned data.
+pub trait Cipher: Send + Sync + 'static {
+    /// Written in the file header to find the cipher back on load, 0 is the legacy cipher
+    fn id(&self) -> u8;
+
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError>;
+
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError>;
+}
+
+/// `simple_crypt`, used by saves written before ciphers were selectable. Its older files have no header.
+pub struct LegacyCipher;
+
+impl Cipher for LegacyCipher {
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        simple_crypt::encrypt(data, key).map_err(|e| SaveError::Encrypt(e.into()))
+    }
+
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        simple_crypt::decrypt(data, key).map_err(|e| SaveError::Decrypt(e.into()))
+    }
+}
+
+/// Leaves the data as it is, for slots saved without encryption with
+/// [`SaveGame::plain`](crate::save::SaveGame::plain). Its saves need neither key nor password.
+pub struct PlainCipher;
+
+impl Cipher for PlainCipher {
+    fn id(&self) -> u8 {
+        PLAIN_CIPHER
+    }
+
+    fn encrypt(&self, data: &[u8], _key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        Ok(data.to_vec())
+    }
+
+    fn decrypt(&self, data: &[u8], _key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        Ok(data.to_vec())
+    }
+}
+
+/// AES-256-GCM with a random nonce per file, the key is hashed with SHA-256
+#[cfg(feature = "aes-gcm")]
+pub struct AesGcmCipher;
+
+#[cfg(feature = "aes-gcm")]
+impl Cipher for AesGcmCipher {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        use aes_gcm::aead::{
+            Aead,
+            AeadCore,
+            KeyInit,
+            OsRng,
+        };
+        use aes_gcm::Aes256Gcm;
+
+        let cipher =
+            Aes256Gcm::new_from_slice(&*hash_key(key)).map_err(|e| SaveError::Encrypt(e.to_string().into()))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let encrypted = cipher
+            .encrypt(&nonce, data)
+            .map_err(|e| SaveError::Encrypt(e.to_string().into()))?;
+        Ok([nonce.as_slice(), &encrypted].concat())
+    }
+
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        use aes_gcm::aead::{
+            Aead,
+            KeyInit,
+        };
+        use aes_gcm::{
+            Aes256Gcm,
+            Nonce,
+        };
+
+        const NONCE_LEN: usize = 12;
+        if data.len() < NONCE_LEN {
+            return Err(SaveError::Corrupted("Missing nonce".to_string()));
+        }
+        let (nonce, encrypted) = data.split_at(NONCE_LEN);
+        Aes256Gcm::new_from_slice(&*hash_key(key))
+            .map_err(|e| SaveError::Decrypt(e.to_string().into()))?
+            .decrypt(Nonce::from_slice(nonce), encrypted)
+            .map_err(|e| SaveError::Decrypt(e.to_string().into()))
+    }
+}
+
+/// ChaCha20-Poly1305 with a random nonce per file, the key is hashed with SHA-256
+#[cfg(feature = "chacha20poly1305")]
+pub struct ChaChaCipher;
+
+#[cfg(feature = "chacha20poly1305")]
+impl Cipher for ChaChaCipher {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        use chacha20poly1305::aead::{
+            Aead,
+            AeadCore,
+            KeyInit,
+            OsRng,
+        };
+        use chacha20poly1305::ChaCha20Poly1305;
+
+        let cipher =
+            ChaCha20Poly1305::new_from_slice(&*hash_key(key)).map_err(|e| SaveError::Encrypt(e.to_string().into()))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = cipher
+            .encrypt(&nonce, data)
+            .map_err(|e| SaveError::Encrypt(e.to_string().into()))?;
+        Ok([nonce.as_slice(), &encrypted].concat())
+    }
+
+    fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+        use chacha20poly1305::aead::{
+            Aead,
+            KeyInit,
+        };
+        use chacha20poly1305::{
+            ChaCha20Poly1305,
+            Nonce,
+        };
+
+        const NONCE_LEN: usize = 12;
+        if data.len() < NONCE_LEN {
+            return Err(SaveError::Corrupted("Missing nonce".to_string()));
+        }
+        let (nonce, encrypted) = data.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new_from_slice(&*hash_key(key))
+            .map_err(|e| SaveError::Decrypt(e.to_string().into()))?
+            .decrypt(Nonce::from_slice(nonce), encrypted)
+            .map_err(|e| SaveError::Decrypt(e.to_string().into()))
+    }
+}
+
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+fn hash_key(key: &[u8]) -> Zeroizing<[u8; 32]> {
+    use sha2::Digest;
+    Zeroizing::new(sha2::Sha256::digest(key).into())
+}
+
+/// Cipher of new saves. Set it with [`EncryptSavePlugin::with_cipher`](crate::save::EncryptSavePlugin::with_cipher),
+/// it defaults to the strongest one enabled by features. Saves of other built-in ciphers stay readable.
+#[derive(Resource, Clone, Deref)]
+pub struct SaveCipher(pub Arc<dyn Cipher>);
+
+impl Default for SaveCipher {
+    fn default() -> Self {
+        #[cfg(feature = "aes-gcm")]
+        return Self(Arc::new(AesGcmCipher));
+        #[cfg(all(feature = "chacha20poly1305", not(feature = "aes-gcm")))]
+        return Self(Arc::new(ChaChaCipher));
+        #[cfg(not(any(feature = "aes-gcm", feature = "chacha20poly1305")))]
+        Self(Arc::new(LegacyCipher))
+    }
+}
+
+/// Fields in front of the encrypted data, stored as tagged values so that readers skip the ones they don't know
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct SaveHeader {
+    /// [`Cipher::id`] of the data
+    pub cipher: u8,
+    /// Salt of the key derived from a [`SavePassword`], `None` if the save has no password
+    pub salt: Option<[u8; SALT_LEN]>,
+    /// [`key_id`] of the key, `None` with a password
+    pub key_id: Option<u32>,
+    /// Version of the game that wrote the save, see [`EncryptSavePlugin::with_game_version`](crate::save::EncryptSavePlugin::with_game_version)
+    pub game_version: Option<String>,
+    /// Size of the chunks of a streamed save, `None` if the data is encrypted at once.
+    /// See [`EncryptSavePlugin::with_streaming`](crate::save::EncryptSavePlugin::with_streaming).
+    pub chunk_size: Option<u32>,
+    /// HMAC-SHA256 of the other fields and the data after the header, see [`EncryptSavePlugin::with_signing_key`](crate::save::EncryptSavePlugin::with_signing_key)
+    pub signature: Option<[u8; SIGNATURE_LEN]>,
+    /// Cost of the key derived from the password, set with `salt`
+    pub kdf: Option<KdfParams>,
+}
+
+/// Argon2id cost of the key derived from a [`SavePassword`], recorded in the [`SaveHeader`] so it can be raised
+/// without breaking older saves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub mem_cost: u32,
+    /// Passes over the memory
+    pub time_cost: u32,
+    pub lanes: u32,
+}
+
+impl KdfParams {
+    /// Cost of new saves: 19 MiB, 2 passes and 1 lane, the minimum recommended by OWASP for Argon2id.
+    /// A key takes tens of milliseconds to derive, once per save or load, while guessing passwords needs the
+    /// memory for every attempt.
+    pub const DEFAULT: Self = Self {
+        mem_cost: 19 * 1024,
+        time_cost: 2,
+        lanes: 1,
+    };
+}
+
+impl SaveHeader {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.extend([TAG_CIPHER, 1, self.cipher]);
+        if let Some(salt) = &self.salt {
+            header.extend([TAG_SALT, SALT_LEN as u8]);
+            header.extend(salt);
+        }
+        if let Some(key_id) = self.key_id {
+            header.extend([TAG_KEY_ID, 4]);
+            header.extend(key_id.to_le_bytes());
+        }
+        if let Some(game_version) = &self.game_version {
+            // Values are at most 255 bytes long
+            let game_version = &game_version.as_bytes()[..game_version.len().min(u8::MAX as usize)];
+            header.extend([TAG_GAME_VERSION, game_version.len() as u8]);
+            header.extend(game_version);
+        }
+        if let Some(chunk_size) = self.chunk_size {
+            header.extend([TAG_CHUNK_SIZE, 4]);
+            header.extend(chunk_size.to_le_bytes());
+        }
+        if let Some(signature) = &self.signature {
+            header.extend([TAG_SIGNATURE, SIGNATURE_LEN as u8]);
+            header.extend(signature);
+        }
+        if let Some(kdf) = &self.kdf {
+            header.extend([TAG_KDF, 12]);
+            header.extend(kdf.mem_cost.to_le_bytes());
+            header.extend(kdf.time_cost.to_le_bytes());
+            header.extend(kdf.lanes.to_le_bytes());
+        }
+        header.push(TAG_END);
+        header
+    }
+
+    /// Header of `data` and the encrypted data after it
+    pub fn decode(data: &[u8]) -> Option<(Self, &[u8])> {
+        let mut rest = data.strip_prefix(MAGIC.as_slice())?;
+        let mut header = Self::default();
+        loop {
+            let (&tag, tail) = rest.split_first()?;
+            if tag == TAG_END {
+                return Some((header, tail));
+            }
+            let (&len, tail) = tail.split_first()?;
+            let (value, tail) = tail.split_at_checked(len as usize)?;
+            match tag {
+                TAG_CIPHER => header.cipher = *value.first()?,
+                TAG_SALT => header.salt = Some(value.try_into().ok()?),
+                TAG_KEY_ID => header.key_id = Some(u32::from_le_bytes(value.try_into().ok()?)),
+                TAG_GAME_VERSION => header.game_version = Some(String::from_utf8_lossy(value).into_owned()),
+                TAG_CHUNK_SIZE => header.chunk_size = Some(u32::from_le_bytes(value.try_into().ok()?)),
+                TAG_SIGNATURE => header.signature = Some(value.try_into().ok()?),
+                TAG_KDF => {
+                    let field = |i: usize| value.get(i * 4..i * 4 + 4)?.try_into().ok().map(u32::from_le_bytes);
+                    header.kdf = Some(KdfParams {
+                        mem_cost: field(0)?,
+                        time_cost: field(1)?,
+                        lanes: field(2)?,
+                    });
+                }
+                _ => {}
+            }
+            rest = tail;
+        }
+    }
+
+    /// Read the header at the start of `reader`, also returning the bytes read.
+    /// Stops as soon as the data can't be a header, so the bytes read are the whole file up to there.
+    pub fn read_from(reader: &mut dyn Read) -> io::Result<(Option<Self>, Vec<u8>)> {
+        let mut read = Vec::new();
+        let mut magic = [0; MAGIC.len()];
+        let n = (&mut *reader).take(MAGIC.len() as u64).read_to_end(&mut read)?;
+        magic[..n].copy_from_slice(&read);
+        if magic != *MAGIC {
+            return Ok((None, read));
+        }
+        loop {
+            let start = read.len();
+            (&mut *reader).take(1).read_to_end(&mut read)?;
+            match read.get(start) {
+                Some(&TAG_END) => return Ok((Self::decode(&read).map(|(header, _)| header), read)),
+                Some(_) => {}
+                None => return Ok((None, read)),
+            }
+            (&mut *reader).take(1).read_to_end(&mut read)?;
+            let Some(&len) = read.get(start + 1) else {
+                return Ok((None, read));
+            };
+            if (&mut *reader).take(len as u64).read_to_end(&mut read)? < len as usize {
+                return Ok((None, read));
+            }
+        }
+    }
+}
+
+/// Encryption key or password, wiped from memory when dropped
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKey(Zeroizing<String>);
+
+impl SecretKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(Zeroizing::new(key.into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    /// The key in clear, e.g. to store it. Copies of it aren't wiped.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SecretKey {
+    fn from(key: &str) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<String> for SecretKey {
+    fn from(key: String) -> Self {
+        Self::new(key)
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// Fingerprint of `key` recorded in the [`SaveHeader`], to tell which key a save needs without trying it
+pub fn key_id(key: &SecretKey) -> u32 {
+    // FNV-1a, stable across Rust versions unlike the std hasher
+    key.as_bytes().iter().fold(0x811c9dc5, |hash: u32, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Passphrase chosen by the player, from which the key of slot saves is derived with Argon2id.
+/// Without it, saves are encrypted with [`EncryptSave::ENCR_KEY`](crate::save::EncryptSave::ENCR_KEY).
+#[derive(Resource, Clone, Default)]
+pub struct SavePassword(Option<SecretKey>);
+
+impl SavePassword {
+    pub fn get(&self) -> Option<&SecretKey> {
+        self.0.as_ref()
+    }
+}
+
+/// Key of slot saves, replacing [`EncryptSave::ENCR_KEY`](crate::save::EncryptSave::ENCR_KEY) when set,
+/// e.g. by [`EncryptSavePlugin::with_keyring`](crate::save::EncryptSavePlugin::with_keyring)
+#[derive(Resource, Clone, Default)]
+pub struct SaveKey(Option<SecretKey>);
+
+impl SaveKey {
+    pub fn new(key: impl Into<SecretKey>) -> Self {
+        Self(Some(key.into()))
+    }
+
+    pub fn get(&self) -> Option<&SecretKey> {
+        self.0.as_ref()
+    }
+}
+
+/// Protect the next saves with a password, or remove it with `None`.
+/// Slots saved with another password can't be loaded until it is set back.
+#[derive(Message)]
+pub struct SetSavePassword(pub Option<SecretKey>);
+
+pub(crate) fn set_password(mut set_password: MessageReader<SetSavePassword>, mut password: ResMut<SavePassword>) {
+    for SetSavePassword(new_password) in set_password.read() {
+        password.0 = new_password.clone();
+    }
+}
+
+fn derive_key(password: &SecretKey, salt: &[u8], kdf: KdfParams) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        version: argon2::Version::Version13,
+        hash_length: 32,
+        mem_cost: kdf.mem_cost,
+        time_cost: kdf.time_cost,
+        lanes: kdf.lanes,
+        ..argon2::Config::default()
+    };
+    argon2::hash_raw(password.as_bytes(), salt, &config)
+        .map(Zeroizing::new)
+        .map_err(|e| SaveError::Encrypt(e.into()))
+}
+
+/// Salt of a new key derived from a password, from the randomness of the OS
+fn new_salt() -> Result<[u8; SALT_LEN], SaveError> {
+    let mut salt = [0; SALT_LEN];
+    getrandom::fill(&mut salt).map_err(|e| SaveError::Encrypt(e.into()))?;
+    Ok(salt)
+}
+
+/// Encrypt `data` with `cipher` and prepend the [`SaveHeader`].
+/// The key is derived from `password` if there is one, otherwise `key` is used.
+pub fn seal(
+    cipher: &dyn Cipher,
+    data: &[u8],
+    key: &SecretKey,
+    password: Option<&SecretKey>,
+    game_version: Option<&str>,
+) -> Result<Vec<u8>, SaveError> {
+    let mut header = SaveHeader {
+        cipher: cipher.id(),
+        game_version: game_version.map(str::to_string),
+        ..SaveHeader::default()
+    };
+    let plain = cipher.id() == PLAIN_CIPHER;
+    let encrypted = match password.filter(|_| !plain) {
+        Some(password) => {
+            let salt = new_salt()?;
+            header.salt = Some(salt);
+            header.kdf = Some(KdfParams::DEFAULT);
+            cipher.encrypt(data, &derive_key(password, &salt, KdfParams::DEFAULT)?)?
+        }
+        None => cipher.encrypt(data, key.as_bytes())?,
+    };
+    if password.is_none() && !plain {
+        header.key_id = Some(key_id(key));
+    }
+    Ok([header.encode(), encrypted].concat())
+}
+
+/// Decrypt `data` with the cipher named in its header, `cipher` is tried first for custom ones.
+/// Data of [`PlainCipher`] is refused unless `allow_plain`, for the slots saved as plain.
+pub fn open(
+    cipher: &dyn Cipher,
+    data: &[u8],
+    key: &SecretKey,
+    password: Option<&SecretKey>,
+    allow_plain: bool,
+) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    let result = if let Some((header, encrypted)) = SaveHeader::decode(data) {
+        header_key(&header, key, password, allow_plain).and_then(|key| match header.chunk_size {
+            Some(chunk_size) => {
+                let mut chunks = ChunkReader::new(cipher, header.cipher, key, chunk_size, u64::MAX, encrypted);
+                let mut data = Vec::new();
+                let result = chunks.read_to_end(&mut data);
+                chunks.check(result).map(|_| data)
+            }
+            None => decrypt_with(cipher, header.cipher, encrypted, &key),
+        })
+    } else {
+        return LegacyCipher.decrypt(data, key.as_bytes()).map(Zeroizing::new);
+    };
+    // A legacy save could start with the magic by chance
+    result
+        .or_else(|e| LegacyCipher.decrypt(data, key.as_bytes()).map_err(|_| e))
+        .map(Zeroizing::new)
+}
+
+/// Key of a save with `header`, derived from `password` if the save has one
+fn header_key(
+    header: &SaveHeader,
+    key: &SecretKey,
+    password: Option<&SecretKey>,
+    allow_plain: bool,
+) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    check_plain(header.cipher, allow_plain)?;
+    match (header.salt, password) {
+        _ if header.cipher == PLAIN_CIPHER => Ok(Zeroizing::new(Vec::new())),
+        (Some(salt), Some(password)) => match header.kdf {
+            Some(kdf) => derive_key(password, &salt, kdf),
+            None => Err(SaveError::Corrupted("Missing key derivation cost".to_string())),
+        },
+        (Some(_), None) => Err(SaveError::PasswordRequired),
+        (None, _) if header.key_id.is_some_and(|id| id != key_id(key)) => Err(SaveError::WrongKey),
+        (None, _) => Ok(Zeroizing::new(key.as_bytes().to_vec())),
+    }
+}
+
+/// Encrypt the data written by `write` into `out` in chunks of `chunk_size` bytes, after the [`SaveHeader`].
+/// Only one chunk is held in memory. Returns the number of bytes written to `out`.
+/// Refuses [`LegacyCipher`], which would derive a key for every chunk.
+pub fn seal_stream(
+    cipher: &dyn Cipher,
+    key: &SecretKey,
+    password: Option<&SecretKey>,
+    game_version: Option<&str>,
+    chunk_size: u32,
+    out: &mut dyn Write,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), SaveError>,
+) -> Result<u64, SaveError> {
+    if cipher.id() == LegacyCipher.id() {
+        return Err(SaveError::Invalid("The legacy cipher can't stream saves".to_string()));
+    }
+    let mut header = SaveHeader {
+        cipher: cipher.id(),
+        game_version: game_version.map(str::to_string),
+        chunk_size: Some(chunk_size.max(1)),
+        ..SaveHeader::default()
+    };
+    let key = match password {
+        _ if header.cipher == PLAIN_CIPHER => Zeroizing::new(Vec::new()),
+        Some(password) => {
+            let salt = new_salt()?;
+            header.salt = Some(salt);
+            header.kdf = Some(KdfParams::DEFAULT);
+            derive_key(password, &salt, KdfParams::DEFAULT)?
+        }
+        None => {
+            header.key_id = Some(key_id(key));
+            Zeroizing::new(key.as_bytes().to_vec())
+        }
+    };
+    let header = header.encode();
+    out.write_all(&header)?;
+
+    let mut chunks = ChunkWriter {
+        out,
+        cipher,
+        key,
+        chunk_size: chunk_size.max(1) as usize,
+        buffer: Zeroizing::new(Vec::new()),
+        index: 0,
+        written: header.len() as u64,
+    };
+    write(&mut chunks)?;
+    chunks.seal_chunk(true)?;
+    Ok(chunks.written)
+}
+
+/// Decrypt the streamed save in `input` chunk by chunk, `header` is the one read from its start.
+/// Reading fails past `max_size` bytes of plain data. Data of [`PlainCipher`] is refused unless `allow_plain`.
+pub fn open_stream<'a>(
+    cipher: &'a dyn Cipher,
+    header: &SaveHeader,
+    input: impl Read + 'a,
+    key: &SecretKey,
+    password: Option<&SecretKey>,
+    allow_plain: bool,
+    max_size: u64,
+) -> Result<ChunkReader<'a>, SaveError> {
+    let chunk_size = header
+        .chunk_size
+        .ok_or_else(|| SaveError::Corrupted("Not a streamed save".to_string()))?;
+    let key = header_key(header, key, password, allow_plain)?;
+    Ok(ChunkReader::new(
+        cipher,
+        header.cipher,
+        key,
+        chunk_size,
+        max_size,
+        input,
+    ))
+}
+
+/// Encrypts each `chunk_size` bytes written to it as a separate chunk
+struct ChunkWriter<'a> {
+    out: &'a mut dyn Write,
+    cipher: &'a dyn Cipher,
+    key: Zeroizing<Vec<u8>>,
+    chunk_size: usize,
+    buffer: Zeroizing<Vec<u8>>,
+    index: u32,
+    written: u64,
+}
+
+impl ChunkWriter<'_> {
+    /// Chunks hold their index and whether they are the last one, so they can't be reordered or cut off
+    fn seal_chunk(&mut self, last: bool) -> Result<(), SaveError> {
+        let mut chunk = Zeroizing::new(Vec::with_capacity(CHUNK_PREFIX_LEN + self.buffer.len()));
+        chunk.extend(self.index.to_le_bytes());
+        chunk.push(last as u8);
+        chunk.extend(self.buffer.iter());
+        let encrypted = self.cipher.encrypt(&chunk, &self.key)?;
+        self.out.write_all(&(encrypted.len() as u32).to_le_bytes())?;
+        self.out.write_all(&encrypted)?;
+        self.written += 4 + encrypted.len() as u64;
+        self.buffer.clear();
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl Write for ChunkWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend(&buf[..n]);
+        if self.buffer.len() == self.chunk_size {
+            self.seal_chunk(false).map_err(io::Error::other)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Plain data of a streamed save, decrypted one chunk at a time
+pub struct ChunkReader<'a> {
+    input: Box<dyn Read + 'a>,
+    cipher: &'a dyn Cipher,
+    cipher_id: u8,
+    key: Zeroizing<Vec<u8>>,
+    chunk_size: u32,
+    /// Fails with [`SaveError::TooLarge`] past this many bytes of plain data
+    max_size: u64,
+    chunk: Zeroizing<Vec<u8>>,
+    position: usize,
+    index: u32,
+    total: u64,
+    done: bool,
+    /// First failure, reported by [`ChunkReader::check`]
+    error: Option<SaveError>,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn new(
+        cipher: &'a dyn Cipher,
+        cipher_id: u8,
+        key: Zeroizing<Vec<u8>>,
+        chunk_size: u32,
+        max_size: u64,
+        input: impl Read + 'a,
+    ) -> Self {
+        Self {
+            input: Box::new(input),
+            cipher,
+            cipher_id,
+            key,
+            chunk_size,
+            max_size,
+            chunk: Zeroizing::new(Vec::new()),
+            position: 0,
+            index: 0,
+            total: 0,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// The error that made reading fail, in place of the IO error returned to the reader
+    pub fn check<T>(&mut self, result: io::Result<T>) -> Result<T, SaveError> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(result?),
+        }
+    }
+
+    fn next_chunk(&mut self) -> Result<(), SaveError> {
+        let corrupted = |reason: &str| SaveError::Corrupted(reason.to_string());
+        let mut len = [0; 4];
+        self.input
+            .read_exact(&mut len)
+            .map_err(|_| corrupted("Missing chunk"))?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > self.chunk_size as usize + CHUNK_PREFIX_LEN + CHUNK_OVERHEAD {
+            return Err(corrupted("Chunk larger than the chunk size"));
+        }
+        let mut encrypted = vec![0; len];
+        self.input
+            .read_exact(&mut encrypted)
+            .map_err(|_| corrupted("Truncated chunk"))?;
+        let chunk = Zeroizing::new(decrypt_with(self.cipher, self.cipher_id, &encrypted, &self.key)?);
+        let (prefix, data) = chunk
+            .split_at_checked(CHUNK_PREFIX_LEN)
+            .ok_or_else(|| corrupted("Chunk without index"))?;
+        if u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) != self.index {
+            return Err(corrupted("Chunks out of order"));
+        }
+        self.total += data.len() as u64;
+        if self.total > self.max_size {
+            return Err(SaveError::TooLarge {
+                size: self.total,
+                limit: self.max_size,
+            });
+        }
+        self.done = prefix[4] != 0;
+        if self.done && self.input.read(&mut [0])? != 0 {
+            return Err(corrupted("Data after the last chunk"));
+        }
+        self.chunk = Zeroizing::new(data.to_vec());
+        self.position = 0;
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            if let Err(error) = self.next_chunk() {
+                let io_error = io::Error::new(io::ErrorKind::InvalidData, error.to_string());
+                self.error = Some(error);
+                return Err(io_error);
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Fail for data of [`PlainCipher`] unless `allow_plain`, so a save can't be rewritten without encryption
+fn check_plain(id: u8, allow_plain: bool) -> Result<(), SaveError> {
+    if id == PLAIN_CIPHER && !allow_plain {
+        return Err(SaveError::Corrupted(
+            "Unencrypted data in place of an encrypted save".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn decrypt_with(cipher: &dyn Cipher, id: u8, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+    match id {
+        _ if id == cipher.id() => cipher.decrypt(data, key),
+        0 => LegacyCipher.decrypt(data, key),
+        #[cfg(feature = "aes-gcm")]
+        1 => AesGcmCipher.decrypt(data, key),
+        #[cfg(feature = "chacha20poly1305")]
+        2 => ChaChaCipher.decrypt(data, key),
+        PLAIN_CIPHER => PlainCipher.decrypt(data, key),
+        _ => Err(SaveError::Decrypt(format!("Unknown cipher {}", id).into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"0123456789";
+
+    /// Header and length-prefixed chunks of `DATA` streamed in chunks of 4 bytes
+    fn streamed() -> (Vec<u8>, Vec<Vec<u8>>) {
+        let mut out = Vec::new();
+        seal_stream(
+            &PlainCipher,
+            &SecretKey::from("key"),
+            None,
+            None,
+            4,
+            &mut out,
+            |writer| Ok(writer.write_all(DATA)?),
+        )
+        .unwrap();
+        let (_, mut rest) = SaveHeader::decode(&out).unwrap();
+        let header = out[..out.len() - rest.len()].to_vec();
+        let mut chunks = Vec::new();
+        while !rest.is_empty() {
+            let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+            chunks.push(rest[..4 + len].to_vec());
+            rest = &rest[4 + len..];
+        }
+        (header, chunks)
+    }
+
+    fn open_chunks(header: &[u8], chunks: &[Vec<u8>]) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+        open(
+            &PlainCipher,
+            &[header.to_vec(), chunks.concat()].concat(),
+            &SecretKey::from("key"),
+            None,
+            true,
+        )
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let (header, chunks) = streamed();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(*open_chunks(&header, &chunks).unwrap(), DATA);
+    }
+
+    #[test]
+    fn truncated_stream_is_refused() {
+        let (header, chunks) = streamed();
+        assert!(matches!(
+            open_chunks(&header, &chunks[..2]),
+            Err(SaveError::Corrupted(_))
+        ));
+
+        let mut cut = chunks.concat();
+        cut.pop();
+        assert!(matches!(open_chunks(&header, &[cut]), Err(SaveError::Corrupted(_))));
+    }
+
+    #[test]
+    fn reordered_chunks_are_refused() {
+        let (header, mut chunks) = streamed();
+        chunks.swap(0, 1);
+        assert!(matches!(open_chunks(&header, &chunks), Err(SaveError::Corrupted(_))));
+    }
+
+    /// Xor with the first byte of the key, to test the dispatch without the cost of a real cipher
+    struct XorCipher;
+
+    impl Cipher for XorCipher {
+        fn id(&self) -> u8 {
+            42
+        }
+
+        fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+            Ok(data.iter().map(|byte| byte ^ key[0]).collect())
+        }
+
+        fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, SaveError> {
+            self.encrypt(data, key)
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = SaveHeader {
+            cipher: 2,
+            salt: Some([3; SALT_LEN]),
+            key_id: Some(4),
+            game_version: Some("1.2.3".to_string()),
+            chunk_size: Some(5),
+            signature: Some([6; SIGNATURE_LEN]),
+            kdf: Some(KdfParams::DEFAULT),
+        };
+        let file = [header.encode(), DATA.to_vec()].concat();
+        assert_eq!(SaveHeader::decode(&file), Some((header.clone(), DATA)));
+
+        let (read, bytes) = SaveHeader::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(read, Some(header));
+        assert_eq!(bytes, file[..file.len() - DATA.len()]);
+    }
+
+    #[test]
+    fn unknown_tags_are_skipped() {
+        let file = [
+            MAGIC.as_slice(),
+            &[TAG_CIPHER, 1, 2],
+            &[99, 3, 7, 8, 9],
+            &[TAG_END],
+            DATA,
+        ]
+        .concat();
+        let (header, data) = SaveHeader::decode(&file).unwrap();
+        assert_eq!(header.cipher, 2);
+        assert_eq!(data, DATA);
+        assert_eq!(SaveHeader::read_from(&mut file.as_slice()).unwrap().0, Some(header));
+    }
+
+    #[test]
+    fn truncated_header_is_not_a_header() {
+        let header = SaveHeader {
+            game_version: Some("1.0".to_string()),
+            ..SaveHeader::default()
+        }
+        .encode();
+        assert_eq!(SaveHeader::decode(&header[..header.len() - 3]), None);
+        assert_eq!(SaveHeader::read_from(&mut &header[..header.len() - 1]).unwrap().0, None);
+        assert_eq!(SaveHeader::decode(DATA), None);
+    }
+
+    #[test]
+    fn cipher_of_the_header_is_used() {
+        let key = SecretKey::from("key");
+        let sealed = seal(&XorCipher, DATA, &key, None, None).unwrap();
+        assert_eq!(SaveHeader::decode(&sealed).unwrap().0.cipher, XorCipher.id());
+        assert_eq!(*open(&XorCipher, &sealed, &key, None, false).unwrap(), DATA);
+        // Unknown to the current cipher and to the built-in ones
+        assert!(matches!(
+            open(&PlainCipher, &sealed, &key, None, false),
+            Err(SaveError::Decrypt(_))
+        ));
+    }
+
+    #[test]
+    fn file_of_another_key_is_refused() {
+        let sealed = seal(&XorCipher, DATA, &SecretKey::from("key"), None, None).unwrap();
+        let result = open(&XorCipher, &sealed, &SecretKey::from("other"), None, false);
+        assert!(matches!(result, Err(SaveError::WrongKey)));
+    }
+
+    #[test]
+    fn legacy_cipher_records_the_key() {
+        let sealed = seal(&LegacyCipher, DATA, &SecretKey::from("key"), None, None).unwrap();
+        let (header, _) = SaveHeader::decode(&sealed).unwrap();
+        assert_eq!(header.key_id, Some(key_id(&SecretKey::from("key"))));
+        let result = open(&LegacyCipher, &sealed, &SecretKey::from("other"), None, false);
+        assert!(matches!(result, Err(SaveError::WrongKey)));
+    }
+
+    #[test]
+    fn password_saves_record_their_cost() {
+        let key = SecretKey::from("key");
+        let password = SecretKey::from("password");
+        let sealed = seal(&XorCipher, DATA, &key, Some(&password), None).unwrap();
+        assert_eq!(SaveHeader::decode(&sealed).unwrap().0.kdf, Some(KdfParams::DEFAULT));
+        assert_eq!(*open(&XorCipher, &sealed, &key, Some(&password), false).unwrap(), DATA);
+
+        let (mut header, encrypted) = SaveHeader::decode(&sealed).unwrap();
+        header.kdf = None;
+        let uncosted = [header.encode(), encrypted.to_vec()].concat();
+        assert!(matches!(
+            open(&XorCipher, &uncosted, &key, Some(&password), false),
+            Err(SaveError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn headerless_saves_are_read() {
+        let key = SecretKey::from("key");
+        let legacy = LegacyCipher.encrypt(DATA, key.as_bytes()).unwrap();
+        assert_eq!(*open(&XorCipher, &legacy, &key, None, false).unwrap(), DATA);
+    }
+
+    #[test]
+    fn legacy_cipher_is_not_streamed() {
+        let result = seal_stream(
+            &LegacyCipher,
+            &SecretKey::from("key"),
+            None,
+            None,
+            4,
+            &mut Vec::new(),
+            |_| Ok(()),
+        );
+        assert!(matches!(result, Err(SaveError::Invalid(_))));
+    }
+}
@@ -0,0 +1,167 @@
+//! Saves as base64 strings on the clipboard, to share seeds or loadouts and for support requests.
+//! Strings hold a compressed [archive](crate::archive), so they are only practical for small saves.
+use crate::archive::{
+    encode_archive,
+    ArchiveImporter,
+};
+use crate::backend::SaveStorage;
+use crate::delta::DeltaSaves;
+use crate::error::SaveError;
+use crate::io::PendingWrites;
+use crate::paths::SaveDirs;
+use crate::save::{
+    EncryptSave,
+    LoadLimits,
+    SaveConfig,
+    SaveOptions,
+};
+use arboard::Clipboard;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    Deref,
+    DerefMut,
+    Local,
+    Message,
+    MessageReader,
+    MessageWriter,
+    Res,
+    Resource,
+};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+
+/// Start of save strings
+const PREFIX: &str = "BSM1:";
+
+/// Put a slot on the clipboard as a save string
+#[derive(Message, Deref, DerefMut)]
+pub struct CopySaveToClipboard(pub u32);
+
+/// Add the save string on the clipboard as a new slot
+#[derive(Message)]
+pub struct PasteSaveFromClipboard;
+
+#[derive(Message, Deref, DerefMut)]
+pub struct SaveCopiedToClipboard(pub u32);
+
+/// Sent with the id of the new slot
+#[derive(Message, Deref, DerefMut)]
+pub struct SavePasted(pub u32);
+
+#[derive(Message, Debug)]
+pub struct ClipboardFailed(pub SaveError);
+
+/// Compress `archive` into a save string, with a CRC32 of the compressed data
+pub fn encode_save_string(archive: &[u8]) -> Result<String, SaveError> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(archive)?;
+    let compressed = encoder.finish()?;
+    let checksum = crc32fast::hash(&compressed).to_le_bytes();
+    Ok(format!(
+        "{}{}",
+        PREFIX,
+        STANDARD.encode([checksum.as_slice(), &compressed].concat())
+    ))
+}
+
+/// Archive in a save string written by [`encode_save_string`], failing if it is larger than `max_size` bytes
+pub fn decode_save_string(text: &str, max_size: u64) -> Result<Vec<u8>, SaveError> {
+    let corrupted = || SaveError::Corrupted("Not a save string".to_string());
+    let encoded = text.trim().strip_prefix(PREFIX).ok_or_else(corrupted)?;
+    let decoded = STANDARD.decode(encoded).map_err(|_| corrupted())?;
+    let (checksum, compressed) = decoded.split_first_chunk::<4>().ok_or_else(corrupted)?;
+    if crc32fast::hash(compressed) != u32::from_le_bytes(*checksum) {
+        return Err(SaveError::Corrupted("Checksum mismatch".to_string()));
+    }
+    let mut archive = Vec::new();
+    DeflateDecoder::new(compressed)
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut archive)?;
+    LoadLimits::check(archive.len() as u64, max_size)?;
+    Ok(archive)
+}
+
+/// Kept open, on Linux the copied text is gone once the clipboard is dropped
+#[derive(Default)]
+pub(crate) struct ClipboardHandle(Option<Clipboard>);
+
+impl ClipboardHandle {
+    fn get(&mut self) -> Result<&mut Clipboard, SaveError> {
+        if self.0.is_none() {
+            self.0 = Some(Clipboard::new().map_err(io::Error::other)?);
+        }
+        Ok(self.0.as_mut().expect("clipboard was just opened"))
+    }
+}
+
+pub(crate) fn on_copy_to_clipboard<T>(
+    mut copies: MessageReader<CopySaveToClipboard>,
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    storage: Res<SaveStorage>,
+    writes: Res<PendingWrites>,
+    delta_saves: DeltaSaves,
+    mut clipboard: Local<ClipboardHandle>,
+    mut copied: MessageWriter<SaveCopiedToClipboard>,
+    mut failed: MessageWriter<ClipboardFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    // Files still being written would be copied half done
+    writes.flush();
+
+    let flattener = delta_saves.flattener::<T>();
+    for id in copies.read() {
+        let result = encode_archive(&save_config, &dirs, &storage, &flattener, **id)
+            .and_then(|archive| encode_save_string(&archive))
+            .and_then(|text| Ok(clipboard.get()?.set_text(text).map_err(io::Error::other)?));
+        match result {
+            Ok(()) => {
+                copied.write(SaveCopiedToClipboard(**id));
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to copy slot {} to the clipboard: {}", **id, error);
+                failed.write(ClipboardFailed(error));
+            }
+        }
+    }
+}
+
+pub(crate) fn on_paste_from_clipboard<T>(
+    mut pastes: MessageReader<PasteSaveFromClipboard>,
+    mut importer: ArchiveImporter,
+    mut clipboard: Local<ClipboardHandle>,
+    options: Res<SaveOptions>,
+    mut pasted: MessageWriter<SavePasted>,
+    mut failed: MessageWriter<ClipboardFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    for _ in pastes.read() {
+        let result = clipboard
+            .get()
+            .and_then(|clipboard| Ok(clipboard.get_text().map_err(io::Error::other)?))
+            .and_then(|text| decode_save_string(&text, options.load_limits.max_decompressed_size))
+            .and_then(|archive| importer.import::<T>(&archive));
+        match result {
+            Ok(id) => {
+                pasted.write(SavePasted(id));
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to import the save on the clipboard: {}", error);
+                failed.write(ClipboardFailed(error));
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Text commands to read and change settings at runtime, for the developer console of the game.
+//!
+//! Forward the lines typed in the console as [`SettingCommand`]s and print the [`SettingCommandOutput`]s:
+//!
+//! - `get audio` or `get audio.master`: values of a setting or of one of its fields
+//! - `set audio.master 0.5`: change a field, the value is written in RON
+//! - `settings list`: names of the settings
+//! - `settings save`: write every setting now
+//! - `settings reload`: read every setting from its files again
+use crate::options::set_from_ron;
+use crate::setting::{
+    load_config,
+    FlushGameSettings,
+    GameSetting,
+    GameSettingChanged,
+};
+use bevy::app::App;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::{
+    on_message,
+    IntoScheduleConfigs,
+    Message,
+    MessageReader,
+    Plugin,
+    Resource,
+    Update,
+    World,
+};
+use bevy::reflect::serde::TypedReflectSerializer;
+use bevy::reflect::{
+    GetTypeRegistration,
+    PartialReflect,
+    Struct,
+    TypeRegistry,
+};
+
+/// A line typed in the console
+#[derive(Message, Clone, Debug)]
+pub struct SettingCommand(pub String);
+
+/// Reply to a [`SettingCommand`], to print in the console
+#[derive(Message, Clone, Debug)]
+pub struct SettingCommandOutput {
+    pub command: String,
+    pub result: Result<String, String>,
+}
+
+/// Run [`SettingCommand`]s on the settings added with [`Self::with_setting`]
+#[derive(Default)]
+pub struct SettingConsolePlugin {
+    settings: Vec<SettingEntry>,
+}
+
+impl SettingConsolePlugin {
+    /// Make setting `T` available to commands as `name`. Its fields and their types must implement `Reflect`.
+    pub fn with_setting<T>(mut self, name: &'static str) -> Self
+    where
+        T: Resource + GameSetting + Struct + GetTypeRegistration,
+    {
+        self.settings.push(SettingEntry {
+            name,
+            get: get_field::<T>,
+            set: set_field::<T>,
+            reload: reload::<T>,
+        });
+        self
+    }
+}
+
+impl Plugin for SettingConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SettingCommands(self.settings.clone()))
+            .add_message::<SettingCommand>()
+            .add_message::<SettingCommandOutput>()
+            .add_message::<GameSettingChanged>()
+            .add_message::<FlushGameSettings>()
+            .add_systems(Update, run_setting_commands.run_if(on_message::<SettingCommand>));
+    }
+}
+
+#[derive(Clone)]
+struct SettingEntry {
+    name: &'static str,
+    /// Values of the field, or of every field with `None`
+    get: fn(&World, Option<&str>) -> Result<String, String>,
+    /// Set the field to a value in RON
+    set: fn(&mut World, &str, &str) -> Result<String, String>,
+    reload: fn(&mut World) -> Result<(), String>,
+}
+
+#[derive(Resource)]
+struct SettingCommands(Vec<SettingEntry>);
+
+fn run_setting_commands(world: &mut World, commands: &mut SystemState<MessageReader<SettingCommand>>) {
+    let lines: Vec<String> = commands
+        .get_mut(world)
+        .read()
+        .map(|command| command.0.clone())
+        .collect();
+    let settings = world.resource::<SettingCommands>().0.clone();
+    for line in lines {
+        let result = run_command(world, &settings, &line);
+        world.write_message(SettingCommandOutput { command: line, result });
+    }
+}
+
+fn run_command(world: &mut World, settings: &[SettingEntry], line: &str) -> Result<String, String> {
+    let line = line.trim();
+    let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let args = args.trim();
+    match command {
+        "get" => {
+            let (name, field) = match args.split_once('.') {
+                Some((name, field)) => (name, Some(field)),
+                None => (args, None),
+            };
+            (find_setting(settings, name)?.get)(world, field)
+        }
+        "set" => {
+            let Some((path, value)) = args.split_once(char::is_whitespace) else {
+                return Err("usage: set <setting>.<field> <value>".to_string());
+            };
+            let Some((name, field)) = path.split_once('.') else {
+                return Err(format!("{} is not a field, expected <setting>.<field>", path));
+            };
+            (find_setting(settings, name)?.set)(world, field, value.trim())
+        }
+        "settings" => match args {
+            "list" => Ok(settings
+                .iter()
+                .map(|setting| setting.name)
+                .collect::<Vec<_>>()
+                .join(", ")),
+            "save" => {
+                world.write_message(GameSettingChanged);
+                world.write_message(FlushGameSettings);
+                Ok("Writing settings".to_string())
+            }
+            "reload" => {
+                for setting in settings {
+                    (setting.reload)(world).map_err(|e| format!("Failed to reload {}: {}", setting.name, e))?;
+                }
+                Ok("Settings reloaded".to_string())
+            }
+            _ => Err("usage: settings list|save|reload".to_string()),
+        },
+        _ => Err(format!("Unknown command {}, expected get, set or settings", command)),
+    }
+}
+
+fn find_setting<'a>(settings: &'a [SettingEntry], name: &str) -> Result<&'a SettingEntry, String> {
+    settings
+        .iter()
+        .find(|setting| setting.name == name)
+        .ok_or_else(|| format!("Unknown setting {}", name))
+}
+
+/// Registry of `T` and the types of its fields
+fn type_registry<T>() -> TypeRegistry
+where
+    T: GetTypeRegistration,
+{
+    let mut registry = TypeRegistry::new();
+    registry.register::<T>();
+    registry
+}
+
+fn format_value(value: &dyn PartialReflect, registry: &TypeRegistry) -> Result<String, String> {
+    ron::to_string(&TypedReflectSerializer::new(value, registry)).map_err(|e| e.to_string())
+}
+
+fn get_field<T>(world: &World, field: Option<&str>) -> Result<String, String>
+where
+    T: Resource + Struct + GetTypeRegistration,
+{
+    let registry = type_registry::<T>();
+    let setting = world
+        .get_resource::<T>()
+        .ok_or_else(|| "Setting is not loaded".to_string())?;
+    if let Some(field) = field {
+        let value = setting.field(field).ok_or_else(|| format!("Unknown field {}", field))?;
+        return format_value(value, &registry);
+    }
+    let mut lines = Vec::new();
+    for (index, value) in setting.iter_fields().enumerate() {
+        let name = setting.name_at(index).unwrap_or_default();
+        lines.push(format!("{} = {}", name, format_value(value, &registry)?));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn set_field<T>(world: &mut World, field: &str, value: &str) -> Result<String, String>
+where
+    T: Resource + GameSetting + Struct + GetTypeRegistration,
+{
+    let registry = type_registry::<T>();
+    let mut setting = world
+        .get_resource_mut::<T>()
+        .ok_or_else(|| "Setting is not loaded".to_string())?;
+    let target = setting
+        .field_mut(field)
+        .ok_or_else(|| format!("Unknown field {}", field))?;
+    set_from_ron(target, value, &registry)?;
+
+    let result = match setting.validate() {
+        Ok(()) => Ok(format!("{} = {}", field, value)),
+        Err(issues) => Err(issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")),
+    };
+    world.write_message(GameSettingChanged);
+    result
+}
+
+fn reload<T>(world: &mut World) -> Result<(), String>
+where
+    T: Resource + GameSetting,
+{
+    world.run_system_cached(load_config::<T>).map_err(|e| e.to_string())
+}
@@ -0,0 +1,129 @@
+use crate::save::{
+    CopySave,
+    CurrentSave,
+    DeleteSave,
+    EncryptSave,
+    LoadGame,
+    QuickSave,
+    SaveConfig,
+    SaveGame,
+};
+use bevy::app::App;
+use bevy::prelude::{
+    DetectChanges,
+    Local,
+    MessageWriter,
+    Plugin,
+    Res,
+    Resource,
+    Result,
+};
+use bevy_egui::{
+    egui,
+    EguiContexts,
+    EguiPrimaryContextPass,
+};
+use std::marker::PhantomData;
+
+/// Debug window listing the save slots, with buttons to save, load, delete and copy them,
+/// and a live JSON view of the saved resource `T`. Requires `bevy_egui::EguiPlugin`.
+pub struct SaveBrowserPlugin<T> {
+    _data: PhantomData<T>,
+}
+
+impl<T> Default for SaveBrowserPlugin<T> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+impl<T> Plugin for SaveBrowserPlugin<T>
+where
+    T: Resource + EncryptSave,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(EguiPrimaryContextPass, save_browser::<T>);
+    }
+}
+
+/// JSON of `T`, only serialized again when `T` changes
+#[derive(Default)]
+struct JsonCache(String);
+
+#[allow(clippy::too_many_arguments)]
+fn save_browser<T>(
+    mut contexts: EguiContexts,
+    save_config: Res<SaveConfig>,
+    current_save: Res<CurrentSave>,
+    data: Res<T>,
+    mut json: Local<JsonCache>,
+    mut quick_save: MessageWriter<QuickSave>,
+    mut save: MessageWriter<SaveGame>,
+    mut load: MessageWriter<LoadGame>,
+    mut delete: MessageWriter<DeleteSave>,
+    mut copy: MessageWriter<CopySave>,
+) -> Result
+where
+    T: Resource + EncryptSave,
+{
+    if data.is_changed() || json.0.is_empty() {
+        json.0 = serde_json::to_string_pretty(&*data).unwrap_or_else(|e| e.to_string());
+    }
+
+    egui::Window::new("Saves").show(contexts.ctx_mut()?, |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("New save").clicked() {
+                save.write(SaveGame::new(0));
+            }
+            if ui.button("Quick save").clicked() {
+                quick_save.write(QuickSave);
+            }
+        });
+        ui.label(format!("Current slot: {}", current_save.0));
+
+        ui.separator();
+        egui::Grid::new("save_slots").striped(true).show(ui, |ui| {
+            for header in ["Id", "Name", "File", "Saved at", "Playtime", "Revision", ""] {
+                ui.strong(header);
+            }
+            ui.end_row();
+
+            for (id, slot) in save_config.sorted_by_recency() {
+                ui.label(id.to_string());
+                let name = ui.label(&slot.name);
+                if !slot.description.is_empty() {
+                    name.on_hover_text(&slot.description);
+                }
+                ui.label(slot.file.display().to_string());
+                ui.label(slot.saved_at.to_string());
+                ui.label(format!("{}s", slot.playtime.as_secs()));
+                ui.label(slot.revision.to_string());
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        load.write(LoadGame(id));
+                    }
+                    if ui.button("Save").clicked() {
+                        save.write(SaveGame::overwrite(id));
+                    }
+                    if ui.button("Copy").clicked() {
+                        copy.write(CopySave { from: id, to: 0 });
+                    }
+                    if ui.button("Delete").clicked() {
+                        delete.write(DeleteSave(id));
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        egui::CollapsingHeader::new(std::any::type_name::<T>())
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    ui.monospace(&json.0);
+                });
+            });
+    });
+    Ok(())
+}
@@ -0,0 +1,291 @@
+//! Binary diffs of save data, written by delta autosaves, see [`EncryptSavePlugin::with_delta_autosaves`](crate::save::EncryptSavePlugin::with_delta_autosaves)
+use crate::backend::SaveStorage;
+use crate::cipher::{
+    open,
+    seal,
+    Cipher,
+    PlainCipher,
+    SaveCipher,
+    SaveHeader,
+    SaveKey,
+    SavePassword,
+    SecretKey,
+};
+use crate::error::SaveError;
+use crate::save::{
+    save_key,
+    EncryptSave,
+    LoadLimits,
+    SaveEncoding,
+    SaveOptions,
+    SaveSlot,
+};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::Res;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Size of the base blocks looked up in the target
+const BLOCK: usize = 32;
+/// Multiplier of the rolling hash
+const PRIME: u32 = 16777619;
+
+#[derive(Serialize, Deserialize)]
+struct Delta {
+    base_len: u64,
+    /// FNV-1a of the base, to refuse a delta applied on another base
+    base_hash: u64,
+    ops: Vec<DeltaOp>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum DeltaOp {
+    /// Bytes of the base
+    Copy { offset: u64, len: u64 },
+    /// Bytes which aren't in the base
+    Insert(Vec<u8>),
+}
+
+/// Encoded diff turning `base` into `target`
+pub(crate) fn diff(base: &[u8], target: &[u8]) -> Result<Vec<u8>, SaveError> {
+    let mut index = HashMap::new();
+    for offset in (0..base.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+        index.entry(block_hash(&base[offset..offset + BLOCK])).or_insert(offset);
+    }
+
+    let mut ops = Vec::new();
+    let mut literal = 0;
+    let mut i = 0;
+    let high = PRIME.wrapping_pow(BLOCK as u32 - 1);
+    let mut hash = target.get(..BLOCK).filter(|_| !index.is_empty()).map(block_hash);
+    while let Some(h) = hash {
+        let found = index
+            .get(&h)
+            .copied()
+            .filter(|offset| base[*offset..*offset + BLOCK] == target[i..i + BLOCK]);
+        if let Some(offset) = found {
+            // Grow the match both ways, into the pending literal and past the block
+            let (mut start, mut from) = (i, offset);
+            while start > literal && from > 0 && base[from - 1] == target[start - 1] {
+                start -= 1;
+                from -= 1;
+            }
+            let (mut end, mut to) = (i + BLOCK, offset + BLOCK);
+            while end < target.len() && to < base.len() && base[to] == target[end] {
+                end += 1;
+                to += 1;
+            }
+            if start > literal {
+                ops.push(DeltaOp::Insert(target[literal..start].to_vec()));
+            }
+            ops.push(DeltaOp::Copy {
+                offset: from as u64,
+                len: (end - start) as u64,
+            });
+            literal = end;
+            i = end;
+            hash = target.get(i..i + BLOCK).map(block_hash);
+        } else if i + BLOCK < target.len() {
+            let rolled = h.wrapping_sub(u32::from(target[i]).wrapping_mul(high));
+            hash = Some(rolled.wrapping_mul(PRIME).wrapping_add(u32::from(target[i + BLOCK])));
+            i += 1;
+        } else {
+            hash = None;
+        }
+    }
+    if literal < target.len() {
+        ops.push(DeltaOp::Insert(target[literal..].to_vec()));
+    }
+
+    SaveEncoding::Legacy.encode(&Delta {
+        base_len: base.len() as u64,
+        base_hash: fnv1a(base),
+        ops,
+    })
+}
+
+/// Apply a diff written by [`diff`] on `base`, failing if the result is larger than `max_size` bytes
+pub(crate) fn patch(base: &[u8], delta: &[u8], max_size: u64) -> Result<Vec<u8>, SaveError> {
+    let delta: Delta = SaveEncoding::Legacy.decode(delta)?;
+    if delta.base_len != base.len() as u64 || delta.base_hash != fnv1a(base) {
+        return Err(SaveError::Corrupted("Delta save doesn't match its base".to_string()));
+    }
+
+    let mut target = Vec::new();
+    for op in delta.ops {
+        let bytes = match &op {
+            DeltaOp::Copy { offset, len } => offset
+                .checked_add(*len)
+                .filter(|end| *end <= base.len() as u64)
+                .map(|end| &base[*offset as usize..end as usize])
+                .ok_or_else(|| SaveError::Corrupted("Delta copies past its base".to_string()))?,
+            DeltaOp::Insert(bytes) => bytes.as_slice(),
+        };
+        LoadLimits::check((target.len() + bytes.len()) as u64, max_size)?;
+        target.extend_from_slice(bytes);
+    }
+    Ok(target)
+}
+
+fn block_hash(block: &[u8]) -> u32 {
+    block.iter().fold(0u32, |hash, byte| {
+        hash.wrapping_mul(PRIME).wrapping_add(u32::from(*byte))
+    })
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Resources to build a [`DeltaFlattener`] from
+#[derive(SystemParam)]
+pub(crate) struct DeltaSaves<'w> {
+    cipher: Res<'w, SaveCipher>,
+    key: Res<'w, SaveKey>,
+    password: Res<'w, SavePassword>,
+    options: Res<'w, SaveOptions>,
+    #[cfg(feature = "signing")]
+    signing: Option<Res<'w, crate::signing::SigningKey>>,
+}
+
+impl DeltaSaves<'_> {
+    pub fn flattener<T>(&self) -> DeltaFlattener
+    where
+        T: EncryptSave,
+    {
+        DeltaFlattener {
+            cipher: self.cipher.0.clone(),
+            key: save_key::<T>(&self.key),
+            password: self.password.get().cloned(),
+            limits: self.options.load_limits,
+            #[cfg(feature = "signing")]
+            signing: self.signing.as_deref().cloned(),
+        }
+    }
+}
+
+/// Turns delta slots back into full saves for the copies leaving this machine, which don't come with the base
+#[derive(Clone)]
+pub(crate) struct DeltaFlattener {
+    cipher: Arc<dyn Cipher>,
+    key: SecretKey,
+    password: Option<SecretKey>,
+    limits: LoadLimits,
+    #[cfg(feature = "signing")]
+    signing: Option<crate::signing::SigningKey>,
+}
+
+impl DeltaFlattener {
+    /// File of `slot` as a full save: the file itself, or the delta applied on its base and encrypted again
+    pub fn full_save(&self, storage: &SaveStorage, save_dir: &Path, slot: &SaveSlot) -> Result<Vec<u8>, SaveError> {
+        let Some(base) = &slot.base else {
+            return Ok(storage.read(&save_dir.join(&slot.file))?);
+        };
+        let (_, base) = self.read(storage, &save_dir.join(base), slot.plain)?;
+        let (game_version, delta) = self.read(storage, &save_dir.join(&slot.file), slot.plain)?;
+        let data = Zeroizing::new(patch(&base, &delta, self.limits.max_allocation)?);
+        let cipher: &dyn Cipher = if slot.plain { &PlainCipher } else { self.cipher.as_ref() };
+        let sealed = seal(
+            cipher,
+            &data,
+            &self.key,
+            self.password.as_ref(),
+            game_version.as_deref(),
+        )?;
+        #[cfg(feature = "signing")]
+        let sealed = crate::signing::sign_with(self.signing.as_ref(), sealed);
+        Ok(sealed)
+    }
+
+    /// Decrypted file at `path` and the game version in its header, checked like a load
+    fn read(
+        &self,
+        storage: &SaveStorage,
+        path: &Path,
+        plain: bool,
+    ) -> Result<(Option<String>, Zeroizing<Vec<u8>>), SaveError> {
+        LoadLimits::check(storage.size(path)?, self.limits.max_file_size)?;
+        let data = storage.read(path)?;
+        #[cfg(feature = "signing")]
+        crate::signing::verify_with(self.signing.as_ref(), &data)?;
+        let game_version = SaveHeader::decode(&data).and_then(|(header, _)| header.game_version);
+        let decrypted = open(self.cipher.as_ref(), &data, &self.key, self.password.as_ref(), plain)?;
+        LoadLimits::check(decrypted.len() as u64, self.limits.max_allocation)?;
+        Ok((game_version, decrypted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Vec<u8> {
+        (0..4096u32).flat_map(|i| (i * 7919).to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn patch_rebuilds_the_target() {
+        let base = base();
+        let mut target = base.clone();
+        target[100..110].fill(0xAA);
+        target.splice(2000..2000, *b"inserted");
+        target.truncate(12000);
+        target.extend(b"tail");
+
+        let delta = diff(&base, &target).unwrap();
+        assert!(delta.len() < target.len() / 10);
+        assert_eq!(patch(&base, &delta, u64::MAX).unwrap(), target);
+    }
+
+    #[test]
+    fn diff_of_unrelated_data() {
+        for (base, target) in [(base(), b"short".to_vec()), (Vec::new(), base()), (base(), Vec::new())] {
+            let delta = diff(&base, &target).unwrap();
+            assert_eq!(patch(&base, &delta, u64::MAX).unwrap(), target);
+        }
+    }
+
+    #[test]
+    fn delta_of_another_base_is_refused() {
+        let base = base();
+        let delta = diff(&base, &base[10..]).unwrap();
+        let mut other = base.clone();
+        other[0] ^= 1;
+        assert!(matches!(patch(&other, &delta, u64::MAX), Err(SaveError::Corrupted(_))));
+        assert!(matches!(
+            patch(&base[1..], &delta, u64::MAX),
+            Err(SaveError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn copy_past_the_base_is_refused() {
+        let base = base();
+        for offset in [base.len() as u64 - 4, u64::MAX] {
+            let delta = SaveEncoding::Legacy
+                .encode(&Delta {
+                    base_len: base.len() as u64,
+                    base_hash: fnv1a(&base),
+                    ops: vec![DeltaOp::Copy { offset, len: 8 }],
+                })
+                .unwrap();
+            assert!(matches!(patch(&base, &delta, u64::MAX), Err(SaveError::Corrupted(_))));
+        }
+    }
+
+    #[test]
+    fn patch_stops_at_the_limit() {
+        let base = base();
+        let delta = diff(&base, &base).unwrap();
+        let result = patch(&base, &delta, base.len() as u64 - 1);
+        assert!(matches!(result, Err(SaveError::TooLarge { .. })));
+    }
+}
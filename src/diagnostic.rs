@@ -0,0 +1,178 @@
+//! Save sizes and durations, also reported as Bevy [`Diagnostic`]s by [`SaveDiagnosticsPlugin`]
+//! and to a [`SaveTelemetry`]
+use crate::backend::SaveStorage;
+use crate::error::SaveError;
+use crate::io::SaveFailed;
+use crate::paths::SaveDirs;
+use crate::save::{
+    LoadFailed,
+    LoadRecentFailed,
+    SaveConfig,
+    SaveSet,
+};
+use bevy::app::App;
+use bevy::diagnostic::{
+    Diagnostic,
+    DiagnosticPath,
+    Diagnostics,
+    RegisterDiagnostic,
+};
+use bevy::prelude::{
+    resource_changed,
+    IntoScheduleConfigs,
+    Local,
+    MessageReader,
+    Plugin,
+    Res,
+    ResMut,
+    Resource,
+    Update,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Kept up to date by [`EncryptSavePlugin`](crate::save::EncryptSavePlugin)
+#[derive(Resource, Clone, Default, Debug)]
+pub struct SaveStats {
+    /// Size of each slot file, in bytes. Filled for slots saved before startup by [`SaveDiagnosticsPlugin`].
+    pub slot_sizes: HashMap<u32, u64>,
+    /// Serialization and encryption of the last slot save, the write itself runs in the background
+    pub last_save_duration: Option<Duration>,
+    /// `None` after a background save, whose size is only known once written
+    pub last_save_size: Option<u64>,
+    /// Reading, decryption and deserialization of the last slot load
+    pub last_load_duration: Option<Duration>,
+    /// Slot saves since startup
+    pub saves: u64,
+    /// Slot loads since startup
+    pub loads: u64,
+}
+
+impl SaveStats {
+    /// Size of every known slot file, in bytes
+    pub fn total_size(&self) -> u64 {
+        self.slot_sizes.values().sum()
+    }
+
+    pub(crate) fn record_save(&mut self, id: u32, size: Option<u64>, duration: Duration) {
+        if let Some(size) = size {
+            self.slot_sizes.insert(id, size);
+        }
+        self.last_save_duration = Some(duration);
+        self.last_save_size = size;
+        self.saves += 1;
+    }
+
+    pub(crate) fn record_load(&mut self, duration: Duration) {
+        self.last_load_duration = Some(duration);
+        self.loads += 1;
+    }
+}
+
+/// Receives the outcome of slot saves and loads, e.g. to forward save health to an analytics backend.
+/// Set with [`EncryptSavePlugin::with_telemetry`](crate::save::EncryptSavePlugin::with_telemetry), calls are made
+/// from the systems of the plugin so they should return quickly.
+pub trait SaveTelemetry: Send + Sync + 'static {
+    /// `size` is the size of the file in bytes, `None` for background saves
+    fn on_save(&self, _slot: u32, _duration: Duration, _size: Option<u64>) {}
+
+    fn on_load(&self, _slot: u32, _duration: Duration) {}
+
+    /// `slot` is `None` for checkpoints and settings files
+    fn on_failure(&self, _operation: SaveOperation, _slot: Option<u32>, _error: &SaveError) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOperation {
+    Save,
+    Load,
+}
+
+#[derive(Resource, Clone)]
+pub(crate) struct Telemetry(pub Arc<dyn SaveTelemetry>);
+
+/// Register the [`SaveStats`] as diagnostics, to follow them in the diagnostics overlay or logs
+pub struct SaveDiagnosticsPlugin;
+
+impl SaveDiagnosticsPlugin {
+    pub const SAVE_TIME: DiagnosticPath = DiagnosticPath::const_new("save_manager/save_time");
+    pub const LOAD_TIME: DiagnosticPath = DiagnosticPath::const_new("save_manager/load_time");
+    pub const SAVE_SIZE: DiagnosticPath = DiagnosticPath::const_new("save_manager/save_size");
+    pub const TOTAL_SIZE: DiagnosticPath = DiagnosticPath::const_new("save_manager/total_size");
+}
+
+impl Plugin for SaveDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveStats>()
+            .register_diagnostic(Diagnostic::new(Self::SAVE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::LOAD_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::SAVE_SIZE).with_suffix(" B"))
+            .register_diagnostic(Diagnostic::new(Self::TOTAL_SIZE).with_suffix(" B"))
+            .add_systems(
+                Update,
+                (
+                    measure_slot_sizes.run_if(resource_changed::<SaveConfig>),
+                    add_measurements.run_if(resource_changed::<SaveStats>),
+                )
+                    .chain()
+                    .after(SaveSet::Write),
+            );
+    }
+}
+
+/// Forget deleted slots and read the size of slots which weren't saved since startup
+fn measure_slot_sizes(
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    storage: Res<SaveStorage>,
+    mut stats: ResMut<SaveStats>,
+) {
+    stats.slot_sizes.retain(|id, _| save_config.slot(*id).is_some());
+    for (id, slot) in save_config.slots() {
+        if !stats.slot_sizes.contains_key(id) {
+            if let Ok(size) = storage.size(&save_config.save_dir(&dirs).join(&slot.file)) {
+                stats.slot_sizes.insert(*id, size);
+            }
+        }
+    }
+}
+
+fn add_measurements(stats: Res<SaveStats>, mut measured: Local<(u64, u64)>, mut diagnostics: Diagnostics) {
+    if stats.saves != measured.0 {
+        if let Some(duration) = stats.last_save_duration {
+            diagnostics.add_measurement(&SaveDiagnosticsPlugin::SAVE_TIME, || duration.as_secs_f64() * 1000.0);
+        }
+        if let Some(size) = stats.last_save_size {
+            diagnostics.add_measurement(&SaveDiagnosticsPlugin::SAVE_SIZE, || size as f64);
+        }
+    }
+    if stats.loads != measured.1 {
+        if let Some(duration) = stats.last_load_duration {
+            diagnostics.add_measurement(&SaveDiagnosticsPlugin::LOAD_TIME, || duration.as_secs_f64() * 1000.0);
+        }
+    }
+    diagnostics.add_measurement(&SaveDiagnosticsPlugin::TOTAL_SIZE, || stats.total_size() as f64);
+    *measured = (stats.saves, stats.loads);
+}
+
+pub(crate) fn report_failures(
+    telemetry: Res<Telemetry>,
+    mut save_failures: MessageReader<SaveFailed>,
+    mut load_failures: MessageReader<LoadFailed>,
+    mut recent_failures: MessageReader<LoadRecentFailed>,
+) {
+    for failure in save_failures.read() {
+        telemetry
+            .0
+            .on_failure(SaveOperation::Save, failure.slot, &failure.error);
+    }
+    for failure in load_failures.read() {
+        telemetry
+            .0
+            .on_failure(SaveOperation::Load, Some(failure.slot), &failure.error);
+    }
+    for (slot, error) in recent_failures.read().flat_map(|failure| &failure.errors) {
+        telemetry.0.on_failure(SaveOperation::Load, Some(*slot), error);
+    }
+}
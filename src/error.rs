@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to serialize save data: {0}")]
+    Serialize(#[source] BoxedError),
+    #[error("Failed to deserialize save data: {0}")]
+    Deserialize(#[source] BoxedError),
+    #[error("Failed to encrypt save data: {0}")]
+    Encrypt(#[source] BoxedError),
+    #[error("Failed to decrypt save data: {0}")]
+    Decrypt(#[source] BoxedError),
+    #[error("Save is protected by a password")]
+    PasswordRequired,
+    #[error("Save is encrypted with another key")]
+    WrongKey,
+    #[error("Save was made by version {saved}, current version is {current}")]
+    VersionMismatch { saved: String, current: String },
+    #[error("Save slot {0} does not exist")]
+    NotFound(u32),
+    #[error("Save slot {0} is already used")]
+    SlotOccupied(u32),
+    #[error("Not enough disk space, {needed} bytes needed but {available} available")]
+    DiskFull { needed: u64, available: u64 },
+    #[error("Save quota exceeded, {needed} bytes needed but {available} available")]
+    QuotaExceeded { needed: u64, available: u64 },
+    #[error("Save data is too large, {size} bytes for a limit of {limit}")]
+    TooLarge { size: u64, limit: u64 },
+    #[error("Save of {size} bytes is over the budget of {limit} bytes per slot")]
+    SlotBudgetExceeded { size: u64, limit: u64 },
+    #[error("Every save slot id is taken")]
+    NoFreeSlot,
+    #[error("Resource {0} does not exist")]
+    MissingResource(&'static str),
+    #[error("Save data is corrupted: {0}")]
+    Corrupted(String),
+    #[error("Save data was rejected: {0}")]
+    Invalid(String),
+    #[error("Save signature is missing or does not match")]
+    InvalidSignature,
+}
+
+impl SaveError {
+    /// Bytes to free or trim for the save to fit, to tell the player, `None` if the error is not about space
+    pub fn missing_space(&self) -> Option<u64> {
+        match self {
+            Self::DiskFull { needed, available } | Self::QuotaExceeded { needed, available } => {
+                Some(needed.saturating_sub(*available))
+            }
+            Self::SlotBudgetExceeded { size, limit } => Some(size.saturating_sub(*limit)),
+            _ => None,
+        }
+    }
+}
+
+impl From<bincode::error::EncodeError> for SaveError {
+    fn from(e: bincode::error::EncodeError) -> Self {
+        Self::Serialize(e.into())
+    }
+}
+
+impl From<bincode::error::DecodeError> for SaveError {
+    fn from(e: bincode::error::DecodeError) -> Self {
+        Self::Deserialize(e.into())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Setting file {0} does not exist")]
+    NotFound(PathBuf),
+    #[error("Failed to serialize setting: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Failed to deserialize setting: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[error("Failed to convert setting: {0}")]
+    Format(#[source] BoxedError),
+    #[error("Setting was written by version {saved}, current version is {current}")]
+    VersionMismatch { saved: u32, current: u32 },
+    #[error("Failed to download setting: {0}")]
+    Download(#[source] BoxedError),
+}
@@ -0,0 +1,278 @@
+//! Save [archives](crate::archive) exported to and imported from files chosen by the player in the browser,
+//! so web players can back up their saves. The File System Access pickers are used where the browser has them,
+//! else a download and a file input.
+use crate::archive::{
+    encode_archive,
+    ArchiveImporter,
+};
+use crate::backend::SaveStorage;
+use crate::delta::DeltaSaves;
+use crate::error::SaveError;
+use crate::io::PendingWrites;
+use crate::paths::SaveDirs;
+use crate::save::{
+    EncryptSave,
+    LoadLimits,
+    SaveConfig,
+    SaveOptions,
+};
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    Deref,
+    DerefMut,
+    Message,
+    MessageReader,
+    MessageWriter,
+    Res,
+    Resource,
+};
+use js_sys::{
+    Array,
+    Function,
+    Object,
+    Promise,
+    Reflect,
+    Uint8Array,
+};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+use std::sync::Mutex;
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::{
+    spawn_local,
+    JsFuture,
+};
+use web_sys::{
+    Blob,
+    File,
+    FileSystemFileHandle,
+    FileSystemWritableFileStream,
+    HtmlAnchorElement,
+    HtmlInputElement,
+    Url,
+    Window,
+};
+
+/// Let the player save the slot as a file. Nothing is sent if they cancel.
+#[derive(Message, Deref, DerefMut)]
+pub struct ExportSaveToFile(pub u32);
+
+/// Let the player pick a file to add as a new slot. Nothing is sent if they cancel.
+#[derive(Message)]
+pub struct ImportSaveFromFile;
+
+#[derive(Message, Deref, DerefMut)]
+pub struct SaveExportedToFile(pub u32);
+
+/// Sent with the id of the new slot
+#[derive(Message, Deref, DerefMut)]
+pub struct SaveImportedFromFile(pub u32);
+
+#[derive(Message, Debug)]
+pub struct FilePickerFailed(pub SaveError);
+
+enum Picked {
+    Exported(u32, Result<(), SaveError>),
+    /// `None` if cancelled
+    Imported(Result<Option<Vec<u8>>, SaveError>),
+}
+
+/// Outcomes of the pickers, which resolve in later frames
+#[derive(Resource)]
+pub(crate) struct FilePickerResults {
+    sender: Sender<Picked>,
+    receiver: Mutex<Receiver<Picked>>,
+}
+
+impl Default for FilePickerResults {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+pub(crate) fn on_export_to_file<T>(
+    mut exports: MessageReader<ExportSaveToFile>,
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    storage: Res<SaveStorage>,
+    writes: Res<PendingWrites>,
+    delta_saves: DeltaSaves,
+    results: Res<FilePickerResults>,
+    mut failed: MessageWriter<FilePickerFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    // Files still being written would be exported half done
+    writes.flush();
+
+    let flattener = delta_saves.flattener::<T>();
+    for id in exports.read() {
+        let id = **id;
+        match encode_archive(&save_config, &dirs, &storage, &flattener, id) {
+            Ok(archive) => {
+                let sender = results.sender.clone();
+                spawn_local(async move {
+                    let result = save_file(&format!("slot_{}.bsma", id), &archive).await;
+                    let result = match result.map_err(js_error) {
+                        Err(e) if e.kind() == ErrorKind::Interrupted => return,
+                        result => result.map_err(SaveError::from),
+                    };
+                    let _ = sender.send(Picked::Exported(id, result));
+                });
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to export slot {} to a file: {}", id, error);
+                failed.write(FilePickerFailed(error));
+            }
+        }
+    }
+}
+
+pub(crate) fn on_import_from_file(
+    mut imports: MessageReader<ImportSaveFromFile>,
+    options: Res<SaveOptions>,
+    results: Res<FilePickerResults>,
+) {
+    let max_file_size = options.load_limits.max_file_size;
+    for _ in imports.read() {
+        let sender = results.sender.clone();
+        spawn_local(async move {
+            let result = match open_file().await.map_err(js_error) {
+                Ok(Some(file)) => match LoadLimits::check(file.size() as u64, max_file_size) {
+                    Ok(()) => read_file(&file).await.map(Some).map_err(|e| js_error(e).into()),
+                    Err(error) => Err(error),
+                },
+                Ok(None) => Ok(None),
+                Err(e) if e.kind() == ErrorKind::Interrupted => Ok(None),
+                Err(e) => Err(e.into()),
+            };
+            let _ = sender.send(Picked::Imported(result));
+        });
+    }
+}
+
+pub(crate) fn receive_picked_files<T>(
+    results: Res<FilePickerResults>,
+    mut importer: ArchiveImporter,
+    mut exported: MessageWriter<SaveExportedToFile>,
+    mut imported: MessageWriter<SaveImportedFromFile>,
+    mut failed: MessageWriter<FilePickerFailed>,
+) where
+    T: Resource + EncryptSave,
+{
+    let Ok(receiver) = results.receiver.lock() else {
+        return;
+    };
+    for picked in receiver.try_iter() {
+        match picked {
+            Picked::Exported(id, Ok(())) => {
+                exported.write(SaveExportedToFile(id));
+            }
+            Picked::Imported(Ok(None)) => {}
+            Picked::Imported(Ok(Some(data))) => match importer.import::<T>(&data) {
+                Ok(id) => {
+                    imported.write(SaveImportedFromFile(id));
+                }
+                Err(error) => {
+                    #[cfg(feature = "log")]
+                    warn!("Failed to import the picked save: {}", error);
+                    failed.write(FilePickerFailed(error));
+                }
+            },
+            Picked::Exported(_, Err(error)) | Picked::Imported(Err(error)) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to exchange a save with a file: {}", error);
+                failed.write(FilePickerFailed(error));
+            }
+        }
+    }
+}
+
+/// Cancelled pickers are [`ErrorKind::Interrupted`]
+fn js_error(error: JsValue) -> io::Error {
+    let error = error
+        .dyn_into::<js_sys::Error>()
+        .map_err(|value| format!("{:?}", value));
+    match error {
+        Ok(error) if error.name() == "AbortError" => io::Error::new(ErrorKind::Interrupted, "Cancelled"),
+        Ok(error) => io::Error::other(String::from(error.message())),
+        Err(message) => io::Error::other(message),
+    }
+}
+
+fn window() -> Result<Window, JsValue> {
+    web_sys::window().ok_or_else(|| JsValue::from_str("No browser window"))
+}
+
+/// `window.<name>` if the browser has it
+fn picker(window: &Window, name: &str) -> Option<Function> {
+    Reflect::get(window, &JsValue::from_str(name)).ok()?.dyn_into().ok()
+}
+
+/// Ask where to write `data`, or download it as `name`
+async fn save_file(name: &str, data: &[u8]) -> Result<(), JsValue> {
+    let window = window()?;
+    if let Some(show_picker) = picker(&window, "showSaveFilePicker") {
+        let options = Object::new();
+        Reflect::set(&options, &JsValue::from_str("suggestedName"), &JsValue::from_str(name))?;
+        let promise: Promise = show_picker.call1(&window, &options.into())?.unchecked_into();
+        let handle: FileSystemFileHandle = JsFuture::from(promise).await?.unchecked_into();
+        let stream: FileSystemWritableFileStream = JsFuture::from(handle.create_writable()).await?.unchecked_into();
+        JsFuture::from(stream.write_with_u8_array(data)?).await?;
+        JsFuture::from(stream.close()).await?;
+        return Ok(());
+    }
+
+    let blob = Blob::new_with_u8_array_sequence(&Array::of1(&Uint8Array::new_from_slice(data)))?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let document = window.document().ok_or_else(|| JsValue::from_str("No document"))?;
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    Url::revoke_object_url(&url)
+}
+
+/// Ask for a file to read, `None` if cancelled
+async fn open_file() -> Result<Option<File>, JsValue> {
+    let window = window()?;
+    if let Some(show_picker) = picker(&window, "showOpenFilePicker") {
+        let promise: Promise = show_picker.call0(&window)?.unchecked_into();
+        let handles: Array = JsFuture::from(promise).await?.unchecked_into();
+        let Some(handle) = handles.get(0).dyn_into::<FileSystemFileHandle>().ok() else {
+            return Ok(None);
+        };
+        return Ok(Some(JsFuture::from(handle.get_file()).await?.unchecked_into()));
+    }
+
+    let document = window.document().ok_or_else(|| JsValue::from_str("No document"))?;
+    let input: HtmlInputElement = document.create_element("input")?.unchecked_into();
+    input.set_type("file");
+    // Resolved when a file is chosen or the dialog is closed
+    let chosen = Promise::new(&mut |resolve, _reject| {
+        let _ = input.add_event_listener_with_callback("change", &resolve);
+        let _ = input.add_event_listener_with_callback("cancel", &resolve);
+    });
+    input.click();
+    JsFuture::from(chosen).await?;
+    Ok(input.files().and_then(|files| files.get(0)))
+}
+
+async fn read_file(file: &File) -> Result<Vec<u8>, JsValue> {
+    let buffer = JsFuture::from(file.array_buffer()).await?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
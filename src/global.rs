@@ -0,0 +1,250 @@
+use crate::backend::SaveStorage;
+use crate::cipher::{
+    open,
+    seal,
+    SaveCipher,
+    SaveKey,
+    SavePassword,
+    SetSavePassword,
+};
+use crate::error::SaveError;
+use crate::io::{
+    PendingIoPlugin,
+    PendingWrites,
+};
+use crate::paths::SaveDirs;
+use crate::profile::{
+    CurrentProfile,
+    ProfileSwitched,
+};
+use crate::save::{
+    save_key,
+    EncryptSave,
+    LoadLimits,
+    SaveEncoding,
+    SaveOptions,
+    SavesReEncrypted,
+};
+use bevy::app::App;
+use bevy::ecs::component::Tick;
+use bevy::ecs::system::SystemParam;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    on_message,
+    DetectChanges,
+    resource_changed,
+    IntoScheduleConfigs,
+    Last,
+    MessageReader,
+    Plugin,
+    Res,
+    ResMut,
+    Resource,
+    Startup,
+    SystemCondition,
+    Update,
+};
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Persist resource `T` to a single file, outside of save slots, e.g. for achievements or unlocks.
+/// It is loaded at startup and written whenever it changes, encrypted like the slot saves with their [`SaveCipher`],
+/// [`SaveKey`], [`SavePassword`] and signing key. It is encrypted again when the key changes.
+/// If the file can't be opened, e.g. before the password is set, it is not written until it is loaded.
+pub struct GlobalSavePlugin<T>
+where
+    T: Resource + Default + EncryptSave + Clone,
+{
+    file: PathBuf,
+    _data: PhantomData<T>,
+}
+
+impl<T> GlobalSavePlugin<T>
+where
+    T: Resource + Default + EncryptSave + Clone,
+{
+    /// `file` is relative to the [data directory](SaveDirs::data_dir), and to the profile directory when profiles
+    /// are used
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for GlobalSavePlugin<T>
+where
+    T: Resource + Default + EncryptSave + Clone,
+{
+    fn default() -> Self {
+        Self::new("global.dat")
+    }
+}
+
+impl<T> Plugin for GlobalSavePlugin<T>
+where
+    T: Resource + Default + EncryptSave + Clone,
+{
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<PendingIoPlugin>() {
+            app.add_plugins(PendingIoPlugin);
+        }
+
+        app.insert_resource(T::default())
+            .insert_resource(GlobalSaveFile::<T> {
+                file: self.file.clone(),
+                loaded: None,
+                locked: false,
+                _data: PhantomData,
+            })
+            .init_resource::<SaveStorage>()
+            .init_resource::<CurrentProfile>()
+            .init_resource::<SaveCipher>()
+            .init_resource::<SaveKey>()
+            .init_resource::<SavePassword>()
+            .add_message::<ProfileSwitched>()
+            .add_message::<SetSavePassword>()
+            .add_message::<SavesReEncrypted>()
+            .add_systems(Startup, load_global::<T>)
+            .add_systems(Update, load_global::<T>.run_if(on_message::<ProfileSwitched>))
+            .add_systems(
+                Last,
+                (
+                    load_global::<T>.run_if(on_message::<SetSavePassword>.and(is_locked::<T>)),
+                    save_global::<T>.run_if(resource_changed::<T>.or(on_message::<SavesReEncrypted>)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct GlobalSaveFile<T> {
+    file: PathBuf,
+    /// Change tick of the resource when it was last replaced by the content of the file
+    loaded: Option<Tick>,
+    /// The file couldn't be opened, it is kept until it is loaded
+    locked: bool,
+    _data: PhantomData<T>,
+}
+
+impl<T> GlobalSaveFile<T> {
+    fn path(&self, dirs: &SaveDirs, profile: &CurrentProfile) -> PathBuf {
+        dirs.data_dir().join(profile.dir()).join(&self.file)
+    }
+}
+
+/// Encryption of the slot saves, which the global save shares
+#[derive(SystemParam)]
+struct GlobalSealing<'w> {
+    cipher: Res<'w, SaveCipher>,
+    key: Res<'w, SaveKey>,
+    password: Res<'w, SavePassword>,
+    options: Option<Res<'w, SaveOptions>>,
+    #[cfg(feature = "signing")]
+    signing: Option<Res<'w, crate::signing::SigningKey>>,
+}
+
+impl GlobalSealing<'_> {
+    fn seal<T>(&self, value: &T) -> Result<Vec<u8>, SaveError>
+    where
+        T: EncryptSave,
+    {
+        let data = Zeroizing::new(SaveEncoding::Legacy.encode(value)?);
+        let game_version = self.options.as_ref().and_then(|options| options.game_version.as_deref());
+        let sealed = seal(
+            self.cipher.0.as_ref(),
+            &data,
+            &save_key::<T>(&self.key),
+            self.password.get(),
+            game_version,
+        )?;
+        #[cfg(feature = "signing")]
+        let sealed = crate::signing::sign_with(self.signing.as_deref(), sealed);
+        Ok(sealed)
+    }
+
+    fn open<T>(&self, data: &[u8]) -> Result<T, SaveError>
+    where
+        T: EncryptSave,
+    {
+        #[cfg(feature = "signing")]
+        crate::signing::verify_with(self.signing.as_deref(), data)?;
+        let decrypted = open(
+            self.cipher.0.as_ref(),
+            data,
+            &save_key::<T>(&self.key),
+            self.password.get(),
+            false,
+        )?;
+        let limits = self.options.as_ref().map(|options| options.load_limits).unwrap_or_default();
+        LoadLimits::check(decrypted.len() as u64, limits.max_allocation)?;
+        SaveEncoding::Legacy.decode(&decrypted)
+    }
+}
+
+fn is_locked<T>(file: Res<GlobalSaveFile<T>>) -> bool
+where
+    T: Resource,
+{
+    file.locked
+}
+
+fn load_global<T>(
+    mut global: ResMut<T>,
+    mut file: ResMut<GlobalSaveFile<T>>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    profile: Res<CurrentProfile>,
+    sealing: GlobalSealing,
+) where
+    T: Resource + Default + EncryptSave,
+{
+    let path = file.path(&dirs, &profile);
+    let loaded = storage.read(&path).map_err(SaveError::from).and_then(|data| sealing.open(&data));
+    file.locked = false;
+    *global = match loaded {
+        Ok(loaded) => loaded,
+        // Nothing saved yet
+        Err(SaveError::Io(e)) if e.kind() == ErrorKind::NotFound => T::default(),
+        Err(_e) => {
+            #[cfg(feature = "log")]
+            warn!("Failed to load global save {}: {}", path.display(), _e);
+            file.locked = true;
+            T::default()
+        }
+    };
+    file.loaded = Some(global.last_changed());
+}
+
+fn save_global<T>(
+    global: Res<T>,
+    file: Res<GlobalSaveFile<T>>,
+    storage: Res<SaveStorage>,
+    writes: Res<PendingWrites>,
+    dirs: Res<SaveDirs>,
+    profile: Res<CurrentProfile>,
+    sealing: GlobalSealing,
+    mut reencrypted: MessageReader<SavesReEncrypted>,
+) where
+    T: Resource + EncryptSave,
+{
+    // Written again with the new key even if it didn't change
+    let key_changed = reencrypted.read().count() > 0;
+    // Only loaded since it was last written
+    if file.locked || (file.loaded == Some(global.last_changed()) && !key_changed) {
+        return;
+    }
+    let path = file.path(&dirs, &profile);
+    match sealing.seal(&*global) {
+        Ok(data) => writes.spawn_write(storage.0.clone(), path, data, None),
+        Err(_e) => {
+            #[cfg(feature = "log")]
+            warn!("Failed to save global save {}: {}", path.display(), _e);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Quality presets of the rendering, kept in a settings file by [`GraphicsSettingsPlugin`]
+use crate::setting::{
+    GameSetting,
+    GameSettingSupportPlugin,
+    SettingIssue,
+};
+use bevy::app::App;
+#[cfg(feature = "graphics")]
+use bevy::camera::Camera;
+#[cfg(feature = "graphics")]
+use bevy::light::{
+    DirectionalLightShadowMap,
+    PointLightShadowMap,
+};
+#[cfg(feature = "graphics")]
+use bevy::prelude::{
+    DetectChanges,
+    DetectChangesMut,
+    Query,
+    Res,
+    ResMut,
+    Update,
+    With,
+};
+use bevy::prelude::{
+    Plugin,
+    Reflect,
+    Resource,
+};
+#[cfg(feature = "graphics")]
+use bevy::render::view::Msaa;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum GraphicsQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+    /// Values changed one by one
+    Custom,
+}
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Preset the values come from
+    pub quality: GraphicsQuality,
+    /// Samples of MSAA on every camera: 1, 2, 4 or 8
+    pub msaa_samples: u32,
+    /// Width and height of the shadow maps of lights, a power of two
+    pub shadow_map_size: u32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self::preset(GraphicsQuality::default())
+    }
+}
+
+impl GraphicsSettings {
+    /// Values of `quality`, those of [`GraphicsQuality::High`] for [`GraphicsQuality::Custom`]
+    pub fn preset(quality: GraphicsQuality) -> Self {
+        let (msaa_samples, shadow_map_size) = match quality {
+            GraphicsQuality::Low => (1, 512),
+            GraphicsQuality::Medium => (2, 1024),
+            GraphicsQuality::High | GraphicsQuality::Custom => (4, 2048),
+            GraphicsQuality::Ultra => (8, 4096),
+        };
+        Self {
+            quality,
+            msaa_samples,
+            shadow_map_size,
+        }
+    }
+}
+
+impl GameSetting for GraphicsSettings {
+    const DEFAULT_CONF: &'static str = "graphics.conf";
+    /// Depends on the hardware of the machine rather than the player
+    const PER_PROFILE: bool = false;
+
+    fn validate(&mut self) -> Result<(), Vec<SettingIssue>> {
+        let mut issues = Vec::new();
+        if ![1, 2, 4, 8].contains(&self.msaa_samples) {
+            issues.push(SettingIssue::new(
+                "msaa_samples",
+                format!("{} samples are not supported", self.msaa_samples),
+            ));
+            self.msaa_samples = self.msaa_samples.clamp(1, 8).next_power_of_two().min(8);
+        }
+        if !self.shadow_map_size.is_power_of_two() || !(256..=8192).contains(&self.shadow_map_size) {
+            issues.push(SettingIssue::new(
+                "shadow_map_size",
+                format!("{} is not a power of two between 256 and 8192", self.shadow_map_size),
+            ));
+            self.shadow_map_size = self.shadow_map_size.clamp(256, 8192).next_power_of_two().min(8192);
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// Load the [`GraphicsSettings`]. With the `graphics` feature, they are applied to the [`Msaa`](bevy::render::view::Msaa)
+/// of cameras and the shadow map resources of lights.
+pub struct GraphicsSettingsPlugin;
+
+impl Plugin for GraphicsSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GameSettingSupportPlugin::<GraphicsSettings>::default());
+
+        #[cfg(feature = "graphics")]
+        app.add_systems(Update, apply_graphics_settings);
+    }
+}
+
+/// Also applied to the cameras spawned since the last change
+#[cfg(feature = "graphics")]
+fn apply_graphics_settings(
+    settings: Res<GraphicsSettings>,
+    mut cameras: Query<&mut Msaa, With<Camera>>,
+    directional_shadow_map: Option<ResMut<DirectionalLightShadowMap>>,
+    point_shadow_map: Option<ResMut<PointLightShadowMap>>,
+) {
+    let msaa = Msaa::from_samples(settings.msaa_samples);
+    for mut camera_msaa in &mut cameras {
+        if settings.is_changed() || camera_msaa.is_added() {
+            camera_msaa.set_if_neq(msaa);
+        }
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    let size = settings.shadow_map_size as usize;
+    if let Some(mut shadow_map) = directional_shadow_map {
+        if shadow_map.size != size {
+            shadow_map.size = size;
+        }
+    }
+    if let Some(mut shadow_map) = point_shadow_map {
+        if shadow_map.size != size {
+            shadow_map.size = size;
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! Low-level access to save files, for tools working outside of a Bevy app
+use crate::cipher::{
+    open,
+    seal,
+    SaveCipher,
+    SecretKey,
+};
+use crate::error::SaveError;
+use crate::registry::decode_sections;
+use zeroize::Zeroizing;
+
+/// Decrypt a save of any built-in cipher, plain ones included
+pub fn decrypt_save(data: &[u8], key: &SecretKey) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    open(SaveCipher::default().0.as_ref(), data, key, None, true)
+}
+
+/// Encrypt with the default [`SaveCipher`]
+pub fn encrypt_save(data: &[u8], key: &SecretKey) -> Result<Vec<u8>, SaveError> {
+    seal(SaveCipher::default().0.as_ref(), data, key, None, None)
+}
+
+/// Named sections of decrypted save data, `None` for saves written before sections existed,
+/// which only contain the main resource
+pub fn save_sections(data: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    decode_sections(data).map(|saved| saved.sections)
+}
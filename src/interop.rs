@@ -0,0 +1,178 @@
+//! Adapters for projects moving from `bevy_pkv` or `bevy_persistent`, so players keep their data
+use crate::backend::{
+    FsBackend,
+    SaveBackend,
+};
+use crate::error::SettingError;
+use crate::paths::SaveDirs;
+use crate::save::SaveEncoding;
+use crate::setting::{
+    GameSetting,
+    SettingFormat,
+};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Mutex,
+    MutexGuard,
+};
+
+/// Key of the list of stored paths, as key-value stores can't list their keys
+const INDEX_KEY: &str = "bevy_save_manager.index";
+
+/// Key-value store like `bevy_pkv::PkvStore`, implemented by the game for the store it already uses,
+/// e.g. `get` with `store.get::<Vec<u8>>(key).ok()` and `set` with `store.set(key, &value.to_vec())`
+pub trait KeyValueStore: Send + 'static {
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    fn set(&mut self, key: &str, value: &[u8]) -> io::Result<()>;
+
+    fn remove(&mut self, key: &str) -> io::Result<()>;
+}
+
+/// [`SaveBackend`] storing each file under a key of a [`KeyValueStore`], the key being its path with `/`
+/// separators. Stored paths are listed under an extra key.
+pub struct KeyValueBackend<S> {
+    inner: Mutex<KeyValueInner<S>>,
+}
+
+struct KeyValueInner<S> {
+    store: S,
+    /// Read from the store on first use
+    index: Option<BTreeSet<String>>,
+}
+
+impl<S> KeyValueInner<S>
+where
+    S: KeyValueStore,
+{
+    fn index(&mut self) -> &mut BTreeSet<String> {
+        let store = &self.store;
+        self.index.get_or_insert_with(|| {
+            store
+                .get(INDEX_KEY)
+                .map(|index| String::from_utf8_lossy(&index).lines().map(str::to_string).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn write_index(&mut self) -> io::Result<()> {
+        let index = self.index().iter().cloned().collect::<Vec<_>>().join("\n");
+        self.store.set(INDEX_KEY, index.as_bytes())
+    }
+}
+
+impl<S> KeyValueBackend<S>
+where
+    S: KeyValueStore,
+{
+    pub fn new(store: S) -> Self {
+        Self {
+            inner: Mutex::new(KeyValueInner { store, index: None }),
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, KeyValueInner<S>> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Key of `path`, the same on every platform
+fn key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+impl<S> SaveBackend for KeyValueBackend<S>
+where
+    S: KeyValueStore,
+{
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner()
+            .store
+            .get(&key(path))
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let key = key(path);
+        let mut inner = self.inner();
+        inner.store.set(&key, data)?;
+        if inner.index().insert(key) {
+            inner.write_index()?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let key = key(path);
+        let mut inner = self.inner();
+        if !inner.index().remove(&key) {
+            return Err(ErrorKind::NotFound.into());
+        }
+        inner.store.remove(&key)?;
+        inner.write_index()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner().index().contains(&key(path))
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = key(dir);
+        Ok(self
+            .inner()
+            .index()
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.parent().is_some_and(|parent| key(parent) == dir))
+            .collect())
+    }
+}
+
+/// Format of a file written by `bevy_persistent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PersistentFormat {
+    /// [`SaveEncoding::Legacy`] for files written with bincode 1
+    Bincode(SaveEncoding),
+    Text(SettingFormat),
+}
+
+/// Read a resource stored by `bevy_persistent` at `path`. Saves can then be inserted and written to a slot.
+pub fn read_persistent<T>(path: &Path, format: PersistentFormat) -> Result<T, SettingError>
+where
+    T: DeserializeOwned,
+{
+    let data = FsBackend.read(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => SettingError::NotFound(path.to_path_buf()),
+        _ => e.into(),
+    })?;
+    match format {
+        PersistentFormat::Bincode(encoding) => encoding.decode(&data).map_err(|e| SettingError::Format(e.into())),
+        PersistentFormat::Text(format) => format.deserialize(&data),
+    }
+}
+
+/// Write the `bevy_persistent` file at `path` as the file of setting `T` in `dirs`, unless `T` already has one.
+/// Call it before the app starts, the old file is left in place. Returns whether the file was imported.
+pub fn import_persistent_setting<T>(
+    path: &Path,
+    format: PersistentFormat,
+    dirs: &SaveDirs,
+) -> Result<bool, SettingError>
+where
+    T: GameSetting,
+{
+    let config_path = T::config_path(dirs);
+    if FsBackend.exists(&config_path) || !FsBackend.exists(path) {
+        return Ok(false);
+    }
+    let setting: T = read_persistent(path, format)?;
+    FsBackend.write(&config_path, &setting.encode()?)?;
+    Ok(true)
+}
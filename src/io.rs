@@ -0,0 +1,503 @@
+use crate::backend::SaveBackend;
+use crate::error::SaveError;
+use crate::paths::{
+    update_save_dirs,
+    SaveDirs,
+    SavePaths,
+};
+use crate::portable::PortableMode;
+use crate::project::Project;
+use bevy::app::{
+    App,
+    AppExit,
+    Last,
+};
+#[cfg(feature = "log")]
+use bevy::prelude::{
+    error,
+    warn,
+};
+use bevy::prelude::{
+    on_message,
+    resource_changed,
+    resource_exists_and_changed,
+    First,
+    IntoScheduleConfigs,
+    Message,
+    MessageWriter,
+    Plugin,
+    PreStartup,
+    Res,
+    Resource,
+    SystemCondition,
+};
+use bevy::tasks::{
+    block_on,
+    IoTaskPool,
+    Task,
+};
+use std::collections::HashMap;
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+};
+use std::time::Duration;
+
+/// Writes of the app, shared with the tasks running them on the `IoTaskPool`.
+/// Each app has its own, so apps of the same process never wait for or report each other's writes.
+#[derive(Resource, Clone, Default)]
+pub struct PendingWrites(Arc<WriteQueue>);
+
+#[derive(Default)]
+struct WriteQueue {
+    /// Writes spawned on the `IoTaskPool` which haven't been collected yet
+    tasks: Mutex<Vec<Task<()>>>,
+    /// Files being written, with the write to run once the current one has completed.
+    /// Two writes to the same file never run at the same time, which could interleave their data.
+    in_flight: Mutex<HashMap<WriteTarget, Option<QueuedWrite>>>,
+    /// Copy of the [`RetryPolicy`] resource for the `IoTaskPool`
+    retry_policy: Mutex<RetryPolicy>,
+    /// Failed writes, drained into [`SaveFailed`] messages
+    failed: Outcomes<SaveFailed>,
+    /// Slots whose file was written, drained into [`SaveWritten`] messages
+    written: Outcomes<SaveWritten>,
+    /// Slots whose write was verified, drained into [`SaveVerified`] messages
+    verified: Outcomes<SaveVerified>,
+}
+
+/// Sent back from the `IoTaskPool`
+struct Outcomes<M> {
+    sender: Sender<M>,
+    receiver: Mutex<Receiver<M>>,
+}
+
+impl<M> Default for Outcomes<M> {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+/// File of a backend, two backends may hold different files at the same path
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct WriteTarget {
+    backend: usize,
+    path: PathBuf,
+}
+
+impl WriteTarget {
+    fn new(backend: &Arc<dyn SaveBackend>, path: &Path) -> Self {
+        Self {
+            backend: Arc::as_ptr(backend) as *const () as usize,
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+/// Told the path and outcome of a write, see [`PendingWrites::spawn_notified_write`]
+pub(crate) type WriteNotifier = Sender<(PathBuf, io::Result<()>)>;
+
+/// Produces the data of a write on the `IoTaskPool`, see [`PendingWrites::spawn_encoded_write`]
+pub(crate) type Encode = Box<dyn FnOnce() -> Result<Vec<u8>, SaveError> + Send + Sync>;
+
+struct QueuedWrite {
+    backend: Arc<dyn SaveBackend>,
+    data: Vec<u8>,
+    /// Run before the write to fill `data`
+    encode: Option<Encode>,
+    notify: Option<WriteNotifier>,
+    slot: Option<u32>,
+    durability: Durability,
+}
+
+/// Extra steps after writing a file, see [`EncryptSavePlugin::with_fsync`](crate::save::EncryptSavePlugin::with_fsync)
+/// and [`EncryptSavePlugin::with_verify_after_write`](crate::save::EncryptSavePlugin::with_verify_after_write)
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Durability {
+    /// Flush the file to disk with [`SaveBackend::sync`]
+    pub fsync: bool,
+    /// Read the file back and compare it with the written data
+    pub verify: bool,
+}
+
+/// Block until every pending save and settings write has completed.
+/// Also done automatically on `AppExit`.
+#[derive(Message)]
+pub struct FlushSaves;
+
+/// Longest wait between two attempts of a [`RetryPolicy`]
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How writes of saves and settings are retried, for transient failures like antivirus locks or network drives.
+/// Each retry waits twice as long as the previous one, up to [`MAX_BACKOFF`], blocking an `IoTaskPool` thread.
+/// [`SaveFailed`] is only sent once every attempt has failed.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries of each write, including the first one
+    pub attempts: u32,
+    /// Wait before the first retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub const fn new(attempts: u32, backoff: Duration) -> Self {
+        Self { attempts, backoff }
+    }
+}
+
+/// No retry
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(1, Duration::from_millis(100))
+    }
+}
+
+/// The file of `slot` was read back after its write and matched the save data
+#[derive(Message, Debug)]
+pub struct SaveVerified {
+    pub slot: u32,
+    pub path: PathBuf,
+}
+
+/// The file of `slot` was written, and verified if enabled.
+/// With [`EncryptSavePlugin::with_background_saves`](crate::save::EncryptSavePlugin::with_background_saves),
+/// this is when the save is on disk rather than [`GameSaved`](crate::save::GameSaved).
+#[derive(Message, Debug)]
+pub struct SaveWritten {
+    pub slot: u32,
+    pub path: PathBuf,
+    /// Size of the file, in bytes
+    pub size: u64,
+}
+
+/// A save or settings file could not be written
+#[derive(Message, Debug)]
+pub struct SaveFailed {
+    /// `None` for files which don't belong to a save slot, like settings
+    pub slot: Option<u32>,
+    pub path: PathBuf,
+    pub error: SaveError,
+}
+
+pub(crate) struct PendingIoPlugin;
+
+impl Plugin for PendingIoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<FlushSaves>()
+            .add_message::<SaveFailed>()
+            .add_message::<SaveVerified>()
+            .add_message::<SaveWritten>()
+            .init_resource::<RetryPolicy>()
+            .init_resource::<PendingWrites>()
+            .init_resource::<SavePaths>()
+            .init_resource::<PortableMode>()
+            .init_resource::<SaveDirs>()
+            .add_systems(PreStartup, update_save_dirs)
+            .add_systems(
+                First,
+                (
+                    apply_retry_policy.run_if(resource_changed::<RetryPolicy>),
+                    update_save_dirs.run_if(
+                        resource_changed::<SavePaths>
+                            .or(resource_changed::<PortableMode>)
+                            .or(resource_exists_and_changed::<Project>),
+                    ),
+                ),
+            )
+            .add_systems(
+                Last,
+                (
+                    flush_pending_writes.run_if(on_message::<FlushSaves>.or(on_message::<AppExit>)),
+                    collect_finished_writes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl PendingWrites {
+    /// Write `data` to `path` through `backend` on the `IoTaskPool`.
+    /// If `path` is already being written, `data` is written after it, replacing any other write queued for `path`.
+    pub(crate) fn spawn_write(&self, backend: Arc<dyn SaveBackend>, path: PathBuf, data: Vec<u8>, slot: Option<u32>) {
+        self.spawn_durable_write(backend, path, data, slot, Durability::default());
+    }
+
+    /// [`PendingWrites::spawn_write`] followed by the steps of `durability`
+    pub(crate) fn spawn_durable_write(
+        &self,
+        backend: Arc<dyn SaveBackend>,
+        path: PathBuf,
+        data: Vec<u8>,
+        slot: Option<u32>,
+        durability: Durability,
+    ) {
+        self.queue_write(
+            path,
+            QueuedWrite {
+                backend,
+                data,
+                encode: None,
+                notify: None,
+                slot,
+                durability,
+            },
+        );
+    }
+
+    /// [`PendingWrites::spawn_write`] of a file which doesn't belong to a slot, sending its outcome to `notify`.
+    /// Nothing is sent if the write is replaced by a newer one before it starts.
+    pub(crate) fn spawn_notified_write(
+        &self,
+        backend: Arc<dyn SaveBackend>,
+        path: PathBuf,
+        data: Vec<u8>,
+        notify: WriteNotifier,
+    ) {
+        self.queue_write(
+            path,
+            QueuedWrite {
+                backend,
+                data,
+                encode: None,
+                notify: Some(notify),
+                slot: None,
+                durability: Durability::default(),
+            },
+        );
+    }
+
+    /// [`PendingWrites::spawn_durable_write`] of the data returned by `encode`, which also runs on the `IoTaskPool`.
+    /// It is queued like a write, so it never runs before an earlier write of `path` has completed.
+    pub(crate) fn spawn_encoded_write(
+        &self,
+        backend: Arc<dyn SaveBackend>,
+        path: PathBuf,
+        encode: Encode,
+        slot: Option<u32>,
+        durability: Durability,
+    ) {
+        self.queue_write(
+            path,
+            QueuedWrite {
+                backend,
+                data: Vec::new(),
+                encode: Some(encode),
+                notify: None,
+                slot,
+                durability,
+            },
+        );
+    }
+
+    fn queue_write(&self, path: PathBuf, write: QueuedWrite) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let target = WriteTarget::new(&write.backend, &path);
+            let mut in_flight = lock(&self.0.in_flight);
+            if let Some(queued) = in_flight.get_mut(&target) {
+                *queued = Some(write);
+                return;
+            }
+            in_flight.insert(target.clone(), None);
+            drop(in_flight);
+
+            let queue = self.0.clone();
+            let task = IoTaskPool::get().spawn(async move {
+                let mut write = write;
+                loop {
+                    queue.run_write(&mut write, &path);
+                    let mut in_flight = lock(&queue.in_flight);
+                    match in_flight.get_mut(&target).and_then(Option::take) {
+                        Some(next) => write = next,
+                        None => {
+                            in_flight.remove(&target);
+                            break;
+                        }
+                    }
+                }
+            });
+            lock(&self.0.tasks).push(task);
+        }
+
+        // Without threads to wait for, the write runs now, for backends of the browser like `OpfsBackend`
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut write = write;
+            self.0.run_write(&mut write, &path);
+        }
+    }
+
+    /// Whether `path` of `backend` is being written or has a write queued
+    pub(crate) fn is_writing(&self, backend: &Arc<dyn SaveBackend>, path: &Path) -> bool {
+        lock(&self.0.in_flight).contains_key(&WriteTarget::new(backend, path))
+    }
+
+    /// Whether no write is running or queued
+    pub(crate) fn is_idle(&self) -> bool {
+        lock(&self.0.in_flight).is_empty()
+    }
+
+    /// Block until every pending write has completed
+    pub fn flush(&self) {
+        let pending = std::mem::take(&mut *lock(&self.0.tasks));
+        for task in pending {
+            block_on(task);
+        }
+    }
+}
+
+impl WriteQueue {
+    /// Encode and write `write`, then report its outcome
+    fn run_write(&self, write: &mut QueuedWrite, path: &Path) {
+        let encoded = match write.encode.take() {
+            Some(encode) => encode().map(|data| write.data = data),
+            None => Ok(()),
+        };
+        let policy = *lock(&self.retry_policy);
+        let result = encoded.and_then(|()| write_with_retries(policy, write, path));
+        if let Some(notify) = &write.notify {
+            let outcome = match &result {
+                Ok(()) => Ok(()),
+                Err(SaveError::Io(e)) => Err(io::Error::new(e.kind(), e.to_string())),
+                Err(e) => Err(io::Error::other(e.to_string())),
+            };
+            let _ = notify.send((path.to_path_buf(), outcome));
+        }
+        match result {
+            Ok(()) => {
+                if let Some(slot) = write.slot {
+                    if write.durability.verify {
+                        let _ = self.verified.sender.send(SaveVerified {
+                            slot,
+                            path: path.to_path_buf(),
+                        });
+                    }
+                    let _ = self.written.sender.send(SaveWritten {
+                        slot,
+                        path: path.to_path_buf(),
+                        size: write.data.len() as u64,
+                    });
+                }
+            }
+            Err(error) => {
+                let _ = self.failed.sender.send(SaveFailed {
+                    slot: write.slot,
+                    path: path.to_path_buf(),
+                    error,
+                });
+            }
+        }
+    }
+}
+
+fn write_with_retries(policy: RetryPolicy, write: &QueuedWrite, path: &Path) -> Result<(), SaveError> {
+    #[cfg(feature = "trace")]
+    let _span = tracing::info_span!("write", slot = ?write.slot, bytes = write.data.len()).entered();
+    retry(policy, path, || write_durably(write, path))
+}
+
+/// Twice `backoff`, up to [`MAX_BACKOFF`]
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_BACKOFF)
+}
+
+/// Run `write` of `path` again while it fails, as allowed by `policy`
+pub(crate) fn retry(
+    policy: RetryPolicy,
+    _path: &Path,
+    mut write: impl FnMut() -> Result<(), SaveError>,
+) -> Result<(), SaveError> {
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        match write() {
+            Err(_e) if attempt < policy.attempts => {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Failed to write {} (attempt {}/{}), retrying: {}",
+                    _path.display(),
+                    attempt,
+                    policy.attempts,
+                    _e
+                );
+                // The main thread of the browser can't sleep, retries run right away there
+                #[cfg(not(target_arch = "wasm32"))]
+                std::thread::sleep(backoff);
+                backoff = next_backoff(backoff);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn write_durably(write: &QueuedWrite, path: &Path) -> Result<(), SaveError> {
+    write.backend.write(path, &write.data)?;
+    if write.durability.fsync {
+        write.backend.sync(path)?;
+    }
+    if write.durability.verify && write.backend.read(path)? != write.data {
+        return Err(SaveError::Corrupted(
+            "Written file differs from the save data".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Block until every pending write has completed
+pub fn flush_pending_writes(writes: Res<PendingWrites>) {
+    writes.flush();
+}
+
+fn collect_finished_writes(
+    writes: Res<PendingWrites>,
+    mut failed: MessageWriter<SaveFailed>,
+    mut verified: MessageWriter<SaveVerified>,
+    mut written: MessageWriter<SaveWritten>,
+) {
+    let queue = &writes.0;
+    lock(&queue.tasks).retain(|task| !task.is_finished());
+
+    for failure in lock(&queue.failed.receiver).try_iter() {
+        #[cfg(feature = "log")]
+        error!("Failed to write {}: {}", failure.path.display(), failure.error);
+        failed.write(failure);
+    }
+    verified.write_batch(lock(&queue.verified.receiver).try_iter());
+    written.write_batch(lock(&queue.written.receiver).try_iter());
+}
+
+fn apply_retry_policy(policy: Res<RetryPolicy>, writes: Res<PendingWrites>) {
+    *lock(&writes.0.retry_policy) = *policy;
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding the lock can't leave the data in an invalid state
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(next_backoff(Duration::from_secs(1)), Duration::from_secs(2));
+        assert_eq!(next_backoff(MAX_BACKOFF / 2 + Duration::from_secs(1)), MAX_BACKOFF);
+        assert_eq!(next_backoff(Duration::MAX), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::backend::SaveStorage;
+use crate::cipher::{
+    key_id,
+    SaveCipher,
+    SaveHeader,
+    SaveKey,
+    SecretKey,
+};
+use crate::error::SaveError;
+use crate::paths::SaveDirs;
+use crate::save::{
+    reseal,
+    EncryptSave,
+    LoadLimits,
+    SaveConfig,
+    SaveOptions,
+};
+use crate::setting::GameSettingChanged;
+use ::keyring::{
+    Entry,
+    Error,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    Commands,
+    Message,
+    MessageWriter,
+    Plugin,
+    PreStartup,
+    Res,
+    ResMut,
+    Resource,
+};
+use zeroize::Zeroizing;
+
+/// Account name of the key in the credential store
+const USER: &str = "save_key";
+
+/// Fill [`SaveKey`] from the OS credential store (macOS Keychain, Windows Credential Manager, Secret Service),
+/// generating a random key on first run. Added by
+/// [`EncryptSavePlugin::with_keyring`](crate::save::EncryptSavePlugin::with_keyring), which also encrypts again
+/// with it the saves encrypted with `ENCR_KEY` before.
+/// When the store is unavailable, [`KeyringUnavailable`] is sent and kept as a resource, and save requests are
+/// answered with [`SaveRefused`](crate::mode::SaveRefused) rather than encrypted with a key the next runs won't use.
+pub struct KeyringPlugin {
+    pub service: String,
+}
+
+impl Plugin for KeyringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveKey>()
+            .insert_resource(KeyringService(self.service.clone()))
+            .add_message::<KeyringUnavailable>()
+            .add_systems(PreStartup, load_key);
+    }
+}
+
+#[derive(Resource)]
+struct KeyringService(String);
+
+/// The save key couldn't be read from the credential store at startup, saves are refused until the next run
+#[derive(Resource, Message, Clone, Debug)]
+pub struct KeyringUnavailable {
+    pub error: String,
+}
+
+fn load_key(
+    mut commands: Commands,
+    service: Res<KeyringService>,
+    mut key: ResMut<SaveKey>,
+    mut unavailable: MessageWriter<KeyringUnavailable>,
+) {
+    match read_or_create_key(&service.0) {
+        Ok(stored) => *key = SaveKey::new(stored),
+        Err(e) => {
+            #[cfg(feature = "log")]
+            warn!("Failed to get save key from the credential store, saves are refused: {}", e);
+            let error = KeyringUnavailable { error: e.to_string() };
+            commands.insert_resource(error.clone());
+            unavailable.write(error);
+        }
+    }
+}
+
+/// Key stored for `service`, a new one is generated and stored if there is none yet
+pub fn read_or_create_key(service: &str) -> Result<SecretKey, Error> {
+    let entry = Entry::new(service, USER)?;
+    match entry.get_password() {
+        Err(Error::NoEntry) => {
+            let mut bytes = Zeroizing::new([0u8; 32]);
+            getrandom::fill(&mut *bytes).map_err(|e| Error::PlatformFailure(Box::new(e)))?;
+            let key = SecretKey::new(bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+            entry.set_password(key.expose())?;
+            Ok(key)
+        }
+        result => result.map(SecretKey::new),
+    }
+}
+
+/// Encrypt again with the stored key the files still encrypted with `T::ENCR_KEY`, from before the keyring was
+/// enabled. Files which don't open with `T::ENCR_KEY` either are left for their load to report.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn migrate_saves<T>(
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    cipher: Res<SaveCipher>,
+    options: Res<SaveOptions>,
+    key: Res<SaveKey>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+    #[cfg(feature = "signing")] signing: Option<Res<crate::signing::SigningKey>>,
+) where
+    T: EncryptSave,
+{
+    let Some(stored) = key.get() else {
+        return;
+    };
+    let stored_id = key_id(stored);
+    let old_key = SecretKey::from(T::ENCR_KEY);
+    let limits = options.load_limits;
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    let mut migrated = false;
+    for (slot, file) in save_config.sealed_files() {
+        let path = save_dir.join(file);
+        // Only the header is read of the files already migrated, or encrypted with a password
+        let header = storage
+            .reader(&path)
+            .and_then(|mut reader| SaveHeader::read_from(&mut reader))
+            .ok()
+            .and_then(|(header, _)| header);
+        if header.is_some_and(|header| header.key_id == Some(stored_id) || header.salt.is_some()) {
+            continue;
+        }
+        let result = storage
+            .size(&path)
+            .map_err(SaveError::from)
+            .and_then(|size| LoadLimits::check(size, limits.max_file_size))
+            .and_then(|_| Ok(storage.read(&path)?))
+            .and_then(|data| reseal(cipher.0.as_ref(), &data, &old_key, stored, limits))
+            .and_then(|sealed| {
+                #[cfg(feature = "signing")]
+                let sealed = crate::signing::sign_with(signing.as_deref(), sealed);
+                Ok(storage.write(&path, &sealed)?)
+            });
+        match result {
+            Ok(()) => {
+                if let Some(slot) = slot.and_then(|id| save_config.slot_mut(id)) {
+                    slot.revision += 1;
+                }
+                migrated = true;
+            }
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Failed to encrypt {} with the key of the credential store: {}",
+                    path.display(),
+                    _e
+                );
+            }
+        }
+    }
+    if migrated {
+        setting_changed.write(GameSettingChanged);
+    }
+}
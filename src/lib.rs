@@ -1,5 +1,62 @@
 //! ### Plugin
 //!
 
+pub mod archive;
+pub mod audio;
+pub mod backend;
+pub mod cipher;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+#[cfg(feature = "console")]
+pub mod console;
+#[cfg(feature = "egui")]
+pub mod debug_ui;
+mod delta;
+pub mod diagnostic;
+pub mod error;
+#[cfg(feature = "file-picker")]
+pub mod file_picker;
+pub mod global;
+pub mod graphics;
+pub mod inspect;
+pub mod interop;
+pub mod io;
+#[cfg(feature = "keyring")]
+pub mod keyring;
+pub mod locale;
+pub mod menu;
+pub mod meta;
+pub mod mode;
+pub mod naming;
+#[cfg(feature = "opfs")]
+pub mod opfs;
+pub mod options;
+#[cfg(feature = "remote-config")]
+pub mod overlay;
+pub mod paths;
+pub mod platform;
+pub mod portable;
+pub mod profile;
+pub mod progress;
+pub mod project;
+mod registry;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod save;
+#[cfg(feature = "scene")]
+pub mod scene;
 pub mod setting;
-pub mod save;
\ No newline at end of file
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "snapshot-import")]
+pub mod snapshot;
+#[cfg(feature = "steam")]
+pub mod steam;
+pub mod sync;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+#[cfg(feature = "thumbnail")]
+pub mod thumbnail;
+pub mod transform;
+#[cfg(feature = "window")]
+pub mod window;
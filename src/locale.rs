@@ -0,0 +1,79 @@
+//! Language of the game, kept in a settings file by [`LocaleSettingPlugin`]
+use crate::setting::{
+    GameSetting,
+    GameSettingSupportPlugin,
+};
+use bevy::app::App;
+use bevy::prelude::{
+    resource_changed,
+    Deref,
+    IntoScheduleConfigs,
+    Local,
+    Message,
+    MessageWriter,
+    Plugin,
+    Reflect,
+    Res,
+    Resource,
+    Update,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Used when the locale of the OS can't be read
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// Locale as a BCP 47 tag, like `en-US`. Until a file is written, it is the locale of the OS.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LocaleSetting {
+    pub locale: String,
+}
+
+impl Default for LocaleSetting {
+    fn default() -> Self {
+        Self {
+            locale: Self::os_locale(),
+        }
+    }
+}
+
+impl LocaleSetting {
+    /// Preferred locale of the OS, `en-US` if unknown
+    pub fn os_locale() -> String {
+        sys_locale::get_locale().unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+    }
+}
+
+impl GameSetting for LocaleSetting {
+    const DEFAULT_CONF: &'static str = "locale.conf";
+}
+
+/// The locale was loaded or changed, for localization crates to switch their language
+#[derive(Message, Deref, Clone, Debug)]
+pub struct LocaleChanged(pub String);
+
+/// Load the [`LocaleSetting`] and send [`LocaleChanged`] on startup and whenever the locale changes
+pub struct LocaleSettingPlugin;
+
+impl Plugin for LocaleSettingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GameSettingSupportPlugin::<LocaleSetting>::default())
+            .add_message::<LocaleChanged>()
+            .add_systems(Update, notify_locale.run_if(resource_changed::<LocaleSetting>));
+    }
+}
+
+/// Setting the same locale again, like switching to a profile with the same language, isn't reported
+fn notify_locale(
+    settings: Res<LocaleSetting>,
+    mut sent: Local<Option<String>>,
+    mut changed: MessageWriter<LocaleChanged>,
+) {
+    if sent.as_ref() != Some(&settings.locale) {
+        *sent = Some(settings.locale.clone());
+        changed.write(LocaleChanged(settings.locale.clone()));
+    }
+}
@@ -0,0 +1,131 @@
+//! Slots ready to be shown in a save or load menu, see [`SaveMenuModel`]
+use crate::backend::SaveStorage;
+use crate::paths::SaveDirs;
+use crate::save::{
+    CurrentSave,
+    SaveConfig,
+    SaveMetadataRefreshed,
+    SaveSet,
+    SlotKind,
+};
+#[cfg(feature = "thumbnail")]
+use crate::thumbnail::SaveThumbnails;
+use bevy::app::App;
+#[cfg(feature = "thumbnail")]
+use bevy::asset::Handle;
+#[cfg(feature = "thumbnail")]
+use bevy::image::Image;
+use bevy::prelude::{
+    resource_changed,
+    resource_exists,
+    SystemCondition,
+    IntoScheduleConfigs,
+    Local,
+    MessageReader,
+    Plugin,
+    Res,
+    ResMut,
+    Resource,
+    Update,
+};
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::time::Duration;
+
+/// Slot of the [`SaveMenuModel`]
+#[derive(Clone, Debug)]
+pub struct SaveMenuEntry {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    /// Last time the slot was saved or loaded, in seconds since the Unix epoch
+    pub last_played: u64,
+    pub playtime: Duration,
+    pub kind: SlotKind,
+    /// [`SaveSlot::fields`](crate::save::SaveSlot::fields) of the slot
+    pub fields: BTreeMap<String, String>,
+    /// Size of the save file in bytes, `None` if it couldn't be read
+    pub size: Option<u64>,
+    /// Slot last saved or loaded in this session
+    pub is_current: bool,
+    #[cfg(feature = "thumbnail")]
+    pub thumbnail: Option<Handle<Image>>,
+}
+
+/// Slots sorted from the most recently played, kept up to date by [`SaveMenuPlugin`]
+#[derive(Resource, Clone, Default, Debug)]
+pub struct SaveMenuModel {
+    pub entries: Vec<SaveMenuEntry>,
+}
+
+impl SaveMenuModel {
+    pub fn get(&self, id: u32) -> Option<&SaveMenuEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+/// Fill [`SaveMenuModel`] from the slots of [`EncryptSavePlugin`](crate::save::EncryptSavePlugin),
+/// for UI layers to bind to
+pub struct SaveMenuPlugin;
+
+impl Plugin for SaveMenuPlugin {
+    fn build(&self, app: &mut App) {
+        let changed = resource_changed::<SaveConfig>.or(resource_changed::<CurrentSave>);
+        #[cfg(feature = "thumbnail")]
+        let changed = changed.or(resource_exists::<SaveThumbnails>.and(resource_changed::<SaveThumbnails>));
+        app.init_resource::<SaveMenuModel>().add_systems(
+            Update,
+            update_menu_model
+                .after(SaveSet::Write)
+                .run_if(resource_exists::<SaveConfig>.and(changed)),
+        );
+    }
+}
+
+/// Rebuild the entries, the file size of a slot is only read again when its revision changes or it is refreshed
+fn update_menu_model(
+    mut refreshed: MessageReader<SaveMetadataRefreshed>,
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    current_save: Res<CurrentSave>,
+    storage: Res<SaveStorage>,
+    #[cfg(feature = "thumbnail")] thumbnails: Option<Res<SaveThumbnails>>,
+    mut sizes: Local<HashMap<u32, (u64, Option<u64>)>>,
+    mut model: ResMut<SaveMenuModel>,
+) {
+    for refreshed in refreshed.read() {
+        for id in &refreshed.slots {
+            sizes.remove(id);
+        }
+    }
+    sizes.retain(|id, _| save_config.slot(*id).is_some());
+    model.entries = save_config
+        .sorted_by_recency()
+        .into_iter()
+        .map(|(id, slot)| {
+            let size = match sizes.get(&id) {
+                Some((revision, size)) if *revision == slot.revision => *size,
+                _ => {
+                    let size = storage.size(&save_config.save_dir(&dirs).join(&slot.file)).ok();
+                    sizes.insert(id, (slot.revision, size));
+                    size
+                }
+            };
+            SaveMenuEntry {
+                id,
+                name: slot.name.clone(),
+                description: slot.description.clone(),
+                last_played: slot.last_played(),
+                playtime: slot.playtime,
+                kind: slot.kind,
+                fields: slot.fields.clone(),
+                size,
+                is_current: id == current_save.0,
+                #[cfg(feature = "thumbnail")]
+                thumbnail: thumbnails.as_ref().and_then(|thumbnails| thumbnails.get(id)).cloned(),
+            }
+        })
+        .collect();
+}
@@ -0,0 +1,142 @@
+//! Unencrypted `.meta` sidecar next to each save file, to show slots without decrypting them
+use crate::backend::{
+    SaveBackend,
+    SaveStorage,
+};
+use crate::error::SaveError;
+use crate::io::PendingWrites;
+use crate::paths::SaveDirs;
+use crate::save::{
+    SaveConfig,
+    SaveSet,
+    SaveSlot,
+    SlotKind,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    resource_changed,
+    IntoScheduleConfigs,
+    Local,
+    Plugin,
+    Res,
+    Update,
+};
+use ron::ser::PrettyConfig;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+    HashMap,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+/// Content of a `.meta` sidecar, written as RON
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct SlotMeta {
+    pub name: String,
+    pub description: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    pub saved_at: u64,
+    pub playtime: Duration,
+    /// Relative to the save directory
+    pub thumbnail: Option<PathBuf>,
+    pub kind: SlotKind,
+    pub tags: BTreeSet<String>,
+    pub game_version: Option<String>,
+    /// [`SlotFields`](crate::save::SlotFields) at save time
+    pub fields: BTreeMap<String, String>,
+    /// [`SaveSlot::custom`] at save time
+    pub custom: Option<String>,
+}
+
+impl From<&SaveSlot> for SlotMeta {
+    fn from(slot: &SaveSlot) -> Self {
+        Self {
+            name: slot.name.clone(),
+            description: slot.description.clone(),
+            created_at: slot.created_at,
+            saved_at: slot.saved_at,
+            playtime: slot.playtime,
+            thumbnail: slot.thumbnail.clone(),
+            kind: slot.kind,
+            tags: slot.tags.clone(),
+            game_version: slot.game_version.clone(),
+            fields: slot.fields.clone(),
+            custom: slot.custom.clone(),
+        }
+    }
+}
+
+/// Sidecar of the save file `file`
+pub fn meta_path(file: &Path) -> PathBuf {
+    file.with_extension("meta")
+}
+
+/// Read the sidecar of the save file `file`
+pub fn read_meta(backend: &dyn SaveBackend, file: &Path) -> Result<SlotMeta, SaveError> {
+    let data = backend.read(&meta_path(file))?;
+    ron::de::from_bytes(&data).map_err(|e| SaveError::Deserialize(e.into()))
+}
+
+pub(crate) struct MetaPlugin;
+
+impl Plugin for MetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            write_sidecars
+                .after(SaveSet::Write)
+                .run_if(resource_changed::<SaveConfig>),
+        );
+    }
+}
+
+/// Rewrite the sidecars of changed slots and remove the ones of deleted slots
+fn write_sidecars(
+    save_config: Res<SaveConfig>,
+    dirs: Res<SaveDirs>,
+    storage: Res<SaveStorage>,
+    writes: Res<PendingWrites>,
+    mut written: Local<HashMap<PathBuf, SlotMeta>>,
+) {
+    let save_dir = save_config.save_dir(&dirs);
+    written.retain(|file, _| {
+        if save_config.slots().values().any(|slot| slot.file == *file) {
+            return true;
+        }
+        // Slots of another profile are still on disk
+        if !storage.exists(&save_dir.join(file)) {
+            let _ = storage.remove(&save_dir.join(meta_path(file)));
+        }
+        false
+    });
+
+    for (id, slot) in save_config.slots() {
+        let meta = SlotMeta::from(slot);
+        if written.get(&slot.file) == Some(&meta) {
+            continue;
+        }
+        match ron::ser::to_string_pretty(&meta, PrettyConfig::default()) {
+            Ok(text) => {
+                let path = save_dir.join(meta_path(&slot.file));
+                writes.spawn_write(storage.0.clone(), path, text.into_bytes(), Some(*id));
+                written.insert(slot.file.clone(), meta);
+            }
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to serialize metadata of slot {}: {}", id, _e);
+            }
+        }
+    }
+}
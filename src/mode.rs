@@ -0,0 +1,215 @@
+//! Demo builds and "no save" modes, see [`SaveManagerMode`]
+use crate::backend::SaveBackend;
+use bevy::prelude::{
+    Deref,
+    DerefMut,
+    Message,
+    Res,
+    Resource,
+};
+use std::collections::HashMap;
+use std::io;
+use std::io::{
+    Read,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicU8,
+    Ordering,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+};
+
+/// How saves and settings are persisted, can be changed at any time
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SaveManagerMode {
+    #[default]
+    Normal,
+    /// Existing saves can be loaded. Save requests are answered with [`SaveRefused`],
+    /// other writes like settings, copies and imports fail and files can't be deleted.
+    ReadOnly,
+    /// Writes and deletions only happen in memory, on top of the files already stored
+    Ephemeral,
+}
+
+impl SaveManagerMode {
+    fn from_u8(mode: u8) -> Self {
+        match mode {
+            1 => Self::ReadOnly,
+            2 => Self::Ephemeral,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Response to a save request in [`SaveManagerMode::ReadOnly`], or while the save key is unavailable from the
+/// credential store, `None` for checkpoints
+#[derive(Message, Deref, DerefMut, Debug)]
+pub struct SaveRefused(pub Option<u32>);
+
+/// Wraps the storage to apply the current [`SaveManagerMode`]
+pub(crate) struct ModeBackend {
+    inner: Arc<dyn SaveBackend>,
+    mode: Arc<AtomicU8>,
+    /// Files written in [`SaveManagerMode::Ephemeral`], `None` when removed
+    memory: Mutex<HashMap<PathBuf, Option<Vec<u8>>>>,
+}
+
+impl ModeBackend {
+    pub fn new(inner: Arc<dyn SaveBackend>, mode: Arc<AtomicU8>) -> Self {
+        Self {
+            inner,
+            mode,
+            memory: Mutex::default(),
+        }
+    }
+
+    fn mode(&self) -> SaveManagerMode {
+        SaveManagerMode::from_u8(self.mode.load(Ordering::Relaxed))
+    }
+
+    fn memory(&self) -> MutexGuard<'_, HashMap<PathBuf, Option<Vec<u8>>>> {
+        self.memory.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SaveBackend for ModeBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.memory().get(path) {
+            Some(Some(data)) => Ok(data.clone()),
+            Some(None) => Err(io::ErrorKind::NotFound.into()),
+            None => self.inner.read(path),
+        }
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        match self.mode() {
+            SaveManagerMode::Normal => {
+                self.inner.write(path, data)?;
+                self.memory().remove(path);
+                Ok(())
+            }
+            SaveManagerMode::ReadOnly => Err(read_only()),
+            SaveManagerMode::Ephemeral => {
+                self.memory().insert(path.to_path_buf(), Some(data.to_vec()));
+                Ok(())
+            }
+        }
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match self.mode() {
+            SaveManagerMode::Normal => {
+                let in_memory = self.memory().remove(path).is_some();
+                match self.inner.remove(path) {
+                    Err(e) if e.kind() == io::ErrorKind::NotFound && in_memory => Ok(()),
+                    result => result,
+                }
+            }
+            SaveManagerMode::ReadOnly => Err(read_only()),
+            SaveManagerMode::Ephemeral => {
+                self.memory().insert(path.to_path_buf(), None);
+                Ok(())
+            }
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        match self.memory().get(path) {
+            Some(data) => data.is_some(),
+            None => self.inner.exists(path),
+        }
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let memory = self.memory();
+        let mut files = match self.inner.list(dir) {
+            Ok(files) => files,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !memory.is_empty() => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        files.retain(|file| !matches!(memory.get(file), Some(None)));
+        for (file, data) in memory.iter() {
+            if data.is_some() && file.parent() == Some(dir) && !files.contains(file) {
+                files.push(file.clone());
+            }
+        }
+        Ok(files)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if self.mode() == SaveManagerMode::Normal && !self.memory().contains_key(from) {
+            self.inner.copy(from, to)?;
+            self.memory().remove(to);
+            return Ok(());
+        }
+        let data = self.read(from)?;
+        self.write(to, &data)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        if self.mode() == SaveManagerMode::Normal {
+            self.inner.sync(path)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        match self.memory().get(path) {
+            Some(Some(data)) => Ok(data.len() as u64),
+            Some(None) => Err(io::ErrorKind::NotFound.into()),
+            None => self.inner.size(path),
+        }
+    }
+
+    fn available_space(&self, dir: &Path) -> Option<u64> {
+        match self.mode() {
+            SaveManagerMode::Normal => self.inner.available_space(dir),
+            SaveManagerMode::ReadOnly | SaveManagerMode::Ephemeral => None,
+        }
+    }
+
+    fn reader(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self.memory().get(path) {
+            Some(Some(data)) => Ok(Box::new(io::Cursor::new(data.clone()))),
+            Some(None) => Err(io::ErrorKind::NotFound.into()),
+            None => self.inner.reader(path),
+        }
+    }
+
+    fn write_with(&self, path: &Path, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        match self.mode() {
+            SaveManagerMode::Normal => {
+                self.inner.write_with(path, write)?;
+                self.memory().remove(path);
+                Ok(())
+            }
+            SaveManagerMode::ReadOnly => Err(read_only()),
+            SaveManagerMode::Ephemeral => {
+                let mut data = Vec::new();
+                write(&mut data)?;
+                self.write(path, &data)
+            }
+        }
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "Saves are read-only")
+}
+
+/// Shared with the [`ModeBackend`]
+#[derive(Resource)]
+pub(crate) struct ModeSwitch(pub Arc<AtomicU8>);
+
+pub(crate) fn switch_mode(mode: Res<SaveManagerMode>, switch: Res<ModeSwitch>) {
+    switch.0.store(*mode as u8, Ordering::Relaxed);
+}
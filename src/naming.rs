@@ -0,0 +1,176 @@
+//! File names of new slots, checkpoints and delta bases, see [`NamingStrategy`]
+use crate::profile::CurrentProfile;
+use crate::save::unix_now;
+use bevy::prelude::Resource;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+/// Random names tried before numbering the last one, for strategies which always give the same name
+const RANDOM_TRIES: usize = 8;
+
+/// Generates the random parts of file names, from the seed of
+/// [`EncryptSavePlugin::with_naming_seed`](crate::save::EncryptSavePlugin::with_naming_seed) if set
+#[derive(Resource)]
+pub(crate) struct NamingRng(Mutex<fastrand::Rng>);
+
+impl NamingRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self(Mutex::new(
+            seed.map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed),
+        ))
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut fastrand::Rng) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// 12 random letters and digits
+    pub fn string(&self) -> String {
+        self.with(random_string)
+    }
+}
+
+/// File of the new slot `id` in the directory of `profile`, relative to the save directory
+pub(crate) fn new_save_file(
+    naming: &NamingStrategy,
+    rng: &NamingRng,
+    profile: &CurrentProfile,
+    id: u32,
+    taken: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    unique_file(
+        || {
+            let stem = rng.with(|rng| naming.stem_with(id, rng));
+            profile.dir().join(format!("{}.dat", stem))
+        },
+        taken,
+    )
+}
+
+/// Name from `generate` which is not `taken`, generated again a few times then numbered, so a collision never
+/// overwrites the file of another slot
+pub(crate) fn unique_file(mut generate: impl FnMut() -> PathBuf, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let mut file = generate();
+    for _ in 1..RANDOM_TRIES {
+        if !taken(&file) {
+            return file;
+        }
+        file = generate();
+    }
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = file
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned());
+    let mut numbered = file.clone();
+    let mut n = 2;
+    while taken(&numbered) {
+        numbered.set_file_name(format!("{}_{}", stem, n));
+        if let Some(extension) = &extension {
+            numbered.set_extension(extension);
+        }
+        n += 1;
+    }
+    numbered
+}
+
+/// How files of new slots are named, set with [`EncryptSavePlugin::with_naming`](crate::save::EncryptSavePlugin::with_naming)
+#[derive(Clone, Default)]
+pub enum NamingStrategy {
+    /// 12 random letters and digits
+    #[default]
+    Random,
+    /// `slot_003`
+    Slot,
+    /// UUIDv7, sorted by creation time
+    Uuid,
+    /// `2024-05-01_18-30-00_slot3`, in UTC
+    Timestamp,
+    /// Name without extension from the slot id, suffixed with a number if it collides with another file
+    Custom(Arc<dyn Fn(u32) -> String + Send + Sync>),
+}
+
+impl NamingStrategy {
+    pub fn file_stem(&self, id: u32) -> String {
+        self.stem_with(id, &mut fastrand::Rng::new())
+    }
+
+    fn stem_with(&self, id: u32, rng: &mut fastrand::Rng) -> String {
+        match self {
+            Self::Random => random_string(rng),
+            Self::Slot => format!("slot_{:03}", id),
+            Self::Uuid => uuid_v7(rng),
+            Self::Timestamp => {
+                let now = unix_now();
+                let (year, month, day) = civil_from_days((now / 86400) as i64);
+                let secs = now % 86400;
+                format!(
+                    "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}_slot{}",
+                    year,
+                    month,
+                    day,
+                    secs / 3600,
+                    secs % 3600 / 60,
+                    secs % 60,
+                    id
+                )
+            }
+            Self::Custom(name) => name(id),
+        }
+    }
+}
+
+fn uuid_v7(rng: &mut fastrand::Rng) -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+    let mut bytes: [u8; 16] = std::array::from_fn(|_| rng.u8(..));
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | (bytes[6] & 0x0f);
+    bytes[8] = 0x80 | (bytes[8] & 0x3f);
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Year, month and day of `days` since the Unix epoch, from Howard Hinnant's date algorithms
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn random_string(rng: &mut fastrand::Rng) -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    const LEN: usize = 12;
+
+    (0..LEN)
+        .map(|_| {
+            let idx = rng.usize(..CHARSET.len());
+            CHARSET[idx] as char
+        })
+        .collect()
+}
@@ -0,0 +1,266 @@
+//! Saves and settings in the Origin Private File System of the browser, for web builds. Files are stored as
+//! bytes, without the base64 encoding and the small quota of `localStorage`.
+use crate::backend::{
+    MemoryBackend,
+    SaveBackend,
+};
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use js_sys::Uint8Array;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::{
+    spawn_local,
+    JsFuture,
+};
+use web_sys::{
+    File,
+    FileSystemDirectoryHandle,
+    FileSystemFileHandle,
+    FileSystemGetDirectoryOptions,
+    FileSystemGetFileOptions,
+    FileSystemHandle,
+    FileSystemHandleKind,
+    FileSystemWritableFileStream,
+    StorageEstimate,
+    StorageManager,
+};
+
+/// [`SaveBackend`] storing its files in a directory of the Origin Private File System.
+///
+/// The browser only gives asynchronous access to it from the main thread, so the files are read once by
+/// [`OpfsBackend::open`] and served from memory. Writes are copied to the directory in the background, in order,
+/// and only logged if they fail.
+///
+/// Open it before building the app, e.g. in a `wasm_bindgen_futures::spawn_local` task which then runs the app
+/// with `EncryptSavePlugin::default().with_backend(backend)`.
+#[derive(Clone)]
+pub struct OpfsBackend {
+    /// Directory of the files inside the root of the Origin Private File System
+    dir: String,
+    files: MemoryBackend,
+    operations: async_channel::Sender<Operation>,
+    /// Bytes that could still be stored when opened, and the size of the files then
+    quota: Option<(u64, u64)>,
+}
+
+enum Operation {
+    Write(PathBuf, Vec<u8>),
+    Remove(PathBuf),
+}
+
+impl OpfsBackend {
+    /// Read every file of `dir`, created if needed. Persistent storage is requested, so the browser doesn't evict
+    /// the saves when the disk is low.
+    pub async fn open(dir: impl Into<String>) -> io::Result<Self> {
+        let dir = dir.into();
+        let storage = storage()?;
+        if let Ok(persist) = storage.persist() {
+            let _ = JsFuture::from(persist).await;
+        }
+
+        let files = MemoryBackend::default();
+        let root = directory(&dir, Path::new(""), true).await?;
+        let mut dirs = vec![(root, PathBuf::new())];
+        while let Some((handle, path)) = dirs.pop() {
+            let entries = handle.values();
+            loop {
+                let next = entries.next().map_err(js_error)?;
+                let next: js_sys::IteratorNext = JsFuture::from(next).await.map_err(js_error)?.unchecked_into();
+                if next.done() {
+                    break;
+                }
+                let entry: FileSystemHandle = next.value().unchecked_into();
+                let entry_path = path.join(entry.name());
+                match entry.kind() {
+                    FileSystemHandleKind::Directory => dirs.push((entry.unchecked_into(), entry_path)),
+                    _ => {
+                        let data = read_file(&entry.unchecked_into()).await?;
+                        files.write(&entry_path, &data)?;
+                    }
+                }
+            }
+        }
+
+        let estimate = match storage.estimate() {
+            Ok(estimate) => JsFuture::from(estimate).await.ok(),
+            Err(_) => None,
+        };
+        let stored = files.paths().iter().filter_map(|path| files.size(path).ok()).sum();
+        let quota = estimate
+            .map(|estimate| estimate.unchecked_into::<StorageEstimate>())
+            .and_then(|estimate| {
+                let free = estimate.get_quota()? - estimate.get_usage()?;
+                Some((free.max(0.0) as u64, stored))
+            });
+
+        let (operations, receiver) = async_channel::unbounded();
+        spawn_local(apply_operations(dir.clone(), receiver));
+        Ok(Self {
+            dir,
+            files,
+            operations,
+            quota,
+        })
+    }
+
+    /// Directory of the files inside the root of the Origin Private File System
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    fn send(&self, operation: Operation) -> io::Result<()> {
+        self.operations
+            .try_send(operation)
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "OPFS writer stopped"))
+    }
+}
+
+impl SaveBackend for OpfsBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.read(&key(path))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let key = key(path);
+        self.files.write(&key, data)?;
+        self.send(Operation::Write(key, data.to_vec()))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let key = key(path);
+        self.files.remove(&key)?;
+        self.send(Operation::Remove(key))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.exists(&key(path))
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .list(&key(dir))?
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| dir.join(name))
+            .collect())
+    }
+
+    /// Quota of the origin left when opened, minus the bytes stored since
+    fn available_space(&self, _dir: &Path) -> Option<u64> {
+        let (free, stored) = self.quota?;
+        let now = self
+            .files
+            .paths()
+            .iter()
+            .filter_map(|path| self.files.size(path).ok())
+            .sum::<u64>();
+        Some((free + stored).saturating_sub(now))
+    }
+}
+
+/// `path` without its root, as stored under the directory of the backend
+fn key(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+fn js_error(error: JsValue) -> io::Error {
+    let not_found = error
+        .dyn_ref::<js_sys::Error>()
+        .is_some_and(|error| error.name() == "NotFoundError");
+    let message = error
+        .dyn_ref::<js_sys::Error>()
+        .map(|error| String::from(error.message()))
+        .unwrap_or_else(|| format!("{:?}", error));
+    if not_found {
+        io::Error::new(ErrorKind::NotFound, message)
+    } else {
+        io::Error::other(message)
+    }
+}
+
+fn storage() -> io::Result<StorageManager> {
+    let window = web_sys::window().ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "No browser window"))?;
+    Ok(window.navigator().storage())
+}
+
+/// Handle of `path` inside the directory `root` of the Origin Private File System
+async fn directory(root: &str, path: &Path, create: bool) -> io::Result<FileSystemDirectoryHandle> {
+    let mut handle: FileSystemDirectoryHandle = JsFuture::from(storage()?.get_directory())
+        .await
+        .map_err(js_error)?
+        .unchecked_into();
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(create);
+    for name in Path::new(root).join(path).iter() {
+        let name = name.to_string_lossy();
+        handle = JsFuture::from(handle.get_directory_handle_with_options(&name, &options))
+            .await
+            .map_err(js_error)?
+            .unchecked_into();
+    }
+    Ok(handle)
+}
+
+async fn read_file(handle: &FileSystemFileHandle) -> io::Result<Vec<u8>> {
+    let file: File = JsFuture::from(handle.get_file())
+        .await
+        .map_err(js_error)?
+        .unchecked_into();
+    let buffer = JsFuture::from(file.array_buffer()).await.map_err(js_error)?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+async fn write_file(root: &str, path: &Path, data: &[u8]) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = directory(root, path.parent().unwrap_or(Path::new("")), true).await?;
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(true);
+    let file: FileSystemFileHandle = JsFuture::from(dir.get_file_handle_with_options(&name, &options))
+        .await
+        .map_err(js_error)?
+        .unchecked_into();
+    let stream: FileSystemWritableFileStream = JsFuture::from(file.create_writable())
+        .await
+        .map_err(js_error)?
+        .unchecked_into();
+    JsFuture::from(stream.write_with_u8_array(data).map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    // The file is only replaced once the stream is closed
+    JsFuture::from(stream.close()).await.map_err(js_error)?;
+    Ok(())
+}
+
+async fn remove_file(root: &str, path: &Path) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = directory(root, path.parent().unwrap_or(Path::new("")), false).await?;
+    JsFuture::from(dir.remove_entry(&name)).await.map_err(js_error)?;
+    Ok(())
+}
+
+/// Apply the writes and removals of a backend one after the other, so they land in the order they were made
+async fn apply_operations(root: String, operations: async_channel::Receiver<Operation>) {
+    while let Ok(operation) = operations.recv().await {
+        let (_path, result) = match &operation {
+            Operation::Write(path, data) => (path, write_file(&root, path, data).await),
+            Operation::Remove(path) => (path, remove_file(&root, path).await),
+        };
+        if let Err(_e) = result {
+            #[cfg(feature = "log")]
+            warn!("Failed to store {} in the browser: {}", _path.display(), _e);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Descriptions of the fields of settings, to generate options menus from any [`GameSetting`]
+use crate::setting::GameSetting;
+use bevy::reflect::serde::TypedReflectDeserializer;
+use bevy::reflect::{
+    PartialReflect,
+    Struct,
+    TypeInfo,
+    TypeRegistry,
+};
+use serde::de::DeserializeSeed;
+
+/// How an options menu should show a field of a setting, listed in [`GameSetting::UI`]
+/// or given with `#[setting_ui(...)]` on the field when deriving [`GameSetting`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SettingUi {
+    /// Name of the field
+    pub field: &'static str,
+    /// Shown instead of the name of the field
+    pub label: Option<&'static str>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Increment of sliders and spin boxes
+    pub step: Option<f64>,
+}
+
+impl SettingUi {
+    pub const fn new(field: &'static str) -> Self {
+        Self {
+            field,
+            label: None,
+            min: None,
+            max: None,
+            step: None,
+        }
+    }
+
+    pub const fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub const fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub const fn with_step(mut self, step: f64) -> Self {
+        self.step = Some(step);
+        self
+    }
+}
+
+/// A field of a setting with its [`SettingUi`] hints
+#[derive(Clone, Copy, Debug)]
+pub struct SettingField<'a> {
+    pub name: &'a str,
+    /// Label of the hints, or the name of the field
+    pub label: &'a str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: Option<f64>,
+    /// Current value, to pick a widget from its type. Changes are written back with [`Struct::field_mut`].
+    pub value: &'a dyn PartialReflect,
+}
+
+impl SettingField<'_> {
+    /// Variants of an enum field, for a drop-down list
+    pub fn variants(&self) -> Option<&'static [&'static str]> {
+        match self.value.get_represented_type_info()? {
+            TypeInfo::Enum(info) => Some(info.variant_names()),
+            _ => None,
+        }
+    }
+}
+
+/// Reflected fields of `setting`, in declaration order
+pub fn setting_fields<T>(setting: &T) -> impl Iterator<Item = SettingField<'_>>
+where
+    T: GameSetting + Struct,
+{
+    (0..setting.field_len()).filter_map(|index| {
+        let name = setting.name_at(index)?;
+        let ui = T::UI.iter().find(|ui| ui.field == name);
+        Some(SettingField {
+            name,
+            label: ui.and_then(|ui| ui.label).unwrap_or(name),
+            min: ui.and_then(|ui| ui.min),
+            max: ui.and_then(|ui| ui.max),
+            step: ui.and_then(|ui| ui.step),
+            value: setting.field_at(index)?,
+        })
+    })
+}
+
+/// Set `field` to `value` written in RON, strings may go without quotes. The type of `field` must be in `registry`.
+pub(crate) fn set_from_ron(field: &mut dyn PartialReflect, value: &str, registry: &TypeRegistry) -> Result<(), String> {
+    if let Some(text) = field.try_downcast_mut::<String>() {
+        *text = ron::from_str(value).unwrap_or_else(|_| value.to_string());
+        return Ok(());
+    }
+    let registration = field
+        .get_represented_type_info()
+        .and_then(|info| registry.get(info.type_id()))
+        .ok_or_else(|| format!("{} can't be set", field.reflect_type_path()))?;
+    let mut deserializer = ron::Deserializer::from_str(value).map_err(|e| e.to_string())?;
+    let parsed = TypedReflectDeserializer::new(registration, registry)
+        .deserialize(&mut deserializer)
+        .map_err(|e| format!("Invalid value {}: {}", value, e))?;
+    field.try_apply(parsed.as_ref()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,303 @@
+//! Values of a setting downloaded from a server at startup and applied over the local ones by
+//! [`RemoteConfigPlugin`], for live tuning and kill-switches
+use crate::error::SettingError;
+use crate::setting::{
+    load_config,
+    GameSetting,
+    SettingFormat,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    resource_changed,
+    IntoScheduleConfigs,
+    Message,
+    MessageWriter,
+    Plugin,
+    PostUpdate,
+    Res,
+    ResMut,
+    Resource,
+    Startup,
+    SystemCondition,
+    Update,
+};
+use bevy::reflect::{
+    DynamicStruct,
+    GetTypeRegistration,
+    PartialReflect,
+    ReflectRef,
+    Struct,
+};
+use bevy::tasks::IoTaskPool;
+use std::marker::PhantomData;
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Download a file of setting `T` from `url` at startup, and apply the fields it has over the local values.
+/// The file can hold only the fields to tune, the fields `T` doesn't have are ignored.
+///
+/// The downloaded values are a read-only layer: they are never written to the local file, and changes to them
+/// only update the local values, used again once the server stops sending them. `T` must be loaded by a
+/// [`GameSettingSupportPlugin`](crate::setting::GameSettingSupportPlugin).
+pub struct RemoteConfigPlugin<T> {
+    url: String,
+    format: SettingFormat,
+    timeout: Duration,
+    _setting: PhantomData<T>,
+}
+
+impl<T> RemoteConfigPlugin<T>
+where
+    T: GameSetting,
+{
+    /// The file is read in [`GameSetting::FORMAT`]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: T::FORMAT,
+            timeout: Duration::from_secs(10),
+            _setting: PhantomData,
+        }
+    }
+
+    pub fn with_format(mut self, format: SettingFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Give up on the download after `timeout`, 10 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<T> Plugin for RemoteConfigPlugin<T>
+where
+    T: Resource + GameSetting + Struct + GetTypeRegistration + Clone,
+{
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        app.insert_resource(RemoteOverlay::<T>::new())
+            .insert_resource(RemoteDownload::<T> {
+                url: self.url.clone(),
+                format: self.format,
+                timeout: self.timeout,
+                sender,
+                receiver: Mutex::new(receiver),
+                _setting: PhantomData,
+            })
+            .add_message::<RemoteConfigLoaded<T>>()
+            .add_message::<RemoteConfigFailed<T>>()
+            .add_systems(Startup, start_download::<T>.after(load_config::<T>))
+            .add_systems(Update, receive_download::<T>)
+            .add_systems(
+                PostUpdate,
+                apply_overlay::<T>.run_if(resource_changed::<T>.or(resource_changed::<RemoteOverlay<T>>)),
+            );
+    }
+}
+
+/// The file of setting `T` was downloaded and applied
+#[derive(Message)]
+pub struct RemoteConfigLoaded<T> {
+    pub url: String,
+    _setting: PhantomData<T>,
+}
+
+/// The file of setting `T` could not be downloaded or parsed, the local values are kept
+#[derive(Message, Debug)]
+pub struct RemoteConfigFailed<T> {
+    pub url: String,
+    pub error: SettingError,
+    _setting: PhantomData<T>,
+}
+
+/// Downloaded values of setting `T`, and the local values they hide
+#[derive(Resource)]
+pub struct RemoteOverlay<T> {
+    /// Fields found in the download, `None` until it is applied
+    remote: Option<DynamicStruct>,
+    /// Local values of the fields of `remote`, with the same nested fields
+    local: DynamicStruct,
+    unmerge: fn(&T, &DynamicStruct) -> T,
+}
+
+impl<T> RemoteOverlay<T>
+where
+    T: Struct + Clone,
+{
+    fn new() -> Self {
+        Self {
+            remote: None,
+            local: DynamicStruct::default(),
+            unmerge: with_fields::<T>,
+        }
+    }
+}
+
+impl<T> RemoteOverlay<T> {
+    /// Whether the download was applied
+    pub fn is_loaded(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    /// Whether the value of `field` comes from the server, even partly for a struct
+    pub fn is_remote(&self, field: &str) -> bool {
+        self.get(field).is_some()
+    }
+
+    /// Names of the fields whose values come from the server
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.remote
+            .iter()
+            .flat_map(|remote| (0..remote.field_len()).filter_map(|index| remote.name_at(index)))
+    }
+
+    /// Value of `field` sent by the server. Structs only have the fields that were sent.
+    pub fn get(&self, field: &str) -> Option<&dyn PartialReflect> {
+        self.remote.as_ref()?.field(field)
+    }
+
+    /// `config` with the local values instead of the downloaded ones, as written to the file
+    pub(crate) fn local_values(&self, config: &T) -> Option<T> {
+        self.remote.as_ref().map(|_| (self.unmerge)(config, &self.local))
+    }
+}
+
+#[derive(Resource)]
+struct RemoteDownload<T> {
+    url: String,
+    format: SettingFormat,
+    timeout: Duration,
+    sender: Sender<Result<Vec<u8>, SettingError>>,
+    receiver: Mutex<Receiver<Result<Vec<u8>, SettingError>>>,
+    _setting: PhantomData<T>,
+}
+
+fn fetch(url: &str, timeout: Duration) -> Result<Vec<u8>, SettingError> {
+    attohttpc::get(url)
+        .timeout(timeout)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map_err(|e| SettingError::Download(e.into()))
+}
+
+fn start_download<T>(download: Res<RemoteDownload<T>>)
+where
+    T: Resource,
+{
+    let url = download.url.clone();
+    let timeout = download.timeout;
+    let sender = download.sender.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            let _ = sender.send(fetch(&url, timeout));
+        })
+        .detach();
+}
+
+fn receive_download<T>(
+    download: Res<RemoteDownload<T>>,
+    mut overlay: ResMut<RemoteOverlay<T>>,
+    mut loaded: MessageWriter<RemoteConfigLoaded<T>>,
+    mut failed: MessageWriter<RemoteConfigFailed<T>>,
+) where
+    T: Resource + GetTypeRegistration,
+{
+    let Some(result) = download
+        .receiver
+        .lock()
+        .ok()
+        .and_then(|receiver| receiver.try_recv().ok())
+    else {
+        return;
+    };
+    let fields = result
+        .and_then(|data| download.format.deserialize_partial::<T>(&data))
+        .and_then(|value| match value.reflect_ref() {
+            ReflectRef::Struct(fields) => Ok(fields.to_dynamic_struct()),
+            _ => Err(SettingError::Format("Remote setting is not a struct".into())),
+        });
+    match fields {
+        Ok(fields) => {
+            overlay.remote = Some(fields);
+            loaded.write(RemoteConfigLoaded {
+                url: download.url.clone(),
+                _setting: PhantomData,
+            });
+        }
+        Err(error) => {
+            #[cfg(feature = "log")]
+            warn!("Failed to load remote config {}: {}", download.url, error);
+            failed.write(RemoteConfigFailed {
+                url: download.url.clone(),
+                error,
+                _setting: PhantomData,
+            });
+        }
+    }
+}
+
+/// Keep the downloaded values over the local ones. A value changed under them, by the player or a load,
+/// becomes the new local value.
+fn apply_overlay<T>(mut config: ResMut<T>, mut overlay: ResMut<RemoteOverlay<T>>)
+where
+    T: Resource + Struct,
+{
+    let overlay = &mut *overlay;
+    let Some(remote) = &overlay.remote else {
+        return;
+    };
+    for (index, value) in remote.iter_fields().enumerate() {
+        let Some(name) = remote.name_at(index) else {
+            continue;
+        };
+        let Some(current) = config.field(name) else {
+            continue;
+        };
+        let mut merged = current.to_dynamic();
+        if merged.try_apply(value).is_err() || merged.reflect_partial_eq(current) == Some(true) {
+            continue;
+        }
+        overlay.local.insert_boxed(name, restrict(current, value));
+        if let Some(field) = config.field_mut(name) {
+            let _ = field.try_apply(merged.as_ref());
+        }
+    }
+}
+
+/// Fields of `value` that `shape` has, recursively for structs
+fn restrict(value: &dyn PartialReflect, shape: &dyn PartialReflect) -> Box<dyn PartialReflect> {
+    let (ReflectRef::Struct(value), ReflectRef::Struct(shape)) = (value.reflect_ref(), shape.reflect_ref()) else {
+        return value.to_dynamic();
+    };
+    let mut fields = DynamicStruct::default();
+    for (index, field) in shape.iter_fields().enumerate() {
+        let Some(name) = shape.name_at(index) else {
+            continue;
+        };
+        if let Some(value) = value.field(name) {
+            fields.insert_boxed(name, restrict(value, field));
+        }
+    }
+    Box::new(fields)
+}
+
+/// `config` with `fields` applied over it
+fn with_fields<T>(config: &T, fields: &DynamicStruct) -> T
+where
+    T: Struct + Clone,
+{
+    let mut config = config.clone();
+    let _ = config.try_apply(fields);
+    config
+}
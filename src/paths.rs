@@ -0,0 +1,192 @@
+//! Base directories of saves and settings, chosen by [`PortableMode`], [`Project`] and [`SavePaths`]
+use crate::portable::{
+    exe_dir,
+    PortableMode,
+};
+use crate::project::Project;
+#[cfg(all(target_os = "ios", feature = "log"))]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    Res,
+    ResMut,
+    Resource,
+};
+use directories::ProjectDirs;
+use std::borrow::Cow;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// Overrides of the base directories, to redirect every file without recompiling, e.g. inside Flatpak or Snap
+/// sandboxes or on servers. Insert it before startup, later changes apply to the files read or written afterwards.
+#[derive(Resource, Clone, Default, Debug, PartialEq, Eq)]
+pub struct SavePaths {
+    /// Replaces [`SaveDirs::data_dir`]
+    pub data_dir: Option<PathBuf>,
+    /// Replaces [`SaveDirs::config_dir`]
+    pub config_dir: Option<PathBuf>,
+    /// Keep settings in the configuration directory of the platform, `XDG_CONFIG_HOME` on Linux,
+    /// rather than with the saves
+    pub platform_config_dir: bool,
+    /// On Android, store the files in the external files directory of the app, which can be reached over USB,
+    /// instead of the internal one
+    pub android_external_storage: bool,
+    /// On iOS, leave the saves in `Documents` out of iCloud backups
+    pub ios_exclude_saves_from_backup: bool,
+    /// On iOS, leave the settings in `Library/Application Support` out of iCloud backups
+    pub ios_exclude_settings_from_backup: bool,
+}
+
+/// Base directories of saves and settings, resolved from [`SavePaths`], [`PortableMode`] and the [`Project`]
+/// each time one of them changes
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct SaveDirs {
+    data_dir: PathBuf,
+    config_dir: PathBuf,
+    /// Whether [`Self::data_dir`] was chosen by [`SavePaths`], [`PortableMode`], a [`Project`] or a mobile platform
+    chosen: bool,
+}
+
+impl Default for SaveDirs {
+    fn default() -> Self {
+        Self::new(&SavePaths::default(), PortableMode::default(), None)
+    }
+}
+
+impl SaveDirs {
+    pub fn new(paths: &SavePaths, portable: PortableMode, project: Option<&Project>) -> Self {
+        let portable = portable.is_active();
+        let project = project.and_then(Project::dirs);
+        let data_dir = resolve_data_dir(paths, portable, project.as_ref());
+        let config_dir = resolve_config_dir(paths, portable, project.as_ref(), &data_dir);
+        let chosen = cfg!(any(target_os = "android", target_os = "ios"))
+            || paths.data_dir.is_some()
+            || portable
+            || project.is_some();
+        Self {
+            data_dir,
+            config_dir,
+            chosen,
+        }
+    }
+
+    /// Directory of the global saves and of relative save directories: next to the executable in portable mode,
+    /// the files directory of the app on Android, `Documents` on iOS so saves show in the Files app, else the
+    /// folder of the [`Project`] in the local data directory of the user, `XDG_DATA_HOME` on Linux
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Directory of the settings, `Library/Application Support` on iOS, [`Self::data_dir`] elsewhere unless
+    /// [`SavePaths`] sets another one
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// `path` relative to [`Self::data_dir`] when it is chosen by [`SavePaths`], [`PortableMode`], a [`Project`]
+    /// or a mobile platform, as is otherwise
+    pub fn resolve<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if path.is_relative() && self.chosen {
+            Cow::Owned(self.data_dir.join(path))
+        } else {
+            Cow::Borrowed(path)
+        }
+    }
+}
+
+fn resolve_data_dir(paths: &SavePaths, portable: bool, project: Option<&ProjectDirs>) -> PathBuf {
+    if let Some(dir) = &paths.data_dir {
+        dir.clone()
+    } else if cfg!(target_os = "android") {
+        android_files_dir(paths.android_external_storage).unwrap_or_default()
+    } else if cfg!(target_os = "ios") {
+        dirs::document_dir().unwrap_or_default()
+    } else if portable {
+        exe_dir().unwrap_or_default()
+    } else if let Some(project) = project {
+        project.data_local_dir().to_path_buf()
+    } else {
+        dirs::data_local_dir().unwrap_or_default()
+    }
+}
+
+fn resolve_config_dir(paths: &SavePaths, portable: bool, project: Option<&ProjectDirs>, data_dir: &Path) -> PathBuf {
+    if let Some(dir) = &paths.config_dir {
+        dir.clone()
+    } else if cfg!(target_os = "ios") && paths.data_dir.is_none() {
+        dirs::data_local_dir().unwrap_or_default()
+    } else if paths.platform_config_dir && paths.data_dir.is_none() && !portable {
+        match project {
+            Some(project) => project.config_local_dir().to_path_buf(),
+            None => dirs::config_local_dir().unwrap_or_else(|| data_dir.to_path_buf()),
+        }
+    } else {
+        data_dir.to_path_buf()
+    }
+}
+
+/// `/data/data/<package>/files` or its external counterpart, known once the activity of the app started
+#[cfg(target_os = "android")]
+fn android_files_dir(external: bool) -> Option<PathBuf> {
+    let app = bevy::android::ANDROID_APP.get()?;
+    if external {
+        app.external_data_path()
+    } else {
+        app.internal_data_path()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+fn android_files_dir(_external: bool) -> Option<PathBuf> {
+    None
+}
+
+pub(crate) fn update_save_dirs(
+    paths: Res<SavePaths>,
+    portable: Res<PortableMode>,
+    project: Option<Res<Project>>,
+    mut dirs: ResMut<SaveDirs>,
+) {
+    *dirs = SaveDirs::new(&paths, *portable, project.as_deref());
+    #[cfg(target_os = "ios")]
+    apply_backup_flags(&paths, &dirs);
+}
+
+/// Create the directories of saves and settings and set whether iCloud backs them up
+#[cfg(target_os = "ios")]
+fn apply_backup_flags(paths: &SavePaths, dirs: &SaveDirs) {
+    let flags = [
+        (dirs.data_dir(), paths.ios_exclude_saves_from_backup),
+        (dirs.config_dir(), paths.ios_exclude_settings_from_backup),
+    ];
+    for (dir, exclude) in flags {
+        let result = std::fs::create_dir_all(dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| exclude_from_backup(dir, exclude));
+        if let Err(_e) = result {
+            #[cfg(feature = "log")]
+            warn!("Failed to set the backup flag of {}: {}", dir.display(), _e);
+        }
+    }
+}
+
+#[cfg(target_os = "ios")]
+fn exclude_from_backup(dir: &Path, exclude: bool) -> Result<(), String> {
+    use objc2::runtime::AnyObject;
+    use objc2_foundation::{
+        NSNumber,
+        NSString,
+        NSURLIsExcludedFromBackupKey,
+        NSURL,
+    };
+
+    let path = NSString::from_str(&dir.to_string_lossy());
+    let number = NSNumber::new_bool(exclude);
+    let value: &AnyObject = &number;
+    unsafe {
+        let url = NSURL::fileURLWithPath_isDirectory(&path, true);
+        url.setResourceValue_forKey_error(Some(value), NSURLIsExcludedFromBackupKey)
+            .map_err(|e| e.localizedDescription().to_string())
+    }
+}
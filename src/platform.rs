@@ -0,0 +1,112 @@
+//! Save data of consoles, which has to be mounted before use and committed after writes, see [`PlatformSaveBackend`]
+
+use crate::backend::SaveBackend;
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Mutex,
+    MutexGuard,
+};
+
+/// Save data storage of a platform SDK, e.g. the save data of Switch or PlayStation.
+///
+/// The storage is mounted before the first access and stays mounted until the backend is dropped.
+/// Every write and removal is committed right away, so a save is durable once the plugin reports it.
+/// Wrap it in a [`MountedBackend`] or pass it to
+/// [`EncryptSavePlugin::with_platform_backend`](crate::save::EncryptSavePlugin::with_platform_backend).
+pub trait PlatformSaveBackend: Send + 'static {
+    /// Make the save data accessible
+    fn mount(&mut self) -> io::Result<()>;
+    /// Persist the writes done since the last commit
+    fn commit(&mut self) -> io::Result<()>;
+    /// Release the save data
+    fn unmount(&mut self) {}
+    fn read(&mut self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()>;
+    fn remove(&mut self, path: &Path) -> io::Result<()>;
+    fn exists(&mut self, path: &Path) -> bool;
+    /// Files directly inside `dir`
+    fn list(&mut self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+    /// Bytes that can still be written in `dir`, `None` if unknown
+    fn available_space(&mut self, _dir: &Path) -> Option<u64> {
+        None
+    }
+}
+
+struct Mount<P> {
+    platform: P,
+    mounted: bool,
+}
+
+/// [`SaveBackend`] mounting a [`PlatformSaveBackend`] on first use and committing after every change.
+/// Calls are serialized, platform SDKs rarely allow concurrent access to save data.
+pub struct MountedBackend<P: PlatformSaveBackend>(Mutex<Mount<P>>);
+
+impl<P: PlatformSaveBackend> MountedBackend<P> {
+    pub fn new(platform: P) -> Self {
+        Self(Mutex::new(Mount {
+            platform,
+            mounted: false,
+        }))
+    }
+
+    fn mounted(&self) -> io::Result<MutexGuard<'_, Mount<P>>> {
+        let mut mount = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !mount.mounted {
+            mount.platform.mount()?;
+            mount.mounted = true;
+        }
+        Ok(mount)
+    }
+
+    fn change(&self, change: impl FnOnce(&mut P) -> io::Result<()>) -> io::Result<()> {
+        let mut mount = self.mounted()?;
+        change(&mut mount.platform)?;
+        mount.platform.commit()
+    }
+}
+
+impl<P: PlatformSaveBackend> SaveBackend for MountedBackend<P> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.mounted()?.platform.read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.change(|platform| platform.write(path, data))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.change(|platform| platform.remove(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.mounted().is_ok_and(|mut mount| mount.platform.exists(path))
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        self.mounted()?.platform.list(dir)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.change(|platform| {
+            let data = platform.read(from)?;
+            platform.write(to, &data)
+        })
+    }
+
+    fn available_space(&self, dir: &Path) -> Option<u64> {
+        self.mounted().ok()?.platform.available_space(dir)
+    }
+}
+
+impl<P: PlatformSaveBackend> Drop for MountedBackend<P> {
+    fn drop(&mut self) {
+        let mount = self.0.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if mount.mounted {
+            mount.platform.unmount();
+        }
+    }
+}
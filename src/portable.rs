@@ -0,0 +1,36 @@
+//! Saves and settings stored next to the executable, for zip distributions and installs on USB sticks
+use bevy::prelude::Resource;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// File next to the executable turning [`PortableMode::Detect`] on
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+/// Whether saves and settings are stored next to the executable instead of the local data directory of the user.
+/// Insert it or use `EncryptSavePlugin::with_portable_mode`, later changes apply to the files read or written
+/// afterwards.
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum PortableMode {
+    #[default]
+    Never,
+    Always,
+    /// When a [`PORTABLE_MARKER`] file is next to the executable
+    Detect,
+}
+
+impl PortableMode {
+    /// Whether paths are resolved next to the executable
+    pub fn is_active(self) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Always => exe_dir().is_some(),
+            Self::Detect => exe_dir().is_some_and(|dir| dir.join(PORTABLE_MARKER).is_file()),
+        }
+    }
+}
+
+pub(crate) fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
@@ -0,0 +1,250 @@
+use crate::backend::SaveStorage;
+use crate::paths::{
+    update_save_dirs,
+    SaveDirs,
+};
+use crate::save::{
+    CurrentSave,
+    SaveConfig,
+};
+use crate::setting::{
+    GameSetting,
+    GameSettingChanged,
+    GameSettingSupportPlugin,
+    SettingFile,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    on_message,
+    Deref,
+    IntoScheduleConfigs,
+    Message,
+    MessageReader,
+    MessageWriter,
+    Plugin,
+    PreStartup,
+    Res,
+    ResMut,
+    Resource,
+    Update,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::path::PathBuf;
+
+/// Player whose saves and settings are in use. The default profile, with an empty name,
+/// uses the files directly in the save and settings directories.
+#[derive(Resource, Deref, Clone, Default, Debug, PartialEq, Eq)]
+pub struct CurrentProfile(String);
+
+impl CurrentProfile {
+    pub fn is_default(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Subdirectory holding the files of this profile, relative to the save or settings directory
+    pub fn dir(&self) -> PathBuf {
+        if self.is_default() {
+            PathBuf::new()
+        } else {
+            PathBuf::from("profiles").join(&self.0)
+        }
+    }
+}
+
+/// Profiles created so far and the one to restore at startup
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Profiles {
+    names: Vec<String>,
+    current: String,
+}
+
+impl Profiles {
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        name.is_empty() || self.names.iter().any(|profile| profile == name)
+    }
+}
+
+impl GameSetting for Profiles {
+    const DEFAULT_CONF: &'static str = "profiles.conf";
+    const PER_PROFILE: bool = false;
+}
+
+#[derive(Message)]
+pub struct CreateProfile(pub String);
+
+/// Switch to an existing profile, or to the default one with an empty name.
+/// Every setting and the save list are reloaded from the files of the new profile.
+#[derive(Message)]
+pub struct SwitchProfile(pub String);
+
+/// Delete a profile with its saves and settings. The default profile is used if it was the current one.
+#[derive(Message)]
+pub struct DeleteProfile(pub String);
+
+#[derive(Message, Deref)]
+pub struct ProfileCreated(pub String);
+
+#[derive(Message, Deref)]
+pub struct ProfileSwitched(pub String);
+
+#[derive(Message, Deref)]
+pub struct ProfileDeleted(pub String);
+
+/// Keep saves and settings of several players apart, see [`CurrentProfile`]
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<GameSettingSupportPlugin<Profiles>>() {
+            app.add_plugins(GameSettingSupportPlugin::<Profiles>::default());
+        }
+
+        // Read to delete the saves of a profile, also without the save plugin
+        app.init_resource::<CurrentProfile>()
+            .init_resource::<SettingFile<SaveConfig>>()
+            .add_message::<CreateProfile>()
+            .add_message::<SwitchProfile>()
+            .add_message::<DeleteProfile>()
+            .add_message::<ProfileCreated>()
+            .add_message::<ProfileSwitched>()
+            .add_message::<ProfileDeleted>()
+            .add_systems(PreStartup, restore_profile.after(update_save_dirs))
+            .add_systems(
+                Update,
+                (
+                    on_create.run_if(on_message::<CreateProfile>),
+                    on_delete.run_if(on_message::<DeleteProfile>),
+                    on_switch.run_if(on_message::<SwitchProfile>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Settings are loaded at `Startup`, the profile has to be known before
+fn restore_profile(
+    mut profiles: ResMut<Profiles>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    mut current: ResMut<CurrentProfile>,
+) {
+    if profiles
+        .load_with(storage.0.as_ref(), &Profiles::config_path(&dirs))
+        .is_ok()
+        && profiles.contains(&profiles.current)
+    {
+        current.0 = profiles.current.clone();
+    }
+}
+
+fn on_create(
+    mut create: MessageReader<CreateProfile>,
+    mut profiles: ResMut<Profiles>,
+    mut created: MessageWriter<ProfileCreated>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for CreateProfile(name) in create.read() {
+        if !is_valid_name(name) || profiles.contains(name) {
+            #[cfg(feature = "log")]
+            warn!("Cannot create profile {:?}", name);
+            continue;
+        }
+        profiles.names.push(name.clone());
+        created.write(ProfileCreated(name.clone()));
+        setting_changed.write(GameSettingChanged);
+    }
+}
+
+pub(crate) fn on_switch(
+    mut switch: MessageReader<SwitchProfile>,
+    mut profiles: ResMut<Profiles>,
+    mut current: ResMut<CurrentProfile>,
+    mut current_save: Option<ResMut<CurrentSave>>,
+    mut switched: MessageWriter<ProfileSwitched>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for SwitchProfile(name) in switch.read() {
+        if !profiles.contains(name) {
+            #[cfg(feature = "log")]
+            warn!("Profile {:?} does not exist", name);
+            continue;
+        }
+        if current.0 == *name {
+            continue;
+        }
+
+        current.0 = name.clone();
+        profiles.current = name.clone();
+        if let Some(current_save) = current_save.as_mut() {
+            current_save.0 = 0;
+        }
+        switched.write(ProfileSwitched(name.clone()));
+        setting_changed.write(GameSettingChanged);
+    }
+}
+
+fn on_delete(
+    mut delete: MessageReader<DeleteProfile>,
+    mut profiles: ResMut<Profiles>,
+    current: Res<CurrentProfile>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    config_file: Res<SettingFile<SaveConfig>>,
+    mut switch: MessageWriter<SwitchProfile>,
+    mut deleted: MessageWriter<ProfileDeleted>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for DeleteProfile(name) in delete.read() {
+        if name.is_empty() || !profiles.contains(name) {
+            continue;
+        }
+
+        let profile = CurrentProfile(name.clone());
+        // Save files are in the save directory of the profile, which is only known by its own config
+        let mut save_config = SaveConfig::default();
+        let config_path = config_file.profile_config_path(&dirs, &profile);
+        if config_file
+            .load_with(&mut save_config, storage.0.as_ref(), &config_path)
+            .is_ok()
+        {
+            for (id, slot) in save_config.slots() {
+                if let Some(path) = save_config.slot_path(&dirs, *id) {
+                    let _ = storage.remove(&path);
+                }
+                for file in slot.thumbnail.iter().chain(&slot.base) {
+                    let _ = storage.remove(&save_config.save_dir(&dirs).join(file));
+                }
+            }
+            for checkpoint in save_config.checkpoints() {
+                let _ = storage.remove(&save_config.save_dir(&dirs).join(&checkpoint.file));
+            }
+        }
+        if let Some(settings_dir) = config_path.parent() {
+            for file in storage.list(settings_dir).unwrap_or_default() {
+                let _ = storage.remove(&file);
+            }
+        }
+
+        profiles.names.retain(|profile| profile != name);
+        if *current == profile {
+            switch.write(SwitchProfile(String::new()));
+        }
+        deleted.write(ProfileDeleted(name.clone()));
+        setting_changed.write(GameSettingChanged);
+    }
+}
+
+/// Names are used as directory names
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
@@ -0,0 +1,128 @@
+//! Progress of the running slot save and load, for loading screens, see [`SaveProgress`] and [`LoadProgress`]
+use crate::io::{
+    SaveFailed,
+    SaveWritten,
+};
+use crate::save::{
+    LoadFailed,
+    LoadRecentFailed,
+};
+use bevy::prelude::{
+    MessageReader,
+    ResMut,
+    Resource,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SavePhase {
+    #[default]
+    Idle,
+    /// Registered resources and persisted entities are being serialized, over several frames with
+    /// [`EncryptSavePlugin::with_sliced_saves`](crate::save::EncryptSavePlugin::with_sliced_saves)
+    Capturing,
+    /// Data is handed over to be encrypted and written on the `IoTaskPool`
+    Writing,
+    Done,
+    Failed,
+}
+
+/// Last slot save, updated as it goes through the pipeline.
+/// Checkpoints, snapshots and settings are not tracked.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SaveProgress {
+    /// `None` until the id of a new slot is allocated
+    pub slot: Option<u32>,
+    pub phase: SavePhase,
+    /// Completion of the whole save, from 0 to 1
+    pub fraction: f32,
+    /// Serialized bytes while capturing, size of the file once written
+    pub bytes: u64,
+}
+
+impl SaveProgress {
+    /// `captured` of the `sections` are serialized, writing the file is counted as one more step
+    pub(crate) fn capturing(&mut self, slot: u32, captured: usize, sections: usize, bytes: u64) {
+        let phase = if captured < sections { SavePhase::Capturing } else { SavePhase::Writing };
+        *self = Self {
+            slot: Some(slot),
+            phase,
+            fraction: captured as f32 / (sections + 1) as f32,
+            bytes,
+        };
+    }
+
+    pub(crate) fn done(&mut self, slot: u32, bytes: u64) {
+        *self = Self {
+            slot: Some(slot),
+            phase: SavePhase::Done,
+            fraction: 1.0,
+            bytes,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoadPhase {
+    #[default]
+    Idle,
+    /// A write of the slot file is still in flight, see [`SlotBusy`](crate::save::SlotBusy)
+    Waiting,
+    Done,
+    Failed,
+}
+
+/// Last slot load. The file is read, decoded and applied within one frame, so a load is only seen waiting
+/// or finished: show the loading screen the frame [`LoadGame`](crate::save::LoadGame) is sent.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LoadProgress {
+    /// `None` for [`LoadRecent`](crate::save::LoadRecent) until a slot is loaded
+    pub slot: Option<u32>,
+    pub phase: LoadPhase,
+    /// Completion of the load, from 0 to 1
+    pub fraction: f32,
+    /// Size of the loaded file
+    pub bytes: u64,
+}
+
+impl LoadProgress {
+    pub(crate) fn set(&mut self, slot: Option<u32>, phase: LoadPhase, bytes: u64) {
+        let fraction = if phase == LoadPhase::Done { 1.0 } else { 0.0 };
+        *self = Self {
+            slot,
+            phase,
+            fraction,
+            bytes,
+        };
+    }
+}
+
+/// Finish the save progress with the outcome of the write of its file
+pub(crate) fn track_writes(
+    mut written: MessageReader<SaveWritten>,
+    mut failed: MessageReader<SaveFailed>,
+    mut progress: ResMut<SaveProgress>,
+) {
+    for msg in written.read() {
+        if progress.slot == Some(msg.slot) && progress.phase == SavePhase::Writing {
+            progress.done(msg.slot, msg.size);
+        }
+    }
+    for msg in failed.read() {
+        if msg.slot.is_some() && progress.slot == msg.slot {
+            progress.phase = SavePhase::Failed;
+        }
+    }
+}
+
+pub(crate) fn track_load_failures(
+    mut failed: MessageReader<LoadFailed>,
+    mut recent_failed: MessageReader<LoadRecentFailed>,
+    mut progress: ResMut<LoadProgress>,
+) {
+    for msg in failed.read() {
+        progress.set(Some(msg.slot), LoadPhase::Failed, 0);
+    }
+    for _ in recent_failed.read() {
+        progress.set(None, LoadPhase::Failed, 0);
+    }
+}
@@ -0,0 +1,29 @@
+//! Folder of the game inside the local data directory of the user, so games don't share their files
+use bevy::prelude::Resource;
+use directories::ProjectDirs;
+
+/// Identifies the game like [`ProjectDirs`], e.g. `Project::new("com", "Foo Corp", "Bar App")`.
+/// Insert it or use `EncryptSavePlugin::with_project`, files are stored at the root of the local data directory
+/// otherwise.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Project {
+    /// Reverse domain name of the organization, only used on macOS
+    pub qualifier: String,
+    pub organization: String,
+    pub application: String,
+}
+
+impl Project {
+    pub fn new(qualifier: impl Into<String>, organization: impl Into<String>, application: impl Into<String>) -> Self {
+        Self {
+            qualifier: qualifier.into(),
+            organization: organization.into(),
+            application: application.into(),
+        }
+    }
+
+    /// Directories of the project, `None` when the home directory of the user is unknown
+    pub fn dirs(&self) -> Option<ProjectDirs> {
+        ProjectDirs::from(&self.qualifier, &self.organization, &self.application)
+    }
+}
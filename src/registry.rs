@@ -0,0 +1,639 @@
+use crate::error::SaveError;
+use crate::save::{
+    EncryptSave,
+    SaveEncoding,
+};
+use crate::transform::Transforms;
+use bevy::app::App;
+use bevy::prelude::{
+    Resource,
+    World,
+};
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize,
+};
+use std::any::{
+    type_name,
+    Any,
+};
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+
+pub(crate) type Staged = Box<dyn Any + Send + Sync>;
+/// Puts back what a section replaced when it was applied
+pub(crate) type Undo = Box<dyn FnOnce(&mut World) + Send + Sync>;
+/// Inserts a staged section into the world, leaving the world as it was if it fails
+pub(crate) type Apply = fn(&mut World, Staged) -> Result<Undo, SaveError>;
+/// Decoded section with the function inserting it into the world
+pub(crate) type StagedSection = (Apply, Staged);
+
+/// Empty section marking data as a list of sections, legacy saves of the main resource alone may decode as one
+const SECTIONS_SECTION: &str = "bevy_save_manager::sections";
+/// Section holding [`EncryptSave::VERSION`], absent from saves of version 0
+const VERSION_SECTION: &str = "bevy_save_manager::version";
+/// Section holding the [`SaveEncoding`] of the other sections, absent from legacy saves
+const ENCODING_SECTION: &str = "bevy_save_manager::encoding";
+/// Section holding the names of the [`SaveTransform`](crate::transform::SaveTransform)s run on a save
+const TRANSFORMS_SECTION: &str = "bevy_save_manager::transforms";
+/// Section holding the sections of a save once transformed
+const TRANSFORMED_SECTION: &str = "bevy_save_manager::transformed";
+
+/// One named part of a save file, backed by a resource
+#[derive(Clone)]
+pub(crate) struct SaveSection {
+    pub name: String,
+    pub init: fn(&mut App),
+    pub capture: fn(&World, SaveEncoding) -> Result<Vec<u8>, SaveError>,
+    /// Encode straight into a writer for streamed saves, `capture` is used if not set
+    pub capture_into: Option<fn(&World, SaveEncoding, &mut dyn Write) -> Result<(), SaveError>>,
+    pub stage: fn(&World, &[u8], SaveEncoding) -> Result<Staged, SaveError>,
+    pub apply: Apply,
+    /// Decode data written by another version, only set for the main resource
+    pub migrate: Option<fn(u32, &[u8], SaveEncoding) -> Result<Staged, SaveError>>,
+}
+
+impl SaveSection {
+    pub fn new<R>(name: impl Into<String>) -> Self
+    where
+        R: Resource + Default + Serialize + DeserializeOwned,
+    {
+        Self {
+            name: name.into(),
+            init: init::<R>,
+            capture: capture::<R>,
+            capture_into: Some(capture_into::<R>),
+            stage: stage::<R>,
+            apply: apply::<R>,
+            migrate: None,
+        }
+    }
+
+    pub fn versioned<T>(name: impl Into<String>) -> Self
+    where
+        T: Resource + Default + EncryptSave,
+    {
+        Self {
+            stage: stage_validated::<T>,
+            migrate: Some(migrate::<T>),
+            ..Self::new::<T>(name)
+        }
+    }
+}
+
+/// On-disk layout of a save: every registered resource encoded separately under its section name
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SaveSections {
+    pub sections: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Resource, Clone, Default)]
+pub(crate) struct SaveRegistry {
+    /// The first section is the plugin's main resource
+    pub sections: Vec<SaveSection>,
+    /// Version of the main resource
+    pub version: u32,
+    /// Layout of new saves
+    pub encoding: SaveEncoding,
+    /// Run on the encoded sections of new saves
+    pub transforms: Transforms,
+}
+
+impl SaveRegistry {
+    pub fn encode(&self, world: &World) -> Result<Vec<u8>, SaveError> {
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for section in &self.sections {
+            sections.push((section.name.clone(), (section.capture)(world, self.encoding)?));
+        }
+        self.assemble(sections)
+    }
+
+    /// Every section but the main resource, which is then encoded by [`SaveRegistry::encode_with_main`]
+    pub fn capture_others(&self, world: &World) -> Result<Vec<(String, Vec<u8>)>, SaveError> {
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for section in self.sections.iter().skip(1) {
+            sections.push((section.name.clone(), (section.capture)(world, self.encoding)?));
+        }
+        Ok(sections)
+    }
+
+    /// Same data as [`SaveRegistry::encode`] from a copy of the main resource and the other sections
+    pub fn encode_with_main<T>(&self, main: &T, others: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, SaveError>
+    where
+        T: Serialize,
+    {
+        let name = self
+            .sections
+            .first()
+            .map(|section| section.name.clone())
+            .unwrap_or_default();
+        let mut sections = vec![(name, self.encoding.encode(main)?)];
+        sections.extend(others);
+        self.assemble(sections)
+    }
+
+    fn assemble(&self, mut sections: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, SaveError> {
+        // The list of sections and the sections of the crate always use the legacy layout, to read the others
+        sections.push((SECTIONS_SECTION.to_string(), Vec::new()));
+        if self.version != 0 {
+            sections.push((VERSION_SECTION.to_string(), SaveEncoding::Legacy.encode(&self.version)?));
+        }
+        if self.encoding != SaveEncoding::Legacy {
+            sections.push((ENCODING_SECTION.to_string(), vec![self.encoding.id()]));
+        }
+        self.transform(SaveEncoding::Legacy.encode(&SaveSections { sections })?)
+    }
+
+    /// Wrap `data` in the output of the transforms, `data` as is if there are none
+    fn transform(&self, data: Vec<u8>) -> Result<Vec<u8>, SaveError> {
+        if self.transforms.0.is_empty() {
+            return Ok(data);
+        }
+        let (names, data) = self.transforms.encode(data)?;
+        let sections = vec![
+            (TRANSFORMS_SECTION.to_string(), SaveEncoding::Legacy.encode(&names)?),
+            (TRANSFORMED_SECTION.to_string(), data),
+        ];
+        SaveEncoding::Legacy.encode(&SaveSections { sections })
+    }
+
+    /// Sections of `saved` with its transforms undone, `None` if it wasn't transformed
+    fn untransform(&self, saved: &SaveSections) -> Result<Option<SaveSections>, SaveError> {
+        let Some((_, names)) = saved.sections.iter().find(|(name, _)| name == TRANSFORMS_SECTION) else {
+            return Ok(None);
+        };
+        let names: Vec<String> = SaveEncoding::Legacy.decode(names)?;
+        let data = saved
+            .sections
+            .iter()
+            .find(|(name, _)| name == TRANSFORMED_SECTION)
+            .map(|(_, data)| data.clone())
+            .unwrap_or_default();
+        let data = self.transforms.decode(&names, data)?;
+        decode_sections(&data)
+            .map(Some)
+            .ok_or_else(|| SaveError::Corrupted("Transformed data is not a save".to_string()))
+    }
+
+    /// `saved` with its transforms undone
+    fn restore(&self, saved: SaveSections) -> Result<SaveSections, SaveError> {
+        Ok(self.untransform(&saved)?.unwrap_or(saved))
+    }
+
+    /// Same data as [`SaveRegistry::encode`], written to `writer` without holding it in memory.
+    /// Sections are encoded twice, first to know their size. Transformed saves are encoded whole first.
+    pub fn encode_into(&self, world: &World, writer: &mut dyn Write) -> Result<(), SaveError> {
+        if !self.transforms.0.is_empty() {
+            writer.write_all(&self.encode(world)?)?;
+            return Ok(());
+        }
+        let legacy = SaveEncoding::Legacy;
+        let mut count = self.sections.len() as u64 + 1;
+        count += (self.version != 0) as u64 + (self.encoding != SaveEncoding::Legacy) as u64;
+        legacy.encode_into(&count, writer)?;
+        for section in &self.sections {
+            legacy.encode_into(&section.name, writer)?;
+            match section.capture_into {
+                Some(capture_into) => {
+                    let mut counter = ByteCounter(0);
+                    capture_into(world, self.encoding, &mut counter)?;
+                    legacy.encode_into(&counter.0, writer)?;
+                    capture_into(world, self.encoding, writer)?;
+                }
+                None => {
+                    let data = (section.capture)(world, self.encoding)?;
+                    legacy.encode_into(&data, writer)?;
+                }
+            }
+        }
+        legacy.encode_into(&(SECTIONS_SECTION, Vec::<u8>::new()), writer)?;
+        if self.version != 0 {
+            legacy.encode_into(&(VERSION_SECTION, legacy.encode(&self.version)?), writer)?;
+        }
+        if self.encoding != SaveEncoding::Legacy {
+            legacy.encode_into(&(ENCODING_SECTION, vec![self.encoding.id()]), writer)?;
+        }
+        Ok(())
+    }
+
+    /// [`SaveRegistry::stage`] for data written by [`SaveRegistry::encode_into`], read from `reader`
+    pub fn stage_from(&self, world: &World, reader: &mut dyn Read) -> Result<Vec<StagedSection>, SaveError> {
+        let saved = SaveEncoding::Legacy.decode_from::<SaveSections>(reader)?;
+        if reader.read(&mut [0])? != 0 {
+            return Err(SaveError::Corrupted("Data after the sections".to_string()));
+        }
+        self.stage_sections(world, self.restore(saved)?)
+    }
+
+    /// Decode every section without touching the world. Sections missing from the data are skipped.
+    pub fn stage(&self, world: &World, data: &[u8]) -> Result<Vec<StagedSection>, SaveError> {
+        let saved = decode_sections(data);
+        if saved.as_ref().is_none_or(|saved| !self.is_sections(saved)) {
+            // Saves written before sections existed only contain the main resource
+            let main = self
+                .sections
+                .first()
+                .ok_or_else(|| SaveError::Corrupted("No section registered".to_string()))?;
+            let staged = self.stage_section(main, world, 0, SaveEncoding::Legacy, data);
+            match (staged, &saved) {
+                (Ok(staged), _) => return Ok(vec![(main.apply, staged)]),
+                (Err(e), None) => return Err(e),
+                // Sections of an older save whose main resource was renamed
+                (Err(_), Some(_)) => {}
+            }
+        }
+        let saved = saved.ok_or_else(|| SaveError::Corrupted("Not a save".to_string()))?;
+        self.stage_sections(world, self.restore(saved)?)
+    }
+
+    /// Whether `saved` was written as sections, rather than being a legacy main resource which happens to decode as
+    /// sections. Saves of sections written before [`SECTIONS_SECTION`] existed are told apart by the names they hold.
+    fn is_sections(&self, saved: &SaveSections) -> bool {
+        saved.sections.iter().any(|(name, _)| {
+            name.starts_with("bevy_save_manager::") || self.sections.iter().any(|section| section.name == *name)
+        })
+    }
+
+    fn stage_sections(&self, world: &World, saved: SaveSections) -> Result<Vec<StagedSection>, SaveError> {
+        let version = match saved.sections.iter().find(|(name, _)| name == VERSION_SECTION) {
+            Some((_, bytes)) => SaveEncoding::Legacy.decode(bytes)?,
+            None => 0,
+        };
+        let encoding = saved_encoding(&saved)?;
+        let mut staged = Vec::with_capacity(self.sections.len());
+        let migrating = version != self.version;
+        for (i, section) in self.sections.iter().enumerate() {
+            let saved_section = saved.sections.iter().find(|(name, _)| *name == section.name);
+            // The main resource is always written first, its type may have been renamed since
+            let saved_section = saved_section.or_else(|| saved.sections.first().filter(|_| i == 0 && migrating));
+            match saved_section {
+                Some((_, bytes)) => staged.push((
+                    section.apply,
+                    self.stage_section(section, world, version, encoding, bytes)?,
+                )),
+                None if i == 0 => return Err(SaveError::Corrupted("No main resource in the save".to_string())),
+                None => {}
+            }
+        }
+        Ok(staged)
+    }
+
+    /// Decode the main resource of `data`, migrated if it was written by another version
+    pub fn decode_main<T>(&self, world: &World, data: &[u8]) -> Result<T, SaveError>
+    where
+        T: Resource,
+    {
+        self.stage(world, data)?
+            .into_iter()
+            .find_map(|(_, staged)| staged.downcast::<T>().ok())
+            .map(|main| *main)
+            .ok_or_else(|| SaveError::Corrupted("No main resource in the save".to_string()))
+    }
+
+    /// `data` with its main resource replaced by `main`, the other sections are kept as they were
+    pub fn replace_main<T>(&self, data: &[u8], main: &T) -> Result<Vec<u8>, SaveError>
+    where
+        T: Serialize,
+    {
+        let Some(saved) = decode_sections(data).filter(|saved| self.is_sections(saved)) else {
+            return self.encode_with_main(main, Vec::new());
+        };
+        let saved = self.restore(saved)?;
+        let encoding = saved_encoding(&saved)?;
+        let name = self
+            .sections
+            .first()
+            .map(|section| section.name.clone())
+            .unwrap_or_default();
+        let mut sections: Vec<(String, Vec<u8>)> = saved
+            .sections
+            .into_iter()
+            .filter(|(name, _)| name != VERSION_SECTION && name != SECTIONS_SECTION)
+            .collect();
+        // The main resource is always written first, its type may have been renamed since
+        let index = sections
+            .iter()
+            .position(|(saved_name, _)| *saved_name == name)
+            .unwrap_or(0);
+        let main = (name, encoding.encode(main)?);
+        match sections.get_mut(index) {
+            Some(section) => *section = main,
+            None => sections.push(main),
+        }
+        sections.push((SECTIONS_SECTION.to_string(), Vec::new()));
+        if self.version != 0 {
+            sections.push((VERSION_SECTION.to_string(), SaveEncoding::Legacy.encode(&self.version)?));
+        }
+        self.transform(SaveEncoding::Legacy.encode(&SaveSections { sections })?)
+    }
+
+    /// Fail if `data` was written by a newer version of the main resource, which can't be migrated
+    pub fn check_version(&self, data: &[u8]) -> Result<(), SaveError> {
+        let Some(saved) = decode_sections(data).filter(|saved| self.is_sections(saved)) else {
+            return Ok(());
+        };
+        let saved = self.restore(saved)?;
+        let version: u32 = match saved.sections.iter().find(|(name, _)| name == VERSION_SECTION) {
+            Some((_, bytes)) => SaveEncoding::Legacy.decode(bytes)?,
+            None => 0,
+        };
+        if version > self.version {
+            return Err(SaveError::VersionMismatch {
+                saved: version.to_string(),
+                current: self.version.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn stage_section(
+        &self,
+        section: &SaveSection,
+        world: &World,
+        version: u32,
+        encoding: SaveEncoding,
+        data: &[u8],
+    ) -> Result<Staged, SaveError> {
+        match section.migrate {
+            Some(migrate) if version != self.version => migrate(version, data, encoding),
+            _ => (section.stage)(world, data, encoding),
+        }
+    }
+}
+
+pub(crate) fn decode_sections(data: &[u8]) -> Option<SaveSections> {
+    let (saved, read) = SaveEncoding::Legacy.decode_partial::<SaveSections>(data).ok()?;
+    (read == data.len()).then_some(saved)
+}
+
+/// Layout of the sections of a save other than the ones of the crate
+fn saved_encoding(saved: &SaveSections) -> Result<SaveEncoding, SaveError> {
+    match saved.sections.iter().find(|(name, _)| name == ENCODING_SECTION) {
+        Some((_, bytes)) => bytes
+            .first()
+            .and_then(|id| SaveEncoding::from_id(*id))
+            .ok_or_else(|| SaveError::Corrupted("Unknown encoding".to_string())),
+        None => Ok(SaveEncoding::Legacy),
+    }
+}
+
+pub(crate) fn section_name<R>() -> &'static str {
+    type_name::<R>()
+}
+
+fn init<R>(app: &mut App)
+where
+    R: Resource + Default,
+{
+    app.init_resource::<R>();
+}
+
+fn capture<R>(world: &World, encoding: SaveEncoding) -> Result<Vec<u8>, SaveError>
+where
+    R: Resource + Serialize,
+{
+    let resource = world
+        .get_resource::<R>()
+        .ok_or(SaveError::MissingResource(type_name::<R>()))?;
+    encoding.encode(resource)
+}
+
+fn capture_into<R>(world: &World, encoding: SaveEncoding, writer: &mut dyn Write) -> Result<(), SaveError>
+where
+    R: Resource + Serialize,
+{
+    let resource = world
+        .get_resource::<R>()
+        .ok_or(SaveError::MissingResource(type_name::<R>()))?;
+    encoding.encode_into(resource, writer)
+}
+
+/// Counts the bytes written to it
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Fails unless `data` is exactly one `R`, so a truncated save is never mistaken for a shorter value
+fn stage<R>(_world: &World, data: &[u8], encoding: SaveEncoding) -> Result<Staged, SaveError>
+where
+    R: Resource + DeserializeOwned,
+{
+    let (resource, read) = encoding.decode_partial::<R>(data)?;
+    if read != data.len() {
+        return Err(SaveError::Corrupted(format!(
+            "{} bytes left after {}",
+            data.len() - read,
+            section_name::<R>()
+        )));
+    }
+    Ok(Box::new(resource))
+}
+
+/// [`stage`] of the main resource, checked by [`EncryptSave::validate`]
+fn stage_validated<T>(world: &World, data: &[u8], encoding: SaveEncoding) -> Result<Staged, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let staged = stage::<T>(world, data, encoding)?;
+    if let Some(main) = staged.downcast_ref::<T>() {
+        main.validate()?;
+    }
+    Ok(staged)
+}
+
+fn migrate<T>(version: u32, data: &[u8], encoding: SaveEncoding) -> Result<Staged, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let main = T::migrate(version, data, encoding)?;
+    main.validate()?;
+    Ok(Box::new(main))
+}
+
+fn apply<R>(world: &mut World, staged: Staged) -> Result<Undo, SaveError>
+where
+    R: Resource,
+{
+    let resource = staged
+        .downcast::<R>()
+        .map_err(|_| SaveError::Corrupted(format!("Staged data is not a {}", section_name::<R>())))?;
+    let previous = world.remove_resource::<R>();
+    world.insert_resource(*resource);
+    Ok(Box::new(move |world: &mut World| match previous {
+        Some(previous) => world.insert_resource(previous),
+        None => {
+            world.remove_resource::<R>();
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Serialize, Deserialize, Default, Debug, PartialEq)]
+    struct Progress {
+        level: u32,
+    }
+
+    /// [`Progress`] as saved by version 0
+    #[derive(Serialize, Deserialize)]
+    struct ProgressV0 {
+        level: u16,
+    }
+
+    impl EncryptSave for Progress {
+        const VERSION: u32 = 1;
+
+        fn migrate(version: u32, data: &[u8], encoding: SaveEncoding) -> Result<Self, SaveError> {
+            match version {
+                0 => Ok(Self {
+                    level: encoding.decode::<ProgressV0>(data)?.level.into(),
+                }),
+                _ => Err(SaveError::VersionMismatch {
+                    saved: version.to_string(),
+                    current: Self::VERSION.to_string(),
+                }),
+            }
+        }
+    }
+
+    /// Main resource of version 0, named differently
+    #[derive(Resource, Serialize, Deserialize)]
+    struct OldMain(ProgressV0);
+
+    impl Default for OldMain {
+        fn default() -> Self {
+            Self(ProgressV0 { level: 0 })
+        }
+    }
+
+    #[derive(Resource, Serialize, Deserialize, Default, Debug, PartialEq)]
+    struct Extra {
+        name: String,
+    }
+
+    fn registry(version: u32) -> SaveRegistry {
+        SaveRegistry {
+            sections: vec![
+                SaveSection::versioned::<Progress>("progress"),
+                SaveSection::new::<Extra>("extra"),
+            ],
+            version,
+            ..SaveRegistry::default()
+        }
+    }
+
+    fn saved_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Progress { level: 3 });
+        world.insert_resource(Extra {
+            name: "extra".to_string(),
+        });
+        world
+    }
+
+    /// World with the sections of `data` applied
+    fn load(registry: &SaveRegistry, data: &[u8]) -> Result<World, SaveError> {
+        let mut world = World::new();
+        for (apply, staged) in registry.stage(&world, data)? {
+            let _undo = apply(&mut world, staged)?;
+        }
+        Ok(world)
+    }
+
+    #[test]
+    fn sections_round_trip() {
+        for encoding in [SaveEncoding::Legacy, SaveEncoding::Standard] {
+            let registry = SaveRegistry {
+                encoding,
+                ..registry(1)
+            };
+            let data = registry.encode(&saved_world()).unwrap();
+            let world = load(&registry, &data).unwrap();
+            assert_eq!(world.resource::<Progress>(), &Progress { level: 3 });
+            assert_eq!(world.resource::<Extra>().name, "extra");
+
+            let mut streamed = Vec::new();
+            registry.encode_into(&saved_world(), &mut streamed).unwrap();
+            assert_eq!(streamed, data);
+        }
+    }
+
+    #[test]
+    fn legacy_save_is_migrated() {
+        let data = SaveEncoding::Legacy.encode(&ProgressV0 { level: 5 }).unwrap();
+        let world = load(&registry(1), &data).unwrap();
+        assert_eq!(world.resource::<Progress>(), &Progress { level: 5 });
+        assert!(!world.contains_resource::<Extra>());
+    }
+
+    #[test]
+    fn sections_of_an_older_version_are_migrated() {
+        let mut world = saved_world();
+        let old = SaveRegistry {
+            sections: vec![
+                SaveSection::new::<OldMain>("old_main"),
+                SaveSection::new::<Extra>("extra"),
+            ],
+            ..SaveRegistry::default()
+        };
+        world.insert_resource(OldMain(ProgressV0 { level: 9 }));
+        let data = old.encode(&world).unwrap();
+
+        // The main resource was renamed, it is still found first
+        let world = load(&registry(1), &data).unwrap();
+        assert_eq!(world.resource::<Progress>(), &Progress { level: 9 });
+        assert_eq!(world.resource::<Extra>().name, "extra");
+    }
+
+    #[test]
+    fn missing_sections_are_skipped() {
+        let main_only = SaveRegistry {
+            sections: vec![SaveSection::versioned::<Progress>("progress")],
+            version: 1,
+            ..SaveRegistry::default()
+        };
+        let data = main_only.encode(&saved_world()).unwrap();
+        let world = load(&registry(1), &data).unwrap();
+        assert_eq!(world.resource::<Progress>().level, 3);
+        assert!(!world.contains_resource::<Extra>());
+    }
+
+    #[test]
+    fn newer_version_is_refused() {
+        let data = registry(2).encode(&saved_world()).unwrap();
+        assert!(matches!(
+            registry(1).check_version(&data),
+            Err(SaveError::VersionMismatch { .. })
+        ));
+        assert!(registry(1).stage(&World::new(), &data).is_err());
+        assert!(registry(2).check_version(&data).is_ok());
+    }
+
+    #[test]
+    fn section_with_trailing_bytes_is_refused() {
+        let mut progress = SaveEncoding::Legacy.encode(&Progress { level: 3 }).unwrap();
+        progress.push(0);
+        let sections = vec![
+            ("progress".to_string(), progress),
+            (SECTIONS_SECTION.to_string(), Vec::new()),
+            (VERSION_SECTION.to_string(), SaveEncoding::Legacy.encode(&1u32).unwrap()),
+        ];
+        let data = SaveEncoding::Legacy.encode(&SaveSections { sections }).unwrap();
+        assert!(matches!(load(&registry(1), &data), Err(SaveError::Corrupted(_))));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::save::prune_saves;
+use crate::setting::{
+    load_config,
+    GameSetting,
+    GameSettingSupportPlugin,
+};
+use crate::sync::RemoteBackend;
+use ::s3::creds::Credentials;
+use ::s3::error::S3Error;
+use ::s3::{
+    Bucket,
+    Region,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    resource_changed,
+    IntoScheduleConfigs,
+    Plugin,
+    Res,
+    Resource,
+    Startup,
+    Update,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::{
+    Arc,
+    RwLock,
+};
+
+/// Endpoint and credentials of an S3-compatible bucket (AWS, MinIO, R2, ...).
+/// Stored in plain text next to the other settings.
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct S3Setting {
+    /// e.g. `https://<account>.r2.cloudflarestorage.com`, empty to sync with AWS
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Folder of the saves inside the bucket, e.g. the player name
+    pub prefix: String,
+    /// Address the bucket as `endpoint/bucket` instead of `bucket.endpoint`, required by MinIO
+    pub path_style: bool,
+}
+
+impl GameSetting for S3Setting {
+    const DEFAULT_CONF: &'static str = "s3_setting.conf";
+}
+
+impl S3Setting {
+    pub fn is_configured(&self) -> bool {
+        !self.bucket.is_empty() && !self.access_key.is_empty()
+    }
+}
+
+/// [`RemoteBackend`] for an S3-compatible bucket, configured from [`S3Setting`] whenever it changes
+#[derive(Clone, Default)]
+pub struct S3Backend {
+    remote: Arc<RwLock<Option<S3Remote>>>,
+}
+
+struct S3Remote {
+    bucket: Box<Bucket>,
+    prefix: String,
+}
+
+impl S3Backend {
+    /// Connect to the bucket in `setting`, or disconnect if it isn't configured
+    pub fn configure(&self, setting: &S3Setting) -> Result<(), S3Error> {
+        let remote = if setting.is_configured() {
+            let region = if setting.endpoint.is_empty() {
+                setting.region.parse()?
+            } else {
+                Region::Custom {
+                    region: setting.region.clone(),
+                    endpoint: setting.endpoint.clone(),
+                }
+            };
+            let credentials = Credentials::new(Some(&setting.access_key), Some(&setting.secret_key), None, None, None)?;
+            let mut bucket = Bucket::new(&setting.bucket, region, credentials)?;
+            if setting.path_style {
+                bucket = bucket.with_path_style();
+            }
+            let prefix = setting.prefix.trim_matches('/');
+            Some(S3Remote {
+                bucket,
+                prefix: if prefix.is_empty() { String::new() } else { format!("{}/", prefix) },
+            })
+        } else {
+            None
+        };
+
+        *self.remote.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = remote;
+        Ok(())
+    }
+
+    fn with_remote<R>(&self, f: impl FnOnce(&S3Remote) -> Result<R, S3Error>) -> io::Result<R> {
+        let remote = self.remote.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(remote) = remote.as_ref() else {
+            return Err(io::Error::new(ErrorKind::NotConnected, "S3 bucket is not configured"));
+        };
+        f(remote).map_err(|e| match e {
+            S3Error::HttpFailWithBody(404, _) => io::Error::new(ErrorKind::NotFound, e),
+            _ => io::Error::other(e),
+        })
+    }
+}
+
+impl RemoteBackend for S3Backend {
+    fn upload(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.with_remote(|remote| {
+            remote.bucket.put_object(format!("{}{}", remote.prefix, name), data)?;
+            Ok(())
+        })
+    }
+
+    fn download(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.with_remote(|remote| Ok(remote.bucket.get_object(format!("{}{}", remote.prefix, name))?.to_vec()))
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        self.with_remote(|remote| {
+            let pages = remote.bucket.list(remote.prefix.clone(), None)?;
+            Ok(pages
+                .into_iter()
+                .flat_map(|page| page.contents)
+                .filter_map(|object| object.key.strip_prefix(&remote.prefix).map(str::to_string))
+                .collect())
+        })
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.with_remote(|remote| {
+            remote.bucket.delete_object(format!("{}{}", remote.prefix, name))?;
+            Ok(())
+        })
+    }
+}
+
+#[derive(Resource)]
+struct S3Connection(S3Backend);
+
+pub(crate) struct S3Plugin {
+    pub backend: S3Backend,
+}
+
+impl Plugin for S3Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GameSettingSupportPlugin::<S3Setting>::default())
+            .insert_resource(S3Connection(self.backend.clone()))
+            .add_systems(
+                Startup,
+                configure_bucket.after(load_config::<S3Setting>).before(prune_saves),
+            )
+            .add_systems(Update, configure_bucket.run_if(resource_changed::<S3Setting>));
+    }
+}
+
+fn configure_bucket(connection: Res<S3Connection>, setting: Res<S3Setting>) {
+    if let Err(_e) = connection.0.configure(&setting) {
+        #[cfg(feature = "log")]
+        warn!("Failed to configure S3 bucket {}: {}", setting.bucket, _e);
+    }
+}
@@ -1,45 +1,197 @@
+#[cfg(feature = "drag-and-drop")]
+use crate::archive::import_dropped;
+use crate::archive::{
+    on_export,
+    on_import,
+    ArchiveFailed,
+    ExportSave,
+    ImportSave,
+    SaveExported,
+    SaveImported,
+};
+use crate::backend::{
+    FsBackend,
+    SaveBackend,
+    SaveStorage,
+};
+use crate::cipher::{
+    key_id,
+    open,
+    open_stream,
+    seal,
+    seal_stream,
+    set_password,
+    Cipher,
+    LegacyCipher,
+    PlainCipher,
+    SaveCipher,
+    SaveHeader,
+    SaveKey,
+    SavePassword,
+    SecretKey,
+    SetSavePassword,
+};
+use crate::delta::{
+    diff,
+    patch,
+};
+use crate::diagnostic::{
+    report_failures,
+    SaveOperation,
+    SaveStats,
+    SaveTelemetry,
+    Telemetry,
+};
+use crate::error::SaveError;
+use crate::mode::{
+    switch_mode,
+    ModeBackend,
+    ModeSwitch,
+    SaveManagerMode,
+    SaveRefused,
+};
+use crate::naming::{
+    new_save_file,
+    unique_file,
+    NamingRng,
+};
+use crate::io::{
+    flush_pending_writes,
+    FlushSaves,
+    retry,
+    Durability,
+    PendingWrites,
+    RetryPolicy,
+    SaveFailed,
+    SaveVerified,
+    SaveWritten,
+};
+use crate::paths::SaveDirs;
+use crate::platform::{
+    MountedBackend,
+    PlatformSaveBackend,
+};
+use crate::portable::PortableMode;
+use crate::profile::{
+    CurrentProfile,
+    ProfileSwitched,
+};
+use crate::progress::{
+    track_load_failures,
+    track_writes,
+    LoadPhase,
+    LoadProgress,
+    SaveProgress,
+};
+use crate::project::Project;
+use crate::registry::{
+    section_name,
+    SaveRegistry,
+    SaveSection,
+    StagedSection,
+};
+use crate::sync::{
+    CloudSync,
+    CloudSyncPlugin,
+    SyncMergePlugin,
+};
 use crate::setting::{
+    load_config,
     GameSetting,
     GameSettingChanged,
     GameSettingSupportPlugin,
 };
+use crate::transform::SaveTransform;
 use bevy::app::App;
+use bevy::ecs::schedule::{
+    InternedScheduleLabel,
+    ScheduleLabel,
+};
+use bevy::platform::time::Instant;
 #[cfg(feature = "log")]
 use bevy::prelude::{
     error,
     warn,
 };
 use bevy::prelude::{
+    in_state,
     on_message,
+    resource_changed,
+    AppExit,
+    AppExtStates,
+    Commands,
     Deref,
     DerefMut,
+    DetectChangesMut,
+    First,
     IntoScheduleConfigs,
+    Last,
     Message,
     MessageReader,
     MessageWriter,
+    NextState,
     Plugin,
+    PreUpdate,
+    Real,
     Res,
     ResMut,
     Resource,
+    Startup,
+    State,
+    States,
+    SystemCondition,
+    SystemSet,
+    Time,
     Update,
+    World,
 };
 use bevy::tasks::IoTaskPool;
+#[cfg(feature = "drag-and-drop")]
+use bevy::window::FileDragAndDrop;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{
+    self,
+    DeserializeOwned,
+    MapAccess,
+    Visitor,
+};
 use serde::{
     Deserialize,
+    Deserializer,
     Serialize,
 };
-use simple_crypt::{
-    decrypt,
-    encrypt,
+use std::borrow::Cow;
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+    HashMap,
+    VecDeque,
+};
+use std::fmt;
+use std::io::{
+    self,
+    Read,
+    Write,
 };
-use std::collections::HashMap;
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::marker::PhantomData;
 use std::path::{
     Path,
     PathBuf,
 };
+use std::sync::atomic::AtomicU8;
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+use zeroize::Zeroizing;
+
+/// Turns the saved resource into the RON of [`SaveSlot::custom`]
+type CaptureMeta<T> = Arc<dyn Fn(&T) -> Result<String, ron::Error> + Send + Sync>;
+
+/// Adds a run condition to the sets of [`EncryptSavePlugin`] in its schedule
+type ConfigureSets = Arc<dyn Fn(&mut App, InternedScheduleLabel) + Send + Sync>;
 
 #[derive(Default)]
 pub struct EncryptSavePlugin<T>
@@ -47,6 +199,368 @@ where
     T: Resource + Default + EncryptSave + Clone,
 {
     _config: Option<T>,
+    options: SaveOptions,
+    registry: SaveRegistry,
+    storage: Option<SaveStorage>,
+    cipher: Option<SaveCipher>,
+    mode: SaveManagerMode,
+    retry: Option<RetryPolicy>,
+    sync: Option<CloudSync>,
+    sync_merge: Option<fn(T, T) -> T>,
+    custom_meta: Option<CaptureMeta<T>>,
+    meta_extractor: Option<fn(&T) -> SlotFields>,
+    telemetry: Option<Telemetry>,
+    schedule: Option<InternedScheduleLabel>,
+    conditions: Vec<ConfigureSets>,
+    #[cfg(feature = "s3")]
+    s3: Option<crate::s3::S3Backend>,
+    #[cfg(feature = "steam")]
+    steam: Option<crate::steam::SteamBackend>,
+    #[cfg(feature = "keyring")]
+    keyring: Option<String>,
+    #[cfg(feature = "signing")]
+    signing: Option<crate::signing::SigningKey>,
+    portable: Option<PortableMode>,
+    project: Option<Project>,
+    config_in_save_dir: bool,
+    protected_config: bool,
+    naming_seed: Option<u64>,
+}
+
+impl<T> EncryptSavePlugin<T>
+where
+    T: Resource + Default + EncryptSave + Clone,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delete `.dat` files in the save directory which don't belong to any slot at startup
+    pub fn delete_orphans(mut self, enable: bool) -> Self {
+        self.options.delete_orphans = enable;
+        self
+    }
+
+    /// Drive [`SaveLoadState`]. Requests are then executed on the frame after they are sent,
+    /// once the state has switched to `Saving` or `Loading`.
+    pub fn with_state(mut self) -> Self {
+        self.options.track_state = true;
+        self
+    }
+
+    /// Keep the last `size` [`SaveCheckpoint`]s, outside of the slot list
+    pub fn with_checkpoints(mut self, size: usize) -> Self {
+        self.options.checkpoints = size;
+        self
+    }
+
+    /// Keep up to `depth` [`Snapshot`]s in memory, dropping the oldest ones above `budget` bytes
+    pub fn with_snapshots(mut self, depth: usize, budget: usize) -> Self {
+        self.options.snapshot_depth = depth;
+        self.options.snapshot_budget = budget;
+        self
+    }
+
+    /// Take a screenshot of the primary window after each save, downscaled to fit in `width` x `height`.
+    /// Thumbnails are available in [`SaveThumbnails`](crate::thumbnail::SaveThumbnails).
+    #[cfg(feature = "thumbnail")]
+    pub fn with_thumbnails(mut self, width: u32, height: u32) -> Self {
+        self.options.thumbnail_size = Some(bevy::math::UVec2::new(width, height));
+        self
+    }
+
+    /// Read and write saves and settings through `backend` instead of the local file system
+    pub fn with_backend(mut self, backend: impl SaveBackend) -> Self {
+        self.storage = Some(SaveStorage(Arc::new(backend)));
+        self
+    }
+
+    /// Read and write saves and settings through the save data of a console SDK, see [`PlatformSaveBackend`]
+    pub fn with_platform_backend(self, platform: impl PlatformSaveBackend) -> Self {
+        self.with_backend(MountedBackend::new(platform))
+    }
+
+    /// Layout of the resources in new saves, see [`SaveEncoding`]. Saves of any encoding can be loaded.
+    pub fn with_encoding(mut self, encoding: SaveEncoding) -> Self {
+        self.registry.encoding = encoding;
+        self
+    }
+
+    /// Import [archives](crate::archive) dropped onto a window, see [`SaveImported`]
+    #[cfg(feature = "drag-and-drop")]
+    pub fn with_drag_and_drop(mut self) -> Self {
+        self.options.drag_and_drop = true;
+        self
+    }
+
+    /// Write a `.meta` sidecar next to each slot, see [`crate::meta`]
+    pub fn with_meta_sidecars(mut self) -> Self {
+        self.options.meta_sidecars = true;
+        self
+    }
+
+    /// Record `version` in new saves, e.g. `env!("CARGO_PKG_VERSION")`.
+    /// [`SaveVersionMismatch`] is sent before loading a save of another version.
+    pub fn with_game_version(mut self, version: impl Into<String>) -> Self {
+        self.options.game_version = Some(version.into());
+        self
+    }
+
+    /// Encrypt new saves with `cipher`, see [`SaveCipher`]
+    pub fn with_cipher(mut self, cipher: impl Cipher) -> Self {
+        self.cipher = Some(SaveCipher(Arc::new(cipher)));
+        self
+    }
+
+    /// Encrypt saves with a random key kept in the OS credential store under `service`, see [`KeyringPlugin`](crate::keyring::KeyringPlugin)
+    #[cfg(feature = "keyring")]
+    pub fn with_keyring(mut self, service: impl Into<String>) -> Self {
+        self.keyring = Some(service.into());
+        self
+    }
+
+    /// Sign slot saves with `key`, kept apart from the encryption key, and refuse to load saves whose signature
+    /// doesn't match. See [`verify_signature`](crate::signing::verify_signature) to check them on a server.
+    /// Saves are written in full while it is set, even with [`Self::with_streaming`], and streamed saves fail to load.
+    #[cfg(feature = "signing")]
+    pub fn with_signing_key(mut self, key: impl Into<SecretKey>) -> Self {
+        self.signing = Some(crate::signing::SigningKey(key.into()));
+        self
+    }
+
+    /// Choose ids of new slots with `allocation`, see [`IdAllocation`]
+    pub fn with_id_allocation(mut self, allocation: IdAllocation) -> Self {
+        self.options.id_allocation = allocation;
+        self
+    }
+
+    /// Run only the last [`SaveGame`] or [`QuickSave`] to a slot within `window` of the first one,
+    /// the others are answered with [`SaveSuperseded`]. `Duration::ZERO` coalesces requests of the same frame.
+    pub fn with_save_coalescing(mut self, window: Duration) -> Self {
+        self.options.coalesce_window = Some(window);
+        self
+    }
+
+    /// Flush save files to disk once written, so they survive a power loss
+    pub fn with_fsync(mut self) -> Self {
+        self.options.fsync = true;
+        self
+    }
+
+    /// Read save files back once written and only make them [`SaveConfig::last_saved`] if they match.
+    /// [`SaveVerified`] is sent for each checked file, [`SaveFailed`] when it doesn't match.
+    pub fn with_verify_after_write(mut self) -> Self {
+        self.options.verify_after_write = true;
+        self
+    }
+
+    /// Retry failed writes of saves and settings with `policy`, see [`RetryPolicy`]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Report the outcome of every slot save and load to `telemetry`
+    pub fn with_telemetry(mut self, telemetry: impl SaveTelemetry) -> Self {
+        self.telemetry = Some(Telemetry(Arc::new(telemetry)));
+        self
+    }
+
+    /// Run `transform` on the serialized data of every save before it is encrypted, see [`SaveTransform`].
+    /// Streamed saves are then serialized whole before being written in chunks.
+    pub fn with_transform(mut self, transform: impl SaveTransform) -> Self {
+        self.registry.transforms.0.push(Arc::new(transform));
+        self
+    }
+
+    /// Fail saves with [`SaveError::QuotaExceeded`] once the save files would take more than `bytes`
+    pub fn with_quota(mut self, bytes: u64) -> Self {
+        self.options.quota = Some(bytes);
+        self
+    }
+
+    /// Fail saves with [`SaveError::SlotBudgetExceeded`] when a save file would take more than `bytes`, e.g. for the
+    /// limits of console certification. Streamed saves are only sized once written, they aren't checked.
+    pub fn with_slot_budget(mut self, bytes: u64) -> Self {
+        self.options.slot_budget = Some(bytes);
+        self
+    }
+
+    /// Refuse to load files above `limits`, see [`LoadLimits`]
+    pub fn with_load_limits(mut self, limits: LoadLimits) -> Self {
+        self.options.load_limits = limits;
+        self
+    }
+
+    /// Store saves and settings next to the executable, see [`PortableMode`]
+    pub fn with_portable_mode(mut self, mode: PortableMode) -> Self {
+        self.portable = Some(mode);
+        self
+    }
+
+    /// Keep the slot index in the save directory instead of with the settings. Slot files are stored relative to
+    /// that directory, so it can be copied to another machine as a whole.
+    pub fn with_config_in_save_dir(mut self) -> Self {
+        self.config_in_save_dir = true;
+        self
+    }
+
+    /// Encrypt the slot index like the saves, with [`EncryptSave::ENCR_KEY`] of `T`, so players can't edit
+    /// [`SaveConfig::last_saved`] or the slots in a text editor. A plain index is refused like a corrupted one, so
+    /// enabling it in an update starts players with an empty index. Other settings stay readable.
+    pub fn with_protected_config(mut self) -> Self {
+        self.protected_config = true;
+        self
+    }
+
+    /// Store settings and global saves in the folder of `project` inside the local data directory of the user
+    pub fn with_project(mut self, project: Project) -> Self {
+        self.project = Some(project);
+        self
+    }
+
+    /// Serialize, encrypt and write saves in chunks of `chunk_size` bytes, and load them back the same way,
+    /// so a large save is never held whole in memory. Streamed saves are written on the main thread
+    /// and skip the disk space and quota checks.
+    /// Needs a cipher other than [`LegacyCipher`], e.g. with the `aes-gcm` feature, saves are written in full otherwise.
+    pub fn with_streaming(mut self, chunk_size: u32) -> Self {
+        self.options.stream_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Clone `T` on the main thread and serialize, encrypt and write it on the `IoTaskPool`, so large saves don't
+    /// stall a frame. Other registered resources and persisted entities are still serialized on the main thread.
+    /// [`GameSaved`] is sent once the copy is taken, [`SaveWritten`] once the file is written.
+    pub fn with_background_saves(mut self) -> Self {
+        self.options.background_saves = true;
+        self
+    }
+
+    /// [`Self::with_background_saves`] with the other registered resources and persisted entities captured over
+    /// several frames, spending about `budget` per frame, so large worlds don't stall autosaves. At least one
+    /// resource is captured per frame, each of them in full. Resources captured in later frames are saved as they
+    /// are then, not as they were when the save was requested.
+    pub fn with_sliced_saves(mut self, budget: Duration) -> Self {
+        self.options.background_saves = true;
+        self.options.slice_budget = Some(budget);
+        self
+    }
+
+    /// Write [`SlotKind::Auto`] saves as a binary diff against a full save of the slot, taken again after
+    /// `full_every` diffs. Loads apply the diff transparently. Other saves of the slot are always full,
+    /// and autosaves are written in full while [`Self::with_streaming`] is set.
+    /// Cloud sync and exports send the diff applied on its base, as a full save.
+    pub fn with_delta_autosaves(mut self, full_every: u32) -> Self {
+        self.options.delta_autosaves = Some(full_every);
+        self
+    }
+
+    /// Name files of new slots with `naming`, see [`NamingStrategy`]
+    pub fn with_naming(mut self, naming: NamingStrategy) -> Self {
+        self.options.naming = naming;
+        self
+    }
+
+    /// Generate the random parts of file names from `seed`, e.g. for tests asserting exact file names.
+    /// Names then repeat between runs, so don't use it for players' saves.
+    pub fn with_naming_seed(mut self, seed: u64) -> Self {
+        self.naming_seed = Some(seed);
+        self
+    }
+
+    /// Start in `mode`, see [`SaveManagerMode`]
+    pub fn with_mode(mut self, mode: SaveManagerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Run the save, load and slot management systems in `schedule` instead of `Update`,
+    /// e.g. `Last` to save after every gameplay change of the frame. [`SaveSet`] and [`LoadSet`] are configured there.
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = Some(schedule.intern());
+        self
+    }
+
+    /// Only process saves, loads and deletions while `condition` holds, e.g. `in_state(GameState::Playing)`.
+    /// Saves and loads wait in the queue until then.
+    pub fn run_if<M>(mut self, condition: impl SystemCondition<M> + Clone + Send + Sync + 'static) -> Self {
+        self.conditions.push(Arc::new(move |app, schedule| {
+            app.configure_sets(schedule, SaveSet::Write.run_if(condition.clone()))
+                .configure_sets(schedule, LoadSet::Apply.run_if(condition.clone()))
+                .configure_sets(schedule, DeleteSet.run_if(condition.clone()));
+        }));
+        self
+    }
+
+    /// Mirror saves to a remote backend, see [`CloudSync`]
+    pub fn with_cloud_sync(mut self, sync: CloudSync) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// Settle slots changed both locally and remotely with `merge(local, remote)` under
+    /// [`ConflictStrategy::Merge`](crate::sync::ConflictStrategy::Merge), e.g. to keep the unlocks of both sides.
+    /// The other resources of the save are kept from the local side.
+    pub fn with_sync_merge(mut self, merge: fn(T, T) -> T) -> Self {
+        self.sync_merge = Some(merge);
+        self
+    }
+
+    /// Store `capture(&T)` in each slot on save, e.g. the chapter, difficulty or completion of the game,
+    /// for menus to read with [`SaveSlot::custom_meta`] without decrypting the saves
+    pub fn with_custom_meta<M>(mut self, capture: fn(&T) -> M) -> Self
+    where
+        M: Serialize + 'static,
+    {
+        self.custom_meta = Some(Arc::new(move |save| ron::to_string(&capture(save))));
+        self
+    }
+
+    /// Fill [`SaveSlot::fields`] from the saved resource on each save, e.g. the level name, character or
+    /// progress, on top of the [`SlotFields`] resource
+    pub fn with_meta_extractor(mut self, extract: fn(&T) -> SlotFields) -> Self {
+        self.meta_extractor = Some(extract);
+        self
+    }
+
+    /// Mirror saves to the S3-compatible bucket set in [`S3Setting`](crate::s3::S3Setting)
+    #[cfg(feature = "s3")]
+    pub fn with_s3_sync(mut self, strategy: crate::sync::ConflictStrategy) -> Self {
+        let backend = crate::s3::S3Backend::default();
+        self.s3 = Some(backend.clone());
+        self.with_cloud_sync(CloudSync::new(backend, strategy))
+    }
+
+    /// Store saves and settings in Steam Cloud and report [`SteamCloudConflict`](crate::steam::SteamCloudConflict) at startup
+    #[cfg(feature = "steam")]
+    pub fn with_steam_cloud(mut self, backend: crate::steam::SteamBackend) -> Self {
+        self.steam = Some(backend.clone());
+        self.with_backend(backend)
+    }
+
+    /// Store resource `R` in the same save file as `T`, in a section named after its type
+    pub fn register<R>(self) -> Self
+    where
+        R: Resource + Default + Serialize + DeserializeOwned,
+    {
+        self.register_as::<R>(section_name::<R>())
+    }
+
+    /// Same as [`Self::register`] with an explicit section name, which stays stable if the type is moved or renamed
+    pub fn register_as<R>(mut self, name: impl Into<String>) -> Self
+    where
+        R: Resource + Default + Serialize + DeserializeOwned,
+    {
+        self.registry.sections.push(SaveSection::new::<R>(name));
+        self
+    }
+
+    /// Store every entity with a [`Persist`](crate::scene::Persist) component in the save, respawning them on load
+    #[cfg(feature = "scene")]
+    pub fn persist_entities(mut self) -> Self {
+        self.registry.sections.push(crate::scene::scene_section());
+        self
+    }
 }
 
 impl<T> Plugin for EncryptSavePlugin<T>
@@ -54,221 +568,3101 @@ where
     T: Resource + Default + EncryptSave + Clone,
 {
     fn build(&self, app: &mut App) {
-        app.add_plugins(GameSettingSupportPlugin::<SaveConfig>::default())
-            .insert_resource(T::default())
+        if let Some(mode) = self.portable {
+            app.insert_resource(mode);
+        }
+        if let Some(project) = &self.project {
+            app.insert_resource(project.clone());
+        }
+        app.insert_resource(NamingRng::new(self.naming_seed));
+        let schedule = self.schedule.unwrap_or_else(|| Update.intern());
+        let mut registry = self.registry.clone();
+        for section in &registry.sections {
+            (section.init)(app);
+        }
+        registry
+            .sections
+            .insert(0, SaveSection::versioned::<T>(section_name::<T>()));
+        registry.version = T::VERSION;
+
+        // Every backend goes through the mode, even one inserted before the plugin
+        let storage = self
+            .storage
+            .clone()
+            .or_else(|| app.world().get_resource::<SaveStorage>().cloned())
+            .unwrap_or_default();
+        let switch = Arc::new(AtomicU8::new(self.mode as u8));
+        app.insert_resource(SaveStorage(Arc::new(ModeBackend::new(storage.0, switch.clone()))))
+            .insert_resource(ModeSwitch(switch))
+            .insert_resource(self.mode)
+            .add_message::<SaveRefused>()
+            .add_systems(First, switch_mode.run_if(resource_changed::<SaveManagerMode>));
+        match &self.cipher {
+            Some(cipher) => app.insert_resource(cipher.clone()),
+            None => app.init_resource::<SaveCipher>(),
+        };
+        let mut config = GameSettingSupportPlugin::<SaveConfig>::default();
+        if self.config_in_save_dir {
+            config = config.with_config_path(config_in_save_dir);
+        }
+        if self.protected_config {
+            config = config.with_key(SecretKey::from(T::ENCR_KEY));
+        }
+        app.add_plugins(config);
+        if let Some(retry) = self.retry {
+            app.insert_resource(retry);
+        }
+        app.insert_resource(T::default())
             .insert_resource(CurrentSave(0))
+            .init_resource::<Playtime>()
+            .insert_resource(self.options.clone())
+            .insert_resource(registry)
+            .init_resource::<SaveRequests>()
+            .init_resource::<UnverifiedSave>()
+            .init_resource::<DeltaBase>()
+            .init_resource::<SlicedSave>()
+            .init_resource::<SaveProgress>()
+            .init_resource::<LoadProgress>()
+            .init_resource::<SaveStats>()
+            .init_resource::<Snapshots>()
+            .init_resource::<SavePassword>()
+            .init_resource::<SaveKey>()
+            .init_resource::<SlotFields>()
             .add_message::<QuickSave>()
             .add_message::<SaveGame>()
+            .add_message::<SlotOccupied>()
+            .add_message::<SaveSuperseded>()
+            .add_message::<SlotBusy>()
+            .add_message::<GameSaved>()
             .add_message::<DeleteSave>()
             .add_message::<LoadGame>()
             .add_message::<LoadRecent>()
-            .add_systems(Update, on_load::<T>.run_if(on_message::<LoadGame>))
-            .add_systems(Update, on_load_recent::<T>.run_if(on_message::<LoadRecent>))
-            .add_systems(Update, on_save::<T>.run_if(on_message::<SaveGame>))
-            .add_systems(Update, on_quick_save::<T>.run_if(on_message::<QuickSave>))
-            .add_systems(Update, on_delete.run_if(on_message::<DeleteSave>));
-    }
-}
+            .add_message::<NoSaveFound>()
+            .add_message::<LoadFailed>()
+            .add_message::<LoadRecentFailed>()
+            .add_message::<CopySave>()
+            .add_message::<RenameSave>()
+            .add_message::<SetSlotTags>()
+            .add_message::<SetSlotDescription>()
+            .add_message::<SaveCopied>()
+            .add_message::<SaveRenamed>()
+            .add_message::<SavesPruned>()
+            .add_message::<RefreshSaveMetadata>()
+            .add_message::<SaveMetadataRefreshed>()
+            .add_message::<SaveCheckpoint>()
+            .add_message::<RollbackToCheckpoint>()
+            .add_message::<CheckpointSaved>()
+            .add_message::<CheckpointRestored>()
+            .add_message::<CheckpointNotFound>()
+            .add_message::<RollbackFailed>()
+            .add_message::<Snapshot>()
+            .add_message::<Restore>()
+            .add_message::<SnapshotRestored>()
+            .add_message::<SnapshotNotFound>()
+            .add_message::<SnapshotRestoreFailed>()
+            .add_message::<SetSavePassword>()
+            .add_message::<ReEncryptSaves>()
+            .add_message::<SavesReEncrypted>()
+            .add_message::<ReEncryptFailed>()
+            .add_message::<SaveVersionMismatch>()
+            .add_message::<ExportSave>()
+            .add_message::<ImportSave>()
+            .add_message::<SaveExported>()
+            .add_message::<SaveImported>()
+            .add_message::<ArchiveFailed>()
+            .configure_sets(schedule, (SaveSet::Capture, SaveSet::Write).chain())
+            .configure_sets(schedule, (LoadSet::Apply, LoadSet::PostLoad).chain())
+            .add_systems(Startup, prune_saves.after(load_config::<SaveConfig>))
+            .add_systems(
+                schedule,
+                prune_saves
+                    .after(load_config::<SaveConfig>)
+                    .run_if(on_message::<ProfileSwitched>),
+            )
+            .add_systems(
+                schedule,
+                collect_requests.before(LoadSet::Apply).before(SaveSet::Capture),
+            )
+            .add_systems(
+                schedule,
+                set_password
+                    .before(LoadSet::Apply)
+                    .before(SaveSet::Capture)
+                    .run_if(on_message::<SetSavePassword>),
+            )
+            .add_systems(schedule, process_loads::<T>.run_if(has_loads).in_set(LoadSet::Apply))
+            .add_systems(schedule, process_saves::<T>.run_if(has_saves).in_set(SaveSet::Write))
+            .add_systems(schedule, capture_slices.after(SaveSet::Write).run_if(has_slices))
+            .add_systems(
+                schedule,
+                track_writes
+                    .after(SaveSet::Write)
+                    .run_if(on_message::<SaveWritten>.or(on_message::<SaveFailed>)),
+            )
+            .add_systems(
+                schedule,
+                track_load_failures
+                    .after(LoadSet::Apply)
+                    .run_if(on_message::<LoadFailed>.or(on_message::<LoadRecentFailed>)),
+            )
+            .add_systems(
+                Last,
+                finish_sliced_save
+                    .before(flush_pending_writes)
+                    .run_if(on_message::<FlushSaves>.or(on_message::<AppExit>)),
+            )
+            .add_systems(Update, tick_playtime)
+            .add_systems(schedule, on_save_verified.run_if(on_message::<SaveVerified>))
+            .add_systems(schedule, record_written_size.run_if(on_message::<SaveWritten>))
+            .add_systems(schedule, on_delete.in_set(DeleteSet).run_if(on_message::<DeleteSave>))
+            .add_systems(schedule, on_copy.run_if(on_message::<CopySave>))
+            .add_systems(schedule, on_rename.run_if(on_message::<RenameSave>))
+            .add_systems(schedule, on_set_tags.run_if(on_message::<SetSlotTags>))
+            .add_systems(schedule, on_set_description.run_if(on_message::<SetSlotDescription>))
+            .add_systems(
+                schedule,
+                refresh_metadata
+                    .after(SaveSet::Write)
+                    .run_if(on_message::<RefreshSaveMetadata>),
+            )
+            .add_systems(
+                schedule,
+                on_export::<T>.after(SaveSet::Write).run_if(on_message::<ExportSave>),
+            )
+            .add_systems(schedule, on_import::<T>.run_if(on_message::<ImportSave>))
+            .add_systems(
+                schedule,
+                on_reencrypt::<T>
+                    .before(LoadSet::Apply)
+                    .before(SaveSet::Capture)
+                    .run_if(on_message::<ReEncryptSaves>),
+            );
 
-#[derive(Message)]
-pub struct QuickSave;
+        for condition in &self.conditions {
+            condition(app, schedule);
+        }
 
-#[derive(Message, Deref, DerefMut)]
-pub struct SaveGame(pub u32);
+        if self.options.track_state {
+            app.init_state::<SaveLoadState>()
+                .configure_sets(schedule, LoadSet::Apply.run_if(in_state(SaveLoadState::Loading)))
+                .configure_sets(schedule, SaveSet::Write.run_if(in_state(SaveLoadState::Saving)))
+                .add_systems(PreUpdate, drive_state);
+        }
 
-#[derive(Message, Deref, DerefMut)]
-pub struct DeleteSave(pub u32);
+        #[cfg(feature = "clipboard")]
+        app.add_message::<crate::clipboard::CopySaveToClipboard>()
+            .add_message::<crate::clipboard::PasteSaveFromClipboard>()
+            .add_message::<crate::clipboard::SaveCopiedToClipboard>()
+            .add_message::<crate::clipboard::SavePasted>()
+            .add_message::<crate::clipboard::ClipboardFailed>()
+            .add_systems(
+                schedule,
+                crate::clipboard::on_copy_to_clipboard::<T>
+                    .after(SaveSet::Write)
+                    .run_if(on_message::<crate::clipboard::CopySaveToClipboard>),
+            )
+            .add_systems(
+                schedule,
+                crate::clipboard::on_paste_from_clipboard::<T>
+                    .run_if(on_message::<crate::clipboard::PasteSaveFromClipboard>),
+            );
 
-#[derive(Message, Deref, DerefMut)]
-pub struct LoadGame(pub u32);
+        #[cfg(feature = "file-picker")]
+        app.add_message::<crate::file_picker::ExportSaveToFile>()
+            .add_message::<crate::file_picker::ImportSaveFromFile>()
+            .add_message::<crate::file_picker::SaveExportedToFile>()
+            .add_message::<crate::file_picker::SaveImportedFromFile>()
+            .add_message::<crate::file_picker::FilePickerFailed>()
+            .init_resource::<crate::file_picker::FilePickerResults>()
+            .add_systems(
+                schedule,
+                (
+                    crate::file_picker::on_export_to_file::<T>
+                        .after(SaveSet::Write)
+                        .run_if(on_message::<crate::file_picker::ExportSaveToFile>),
+                    crate::file_picker::on_import_from_file
+                        .run_if(on_message::<crate::file_picker::ImportSaveFromFile>),
+                    crate::file_picker::receive_picked_files::<T>,
+                ),
+            );
 
-#[derive(Message)]
-pub struct LoadRecent;
+        #[cfg(feature = "drag-and-drop")]
+        if self.options.drag_and_drop {
+            app.add_message::<FileDragAndDrop>().add_systems(
+                schedule,
+                import_dropped
+                    .before(on_import::<T>)
+                    .run_if(on_message::<FileDragAndDrop>),
+            );
+        }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct CurrentSave(pub u32);
+        if let Some(telemetry) = &self.telemetry {
+            app.insert_resource(telemetry.clone())
+                .add_systems(schedule, report_failures.after(SaveSet::Write).after(LoadSet::Apply));
+        }
 
-#[derive(Resource, Deserialize, Serialize, Clone, Default)]
-pub struct SaveConfig {
-    /// Valid save id start from 1
-    saves: HashMap<u32, PathBuf>,
-    save_dir: PathBuf,
-    last_saved: u32,
-}
+        if self.options.meta_sidecars {
+            app.add_plugins(crate::meta::MetaPlugin);
+        }
 
-impl GameSetting for SaveConfig {
-    const DEFAULT_CONF: &'static str = "save_setting.conf";
-}
+        #[cfg(feature = "thumbnail")]
+        if let Some(size) = self.options.thumbnail_size {
+            app.add_plugins(crate::thumbnail::ThumbnailPlugin { size });
+        }
 
-fn on_load<T>(
-    mut data: ResMut<T>,
-    mut load_message: MessageReader<LoadGame>,
-    mut current_save: ResMut<CurrentSave>,
-    save_config: Res<SaveConfig>,
-) where
-    T: Resource + EncryptSave,
-{
-    for id in load_message.read() {
-        if let Some(saved_path) = save_config.saves.get(&id.0) {
-            let saved_path = save_config.save_dir.join(saved_path);
-            if let Err(_e) = data.load_from(&saved_path) {
-                #[cfg(feature = "log")]
-                warn!("Failed to load save data {}: {}", saved_path.display(), _e);
-            } else {
-                current_save.0 = id.0;
+        if let Some(capture) = &self.custom_meta {
+            app.insert_resource(CustomMeta(capture.clone()));
+        }
+        if let Some(extract) = self.meta_extractor {
+            app.insert_resource(MetaExtractor(extract));
+        }
+
+        if let Some(sync) = &self.sync {
+            app.add_plugins(CloudSyncPlugin::<T> {
+                sync: sync.clone(),
+                _save: PhantomData,
+            });
+            if let Some(merge) = self.sync_merge {
+                app.add_plugins(SyncMergePlugin { merge });
             }
         }
-    }
-}
 
-fn on_load_recent<T>(mut data: ResMut<T>, mut current_save: ResMut<CurrentSave>, save_config: Res<SaveConfig>)
-where
-    T: Resource + EncryptSave,
-{
-    if let Some(saved_path) = save_config.saves.get(&save_config.last_saved) {
-        let saved_path = save_config.save_dir.join(saved_path);
-        if let Err(_e) = data.load_from(&saved_path) {
-            #[cfg(feature = "log")]
-            warn!("Failed to load save data {}: {}", saved_path.display(), _e);
-        } else {
-            current_save.0 = save_config.last_saved;
+        #[cfg(feature = "s3")]
+        if let Some(backend) = &self.s3 {
+            app.add_plugins(crate::s3::S3Plugin {
+                backend: backend.clone(),
+            });
+        }
+
+        #[cfg(feature = "steam")]
+        if let Some(backend) = &self.steam {
+            app.add_plugins(crate::steam::SteamCloudPlugin {
+                backend: backend.clone(),
+            });
+        }
+
+        #[cfg(feature = "keyring")]
+        if let Some(service) = &self.keyring {
+            app.add_plugins(crate::keyring::KeyringPlugin {
+                service: service.clone(),
+            })
+            .add_systems(
+                Startup,
+                crate::keyring::migrate_saves::<T>
+                    .after(load_config::<SaveConfig>)
+                    .before(prune_saves),
+            );
+        }
+
+        #[cfg(feature = "signing")]
+        if let Some(key) = &self.signing {
+            app.insert_resource(key.clone());
         }
     }
 }
 
-fn on_save<T>(
-    data: Res<T>,
-    mut save_message: MessageReader<SaveGame>,
-    mut current_save: ResMut<CurrentSave>,
-    mut save_config: ResMut<SaveConfig>,
-    mut setting_changed: MessageWriter<GameSettingChanged>,
-) where
-    T: Resource + EncryptSave,
-{
-    for msg in save_message.read() {
-        let save_id = **msg;
-        save(
-            save_id,
-            &data,
-            &mut current_save,
-            &mut save_config,
-            &mut setting_changed,
-        );
-    }
+/// Order systems around saving, in `Update` unless set by [`EncryptSavePlugin::in_schedule`]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SaveSet {
+    /// Runs before serialization, e.g. to copy ECS state into the saved resources
+    Capture,
+    /// Serializes the saved resources and writes the file
+    Write,
 }
 
-fn on_quick_save<T>(
-    data: Res<T>,
-    mut current_save: ResMut<CurrentSave>,
-    mut save_config: ResMut<SaveConfig>,
-    mut setting_changed: MessageWriter<GameSettingChanged>,
-) where
-    T: Resource + EncryptSave,
-{
-    let save_id = **current_save;
-    save(
-        save_id,
-        &data,
-        &mut current_save,
-        &mut save_config,
-        &mut setting_changed,
-    );
+/// Deletion of slots, gated by [`EncryptSavePlugin::run_if`]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct DeleteSet;
+
+/// Order systems around loading, in `Update` unless set by [`EncryptSavePlugin::in_schedule`]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoadSet {
+    /// Reads the file and replaces the saved resources
+    Apply,
+    /// Runs after loaded data is in place, e.g. to rebuild derived state
+    PostLoad,
 }
 
-fn save<T>(
-    save_id: u32,
-    data: &Res<T>,
-    current_save: &mut ResMut<CurrentSave>,
-    save_config: &mut ResMut<SaveConfig>,
-    setting_changed: &mut MessageWriter<GameSettingChanged>,
-) where
-    T: Resource + EncryptSave,
-{
-    if save_id == 0 {
-        let file_name = format!("{}.dat", random_string());
-        let saved_path = save_config.save_dir.join(file_name.as_str());
-        if let Err(_e) = data.save_to(saved_path.clone()) {
-            #[cfg(feature = "log")]
-            error!("Failed to save data {}: {}", saved_path.display(), _e);
-        } else {
-            // TODO: Handle max_key == max of u32
-            let new_key = if let Some(max_key) = save_config.saves.keys().max() { max_key + 1 } else { 1 };
-            save_config.saves.insert(new_key, PathBuf::from(file_name));
-            save_config.last_saved = new_key;
-            current_save.0 = new_key;
-            setting_changed.write(GameSettingChanged);
+/// Save to the current slot, marking it as [`SlotKind::Quick`]
+#[derive(Message)]
+pub struct QuickSave;
+
+/// Save to slot `id`. Use `id = 0` to create a new slot.
+#[derive(Message, Deref, DerefMut)]
+pub struct SaveGame {
+    #[deref]
+    pub id: u32,
+    /// Replace data of an occupied slot. Otherwise [`SlotOccupied`] is sent back.
+    pub overwrite: bool,
+    /// Recorded in the slot, replacing the kind of its previous save
+    pub kind: SlotKind,
+    /// Write the slot without encryption, see [`SaveGame::plain`]
+    pub plain: bool,
+}
+
+impl SaveGame {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            overwrite: false,
+            kind: SlotKind::Manual,
+            plain: false,
         }
-    } else {
-        if let Some(saved_path) = save_config.saves.get(&save_id) {
-            let saved_path = save_config.save_dir.join(saved_path);
-            if let Err(_e) = data.save_to(saved_path.clone()) {
-                #[cfg(feature = "log")]
-                error!("Failed to save data {}: {}", saved_path.display(), _e);
-            } else {
-                save_config.last_saved = save_id;
-                current_save.0 = save_id;
-            }
+    }
+
+    pub fn overwrite(id: u32) -> Self {
+        Self {
+            overwrite: true,
+            ..Self::new(id)
         }
     }
-}
 
-fn on_delete(
-    mut current_save: ResMut<CurrentSave>,
-    mut delete_event: MessageReader<DeleteSave>,
-    mut save_config: ResMut<SaveConfig>,
-) {
-    for saved_id in delete_event.read() {
-        if let Some(saved_path) = save_config.saves.get(&saved_id) {
-            if let Err(_e) = fs::remove_file(saved_path) {
-                #[cfg(feature = "log")]
-                error!("Failed to delete save data {}: {}", saved_path.display(), _e);
-            } else {
-                save_config.saves.remove(&saved_id);
-                current_save.0 = 0;
-                if save_config.last_saved == **saved_id {
-                    save_config.last_saved = 0;
-                }
-            }
+    /// Overwrite slot `id` as an [`SlotKind::Auto`] save
+    pub fn auto(id: u32) -> Self {
+        Self {
+            kind: SlotKind::Auto,
+            ..Self::overwrite(id)
         }
     }
+
+    /// Write the slot without encryption, e.g. for a debug slot. The slot stays plain for its later saves, and is
+    /// still loaded like the others. Its archives are only imported where the [`SaveCipher`] is [`PlainCipher`].
+    pub fn plain(self) -> Self {
+        Self { plain: true, ..self }
+    }
 }
 
-pub trait EncryptSave: Serialize + for<'de> Deserialize<'de> {
-    const ENCR_KEY: &'static str = "0123456789abcdef";
+/// Write the save messages from [`Commands`] or the [`World`], without a [`MessageWriter`] for each of them,
+/// e.g. in observers and UI callbacks
+pub trait SaveCommandsExt {
+    /// Save to slot `id` unless it already has a save, answered with [`SlotOccupied`] then.
+    /// Use `id = 0` to create a new slot.
+    fn save_game(&mut self, id: u32);
 
-    fn load_from(&mut self, config_path: &Path) -> anyhow::Result<()> {
-        let enc_saved = std::fs::read(config_path)?;
-        let decrypted = decrypt(enc_saved.as_slice(), Self::ENCR_KEY.as_bytes())?;
-        (*self, _) = bincode::serde::decode_from_slice(decrypted.as_slice(), bincode::config::legacy())?;
-        Ok(())
+    /// Save to slot `id`, replacing its data
+    fn overwrite_game(&mut self, id: u32);
+
+    /// Load slot `id`, answered with [`LoadFailed`] if it can't be
+    fn load_game(&mut self, id: u32);
+
+    /// Load the last saved slot, falling back to the others, see [`LoadRecent`]
+    fn load_recent(&mut self);
+
+    /// Delete slot `id` and its files
+    fn delete_save(&mut self, id: u32);
+}
+
+impl SaveCommandsExt for Commands<'_, '_> {
+    fn save_game(&mut self, id: u32) {
+        self.write_message(SaveGame::new(id));
     }
 
-    fn save_to(&self, saved_path: PathBuf) -> anyhow::Result<()> {
-        let data = bincode::serde::encode_to_vec(self, bincode::config::legacy())?;
-        let enc_saved = encrypt(data.as_slice(), Self::ENCR_KEY.as_bytes())?;
+    fn overwrite_game(&mut self, id: u32) {
+        self.write_message(SaveGame::overwrite(id));
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        IoTaskPool::get()
-            .spawn(async move {
-                if let Some(parent_dir) = saved_path.parent() {
-                    fs::create_dir_all(parent_dir)?;
-                }
-                File::create(saved_path).and_then(|mut file| file.write_all(enc_saved.as_slice()))
-            })
-            .detach();
+    fn load_game(&mut self, id: u32) {
+        self.write_message(LoadGame(id));
+    }
 
-        Ok(())
+    fn load_recent(&mut self) {
+        self.write_message(LoadRecent);
+    }
+
+    fn delete_save(&mut self, id: u32) {
+        self.write_message(DeleteSave(id));
     }
 }
 
-fn random_string() -> String {
-    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
-    const LEN: usize = 12;
+impl SaveCommandsExt for World {
+    fn save_game(&mut self, id: u32) {
+        self.write_message(SaveGame::new(id));
+    }
 
-    (0..LEN)
-        .map(|_| {
-            let idx = fastrand::usize(..CHARSET.len());
-            CHARSET[idx] as char
-        })
-        .collect()
+    fn overwrite_game(&mut self, id: u32) {
+        self.write_message(SaveGame::overwrite(id));
+    }
+
+    fn load_game(&mut self, id: u32) {
+        self.write_message(LoadGame(id));
+    }
+
+    fn load_recent(&mut self) {
+        self.write_message(LoadRecent);
+    }
+
+    fn delete_save(&mut self, id: u32) {
+        self.write_message(DeleteSave(id));
+    }
+}
+
+/// Save to slot `id` right away and wait for its file, e.g. in exclusive systems or before the app exits.
+/// Use `id = 0` to create a new slot. Returns the id of the slot, or [`SaveError::SlotOccupied`] if it exists, see
+/// [`overwrite_world_blocking`].
+/// Errors are returned rather than sent, except for a failed write of the file, still sent as [`SaveFailed`].
+pub fn save_world_blocking<T>(world: &mut World, id: u32) -> Result<u32, SaveError>
+where
+    T: Resource + EncryptSave + Clone,
+{
+    if world.resource::<SaveConfig>().saves.contains_key(&id) {
+        return Err(SaveError::SlotOccupied(id));
+    }
+    overwrite_world_blocking::<T>(world, id)
+}
+
+/// [`save_world_blocking`] replacing slot `id` if it exists
+pub fn overwrite_world_blocking<T>(world: &mut World, id: u32) -> Result<u32, SaveError>
+where
+    T: Resource + EncryptSave + Clone,
+{
+    if *world.resource::<SaveManagerMode>() == SaveManagerMode::ReadOnly {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Saves are read-only").into());
+    }
+    #[cfg(feature = "keyring")]
+    if let Some(unavailable) = world.get_resource::<crate::keyring::KeyringUnavailable>() {
+        return Err(SaveError::Invalid(format!(
+            "The save key is unavailable: {}",
+            unavailable.error
+        )));
+    }
+    let id = save::<T>(world, id, SlotKind::Manual, false).map_err(|failure| failure.error)?;
+    finish_sliced_save(world);
+    world.resource::<PendingWrites>().flush();
+    Ok(id)
+}
+
+/// Load slot `id` right away, once every pending write is done. Errors are returned rather than sent.
+pub fn load_world_blocking<T>(world: &mut World, id: u32) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    finish_sliced_save(world);
+    world.resource::<PendingWrites>().flush();
+    load::<T>(world, id)
+}
+
+/// Size the file of slot `id` would have if saved now, failing like the save would when it doesn't fit the slot
+/// budget, the quota or the disk. Use `id = 0` for a new slot. Lets menus tell the player the space needed beforehand.
+pub fn measure_save<T>(world: &World, id: u32) -> Result<u64, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let data = serialize(world, Some(id))?;
+    let options = world.resource::<SaveOptions>();
+    let sealed = seal(
+        slot_cipher(world, id).0.as_ref(),
+        &data,
+        &save_key::<T>(world.resource::<SaveKey>()),
+        world.resource::<SavePassword>().get(),
+        options.game_version.as_deref(),
+    )?;
+    #[cfg(feature = "signing")]
+    let sealed = crate::signing::sign_with(world.get_resource(), sealed);
+
+    let save_config = world.resource::<SaveConfig>();
+    let save_dir = save_config.save_dir(world.resource::<SaveDirs>());
+    let storage = world.resource::<SaveStorage>();
+    let file = match save_config.slot(id) {
+        Some(slot) => slot.file.clone(),
+        // A throwaway generator, drawing from `NamingRng` would change the names of the seeded saves that follow
+        None => new_save_file(
+            &options.naming,
+            &NamingRng::new(None),
+            world.resource::<CurrentProfile>(),
+            id,
+            |file| {
+                save_config.file_taken(
+                    world.resource::<SaveDirs>(),
+                    storage,
+                    world.resource::<PendingWrites>(),
+                    file,
+                )
+            },
+        ),
+    };
+    let size = sealed.len() as u64;
+    check_space(
+        world.resource::<SaveStorage>().0.as_ref(),
+        &save_dir,
+        &save_dir.join(file),
+        size,
+        options.quota,
+        options.slot_budget,
+    )?;
+    Ok(size)
+}
+
+/// What triggered the last save of a slot
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+pub enum SlotKind {
+    #[default]
+    Manual,
+    Auto,
+    Quick,
+    Checkpoint,
+}
+
+/// Sent once the data of a slot has been serialized and handed over to be written
+#[derive(Message, Deref, DerefMut)]
+pub struct GameSaved(pub u32);
+
+/// Response to a [`SaveGame`] without `overwrite` that targets an existing slot
+#[derive(Message, Deref, DerefMut)]
+pub struct SlotOccupied(pub u32);
+
+/// A save to this slot was dropped for a newer one, see [`EncryptSavePlugin::with_save_coalescing`]
+#[derive(Message, Deref, DerefMut, Debug)]
+pub struct SaveSuperseded(pub u32);
+
+/// A request on `slot` waits for another one to complete, it runs once that is done: a load waits for a write
+/// of the slot file still in flight, so it never reads a torn file, and a save waits for a waiting load of the slot
+#[derive(Message, Debug)]
+pub struct SlotBusy {
+    pub slot: u32,
+    pub operation: SaveOperation,
+}
+
+#[derive(Message, Deref, DerefMut)]
+pub struct DeleteSave(pub u32);
+
+/// Load slot `id`. Every section of the save is decoded before the world is touched, so the resources are either
+/// all replaced or all left as they were.
+#[derive(Message, Deref, DerefMut)]
+pub struct LoadGame(pub u32);
+
+/// Load `last_saved`, falling back to the other slots from the most recently played
+#[derive(Message)]
+pub struct LoadRecent;
+
+/// Response to [`LoadRecent`] when there is no slot at all
+#[derive(Message)]
+pub struct NoSaveFound;
+
+/// Response to a [`LoadGame`] that failed. The saved resources still hold the values they had before the load.
+#[derive(Message, Debug)]
+pub struct LoadFailed {
+    pub slot: u32,
+    pub error: SaveError,
+}
+
+/// Response to [`LoadRecent`] when every slot failed to load, the saved resources are left as they were
+#[derive(Message)]
+pub struct LoadRecentFailed {
+    pub errors: Vec<(u32, SaveError)>,
+}
+
+/// Duplicate slot `from` into slot `to`. Use `to = 0` to create a new slot.
+#[derive(Message)]
+pub struct CopySave {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Message)]
+pub struct RenameSave {
+    pub id: u32,
+    pub name: String,
+}
+
+/// Replace the tags of slot `id`
+#[derive(Message)]
+pub struct SetSlotTags {
+    pub id: u32,
+    pub tags: BTreeSet<String>,
+}
+
+/// Replace the description of slot `id`, e.g. notes typed by the player in the load menu
+#[derive(Message)]
+pub struct SetSlotDescription {
+    pub id: u32,
+    pub text: String,
+}
+
+#[derive(Message)]
+pub struct SaveCopied {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Message, Deref, DerefMut)]
+pub struct SaveRenamed(pub u32);
+
+/// Sent at startup when slots with missing files or orphaned save files were removed
+#[derive(Message)]
+pub struct SavesPruned {
+    /// Slots whose file no longer exists
+    pub missing: Vec<u32>,
+    /// Files in the save directory not referenced by any slot
+    pub orphans: Vec<PathBuf>,
+}
+
+/// Read the files of slot `id`, or of every slot if `None`, again after they were changed outside of the game,
+/// e.g. by a cloud sync client or a manual copy. Slots take the values of their `.meta` sidecar.
+#[derive(Message, Clone, Copy, Debug, Default)]
+pub struct RefreshSaveMetadata(pub Option<u32>);
+
+/// Sent once [`RefreshSaveMetadata`] is handled
+#[derive(Message, Clone, Debug, Default)]
+pub struct SaveMetadataRefreshed {
+    /// Slots read again
+    pub slots: Vec<u32>,
+    /// Slots whose file no longer exists, now removed
+    pub missing: Vec<u32>,
+}
+
+/// Save to the checkpoint ring set up by [`EncryptSavePlugin::with_checkpoints`], dropping the oldest one when full
+#[derive(Message)]
+pub struct SaveCheckpoint;
+
+/// Restore the `n`-th most recent checkpoint, 1 being the latest. Newer checkpoints are discarded.
+#[derive(Message, Deref, DerefMut)]
+pub struct RollbackToCheckpoint(pub usize);
+
+#[derive(Message)]
+pub struct CheckpointSaved;
+
+#[derive(Message, Deref, DerefMut)]
+pub struct CheckpointRestored(pub usize);
+
+/// Response to [`RollbackToCheckpoint`] when there are fewer checkpoints than requested
+#[derive(Message, Deref, DerefMut)]
+pub struct CheckpointNotFound(pub usize);
+
+#[derive(Message)]
+pub struct RollbackFailed {
+    pub n: usize,
+    pub error: SaveError,
+}
+
+/// Serialize the saved resources into memory, see [`EncryptSavePlugin::with_snapshots`]
+#[derive(Message)]
+pub struct Snapshot;
+
+/// Restore the `n`-th most recent [`Snapshot`], 1 being the latest. It is removed with every newer snapshot,
+/// so repeating `Restore(1)` steps back through the history.
+#[derive(Message, Deref, DerefMut)]
+pub struct Restore(pub usize);
+
+#[derive(Message, Deref, DerefMut)]
+pub struct SnapshotRestored(pub usize);
+
+/// Response to [`Restore`] when there are fewer snapshots than requested
+#[derive(Message, Deref, DerefMut)]
+pub struct SnapshotNotFound(pub usize);
+
+/// Response to a [`Restore`] that failed. The snapshots are kept and the saved resources hold the values they had
+/// before the restore.
+#[derive(Message, Debug)]
+pub struct SnapshotRestoreFailed {
+    pub n: usize,
+    pub error: SaveError,
+}
+
+/// Decrypt every slot and checkpoint of the current profile with `old_key` and encrypt it again with `new_key`,
+/// which is then used for new saves through [`SaveKey`]. Store `new_key` where it is read at the next startup.
+/// Nothing is written and the key is kept when a file fails to open, each one is reported with [`ReEncryptFailed`].
+/// A file which fails to be written is reported too and keeps `old_key`, while the key changes for the others.
+/// Files already encrypted with `new_key` are skipped, so sending the same message again finishes the rotation.
+/// Refused while a [`SavePassword`] is set, the files are then encrypted with the password.
+#[derive(Message)]
+pub struct ReEncryptSaves {
+    pub old_key: SecretKey,
+    pub new_key: SecretKey,
+}
+
+/// Response to [`ReEncryptSaves`] with the number of files written, once the key has changed
+#[derive(Message, Deref)]
+pub struct SavesReEncrypted(pub usize);
+
+/// Sent before loading a save written by another version of the game, see [`EncryptSavePlugin::with_game_version`]
+#[derive(Message, Debug)]
+pub struct SaveVersionMismatch {
+    /// `None` for checkpoints
+    pub slot: Option<u32>,
+    /// `None` for saves written without a game version
+    pub saved: Option<String>,
+    pub current: String,
+}
+
+/// A file that kept [`ReEncryptSaves`] from changing the key, or which is still encrypted with the old key
+#[derive(Message)]
+pub struct ReEncryptFailed {
+    /// `None` for checkpoints
+    pub slot: Option<u32>,
+    pub path: PathBuf,
+    pub error: SaveError,
+}
+
+/// In-memory history written by [`Snapshot`], newest first
+#[derive(Resource, Default)]
+pub struct Snapshots {
+    history: VecDeque<Vec<u8>>,
+    bytes: usize,
+}
+
+impl Snapshots {
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// Memory used by the history, in bytes
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.bytes = 0;
+    }
+
+    fn push(&mut self, data: Vec<u8>, depth: usize, budget: usize) {
+        self.bytes += data.len();
+        self.history.push_front(data);
+        // Always keep the snapshot just taken, even if it alone exceeds the budget
+        while self.history.len() > depth.max(1) || (self.bytes > budget && self.history.len() > 1) {
+            if let Some(dropped) = self.history.pop_back() {
+                self.bytes -= dropped.len();
+            }
+        }
+    }
+
+    /// The `n`-th most recent snapshot, 1 being the latest
+    fn get(&self, n: usize) -> Option<&[u8]> {
+        n.checked_sub(1)
+            .and_then(|i| self.history.get(i))
+            .map(Vec::as_slice)
+    }
+
+    /// Remove the `n` most recent snapshots and return the oldest of them
+    fn pop(&mut self, n: usize) -> Option<Vec<u8>> {
+        if n == 0 || n > self.history.len() {
+            return None;
+        }
+        let mut popped = self.history.drain(..n).collect::<Vec<_>>();
+        self.bytes -= popped.iter().map(Vec::len).sum::<usize>();
+        popped.pop()
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct CurrentSave(pub u32);
+
+/// Time spent in game, ticked every frame with virtual time.
+/// It is recorded into the slot on save and restored on load. Reset it when starting a new game.
+#[derive(Resource, Deref, DerefMut, Clone, Copy, Default, Debug)]
+pub struct Playtime(pub Duration);
+
+#[derive(Resource, Clone, Default)]
+pub struct SaveOptions {
+    pub delete_orphans: bool,
+    pub track_state: bool,
+    /// Size of the checkpoint ring, 0 to disable checkpoints
+    pub checkpoints: usize,
+    /// Number of snapshots kept in memory, 0 to disable snapshots
+    pub snapshot_depth: usize,
+    /// Memory used by snapshots, in bytes
+    pub snapshot_budget: usize,
+    /// Maximum size of slot thumbnails, `None` to disable them
+    #[cfg(feature = "thumbnail")]
+    pub thumbnail_size: Option<bevy::math::UVec2>,
+    /// Recorded in the header of each save
+    pub game_version: Option<String>,
+    pub meta_sidecars: bool,
+    pub naming: NamingStrategy,
+    pub id_allocation: IdAllocation,
+    /// Saves to the same slot within this window are coalesced, `None` to run each of them
+    pub coalesce_window: Option<Duration>,
+    pub fsync: bool,
+    pub verify_after_write: bool,
+    /// Maximum size of the files in the save directory and the profile directory, in bytes
+    pub quota: Option<u64>,
+    /// Maximum size of one save file, in bytes
+    pub slot_budget: Option<u64>,
+    pub load_limits: LoadLimits,
+    /// Chunk size of streamed saves, `None` to encrypt and write saves at once
+    pub stream_chunk_size: Option<u32>,
+    /// Delta autosaves between two full ones, `None` to write autosaves in full
+    pub delta_autosaves: Option<u32>,
+    /// Serialize and encrypt saves on the `IoTaskPool`
+    pub background_saves: bool,
+    /// Time spent capturing background saves per frame, `None` to capture them at once
+    pub slice_budget: Option<Duration>,
+    #[cfg(feature = "drag-and-drop")]
+    pub drag_and_drop: bool,
+}
+
+/// Sizes above which a file is refused with [`SaveError::TooLarge`] before it is decoded,
+/// so a corrupted or malicious save can't exhaust the memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoadLimits {
+    /// Size of save files and archives, in bytes
+    pub max_file_size: u64,
+    /// Size of compressed data once decompressed, like [clipboard](crate::clipboard) strings
+    pub max_decompressed_size: u64,
+    /// Size of the decrypted data handed to the decoder. Collections are not preallocated past a small bound,
+    /// so the memory used while decoding grows with this size.
+    pub max_allocation: u64,
+}
+
+impl Default for LoadLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 256 << 20,
+            max_decompressed_size: 256 << 20,
+            max_allocation: 256 << 20,
+        }
+    }
+}
+
+impl LoadLimits {
+    pub(crate) fn check(size: u64, limit: u64) -> Result<(), SaveError> {
+        if size > limit {
+            return Err(SaveError::TooLarge { size, limit });
+        }
+        Ok(())
+    }
+}
+
+/// Enabled by [`EncryptSavePlugin::with_state`]. Loads take priority when both are pending.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SaveLoadState {
+    #[default]
+    Idle,
+    Saving,
+    Loading,
+}
+
+enum SaveRequest {
+    Slot {
+        id: u32,
+        overwrite: bool,
+        kind: SlotKind,
+        plain: bool,
+    },
+    Quick,
+    Checkpoint,
+    Snapshot,
+}
+
+enum LoadRequest {
+    Slot(u32),
+    Recent,
+    Checkpoint(usize),
+    Snapshot(usize),
+}
+
+/// Slot to make `last_saved` once its file is verified
+#[derive(Resource, Default)]
+struct UnverifiedSave(Option<u32>);
+
+/// Decrypted base of the last delta save, so the next one doesn't read it back
+#[derive(Resource, Default)]
+struct DeltaBase(Option<(PathBuf, Zeroizing<Vec<u8>>)>);
+
+/// Background save whose sections are still being captured, see [`EncryptSavePlugin::with_sliced_saves`]
+#[derive(Resource, Default)]
+struct SlicedSave(Option<PendingSlices>);
+
+struct PendingSlices {
+    path: PathBuf,
+    slot: Option<u32>,
+    /// Sections captured so far, the main resource excluded
+    others: Vec<(String, Vec<u8>)>,
+    /// Hands the save over to be written once every section is captured
+    write: Box<dyn FnOnce(Vec<(String, Vec<u8>)>) + Send + Sync>,
+}
+
+impl DeltaBase {
+    fn get(&self, file: &Path) -> Option<&[u8]> {
+        self.0
+            .as_ref()
+            .filter(|(cached, _)| cached == file)
+            .map(|(_, data)| data.as_slice())
+    }
+}
+
+/// Save and load messages waiting to be processed
+#[derive(Resource, Default)]
+struct SaveRequests {
+    saves: VecDeque<SaveRequest>,
+    loads: VecDeque<LoadRequest>,
+    /// Coalesced saves by slot, with the end of their window
+    delayed: BTreeMap<u32, (Duration, SaveRequest)>,
+    /// Slots of the loads waiting for their file to be written, see [`SlotBusy`]
+    waiting_loads: BTreeSet<u32>,
+    /// Slots of the saves waiting for a load of the slot
+    waiting_saves: BTreeSet<u32>,
+}
+
+impl SaveRequest {
+    /// Existing slot written by this request, `None` for new slots and requests that are never coalesced
+    fn coalesced_slot(&self, current_save: u32) -> Option<u32> {
+        match self {
+            SaveRequest::Slot { id, .. } => Some(*id),
+            SaveRequest::Quick => Some(current_save),
+            SaveRequest::Checkpoint | SaveRequest::Snapshot => None,
+        }
+        .filter(|id| *id != 0)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SaveSlot {
+    /// File name, relative to the save directory
+    pub file: PathBuf,
+    /// User-visible label
+    pub name: String,
+    /// Notes of the player, see [`SetSlotDescription`]
+    pub description: String,
+    /// Screenshot taken at save time, relative to the save directory
+    pub thumbnail: Option<PathBuf>,
+    /// [`Playtime`] at save time
+    pub playtime: Duration,
+    /// Seconds since the Unix epoch, 0 if never happened
+    pub created_at: u64,
+    pub saved_at: u64,
+    pub loaded_at: u64,
+    /// Incremented every time the slot changes
+    pub revision: u64,
+    /// `revision` at the last cloud sync
+    pub synced_revision: u64,
+    pub kind: SlotKind,
+    /// Free-form labels, see [`SetSlotTags`]
+    pub tags: BTreeSet<String>,
+    /// Game version at save time, see [`EncryptSavePlugin::with_game_version`]
+    pub game_version: Option<String>,
+    /// [`SlotFields`] at save time
+    pub fields: BTreeMap<String, String>,
+    /// Full save that `file` is a delta of, relative to the save directory.
+    /// See [`EncryptSavePlugin::with_delta_autosaves`].
+    pub base: Option<PathBuf>,
+    /// Delta saves written against `base`
+    pub deltas: u32,
+    /// Saved without encryption, see [`SaveGame::plain`]
+    pub plain: bool,
+    /// RON of the metadata captured by [`EncryptSavePlugin::with_custom_meta`] at save time
+    pub custom: Option<String>,
+}
+
+/// Custom fields copied into the slot on each save, e.g. the chapter or location to show in the load menu
+#[derive(Resource, Deref, DerefMut, Clone, Default, Debug)]
+pub struct SlotFields(pub BTreeMap<String, String>);
+
+impl SlotFields {
+    /// Add the field `key`, e.g. in the closure of [`EncryptSavePlugin::with_meta_extractor`]
+    pub fn with(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.0.insert(key.into(), value.to_string());
+        self
+    }
+}
+
+impl SaveSlot {
+    /// Last time this slot was saved or loaded, in seconds since the Unix epoch
+    pub fn last_played(&self) -> u64 {
+        self.saved_at.max(self.loaded_at)
+    }
+
+    /// Metadata captured by [`EncryptSavePlugin::with_custom_meta`], `None` if the slot was saved without it
+    /// or it no longer deserializes into `M`
+    pub fn custom_meta<M>(&self) -> Option<M>
+    where
+        M: DeserializeOwned,
+    {
+        ron::from_str(self.custom.as_deref()?).ok()
+    }
+}
+
+#[derive(Resource)]
+struct CustomMeta<T>(CaptureMeta<T>);
+
+#[derive(Resource)]
+struct MetaExtractor<T>(fn(&T) -> SlotFields);
+
+#[derive(Resource, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SaveConfig {
+    /// Valid save id start from 1
+    #[serde(deserialize_with = "deserialize_slots")]
+    saves: HashMap<u32, SaveSlot>,
+    save_dir: PathBuf,
+    last_saved: u32,
+    /// Newest first
+    checkpoints: VecDeque<SaveSlot>,
+    /// Slots deleted since they were last synced, until cloud sync deletes their remote copy
+    deleted: BTreeSet<u32>,
+}
+
+/// Slot of the index, or only its file in indexes written before slots had metadata.
+/// Read without buffering, which RON can't do for the enums of the slot.
+struct StoredSlot(SaveSlot);
+
+impl<'de> Deserialize<'de> for StoredSlot {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(StoredSlotVisitor)
+    }
+}
+
+struct StoredSlotVisitor;
+
+impl<'de> Visitor<'de> for StoredSlotVisitor {
+    type Value = StoredSlot;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a save slot or the path of its file")
+    }
+
+    fn visit_str<E>(self, file: &str) -> Result<StoredSlot, E>
+    where
+        E: de::Error,
+    {
+        Ok(StoredSlot(SaveSlot {
+            file: PathBuf::from(file),
+            ..Default::default()
+        }))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<StoredSlot, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        SaveSlot::deserialize(MapAccessDeserializer::new(map)).map(StoredSlot)
+    }
+}
+
+fn deserialize_slots<'de, D>(deserializer: D) -> Result<HashMap<u32, SaveSlot>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let slots = HashMap::<u32, StoredSlot>::deserialize(deserializer)?;
+    Ok(slots.into_iter().map(|(id, StoredSlot(slot))| (id, slot)).collect())
+}
+
+impl SaveConfig {
+    pub fn slots(&self) -> &HashMap<u32, SaveSlot> {
+        &self.saves
+    }
+
+    pub fn slot(&self, id: u32) -> Option<&SaveSlot> {
+        self.saves.get(&id)
+    }
+
+    pub fn last_saved(&self) -> u32 {
+        self.last_saved
+    }
+
+    /// Slots sorted from the most recently played
+    pub fn sorted_by_recency(&self) -> Vec<(u32, &SaveSlot)> {
+        let mut slots: Vec<(u32, &SaveSlot)> = self.saves.iter().map(|(id, slot)| (*id, slot)).collect();
+        slots.sort_by(|(a_id, a), (b_id, b)| (b.last_played(), b_id).cmp(&(a.last_played(), a_id)));
+        slots
+    }
+
+    /// Slots of `kind`, sorted from the most recently played
+    pub fn slots_by_kind(&self, kind: SlotKind) -> Vec<(u32, &SaveSlot)> {
+        self.sorted_by_recency()
+            .into_iter()
+            .filter(|(_, slot)| slot.kind == kind)
+            .collect()
+    }
+
+    /// Slots tagged with `tag`, sorted from the most recently played
+    pub fn slots_with_tag(&self, tag: &str) -> Vec<(u32, &SaveSlot)> {
+        self.sorted_by_recency()
+            .into_iter()
+            .filter(|(_, slot)| slot.tags.contains(tag))
+            .collect()
+    }
+
+    pub fn most_recent(&self) -> Option<(u32, &SaveSlot)> {
+        self.saves
+            .iter()
+            .map(|(id, slot)| (*id, slot))
+            .max_by_key(|(id, slot)| (slot.last_played(), *id))
+    }
+
+    /// Relative to [`SaveDirs::data_dir`] in [`PortableMode`], with a [`Project`] or [`SavePaths`](crate::paths::SavePaths)
+    pub fn save_dir(&self, dirs: &SaveDirs) -> Cow<'_, Path> {
+        dirs.resolve(&self.save_dir)
+    }
+
+    /// Hidden slots written by [`SaveCheckpoint`], newest first
+    pub fn checkpoints(&self) -> &VecDeque<SaveSlot> {
+        &self.checkpoints
+    }
+
+    /// Files encrypted with the key, of every slot not saved as plain and of every checkpoint, with their slot
+    pub(crate) fn sealed_files(&self) -> Vec<(Option<u32>, PathBuf)> {
+        self.saves
+            .iter()
+            .filter(|(_, slot)| !slot.plain)
+            .flat_map(|(id, slot)| {
+                slot.base
+                    .iter()
+                    .chain([&slot.file])
+                    .map(|file| (Some(*id), file.clone()))
+            })
+            .chain(self.checkpoints.iter().map(|checkpoint| (None, checkpoint.file.clone())))
+            .collect()
+    }
+
+    pub(crate) fn slot_mut(&mut self, id: u32) -> Option<&mut SaveSlot> {
+        self.saves.get_mut(&id)
+    }
+
+    pub(crate) fn insert_slot(&mut self, id: u32, slot: SaveSlot) {
+        self.saves.insert(id, slot);
+    }
+
+    pub(crate) fn deleted(&self) -> &BTreeSet<u32> {
+        &self.deleted
+    }
+
+    pub(crate) fn forget_deleted(&mut self, id: u32) {
+        self.deleted.remove(&id);
+    }
+
+    pub(crate) fn slot_path(&self, dirs: &SaveDirs, id: u32) -> Option<PathBuf> {
+        self.saves.get(&id).map(|slot| self.save_dir(dirs).join(&slot.file))
+    }
+
+    /// Whether `file`, relative to the save directory, belongs to a slot or checkpoint, exists or is being written
+    pub(crate) fn file_taken(
+        &self,
+        dirs: &SaveDirs,
+        storage: &SaveStorage,
+        writes: &PendingWrites,
+        file: &Path,
+    ) -> bool {
+        let path = self.save_dir(dirs).join(file);
+        self.saves
+            .values()
+            .chain(&self.checkpoints)
+            .any(|slot| slot.file == file || slot.base.as_deref() == Some(file))
+            || storage.exists(&path)
+            || writes.is_writing(&storage.0, &path)
+    }
+
+    /// Id for a new slot, `None` when every id is taken
+    pub(crate) fn next_id(&self, allocation: IdAllocation) -> Option<u32> {
+        let max_key = self.saves.keys().max().copied().unwrap_or_default();
+        match allocation {
+            IdAllocation::Monotonic if max_key < u32::MAX => Some(max_key + 1),
+            _ => {
+                let mut ids: Vec<u32> = self.saves.keys().copied().collect();
+                ids.sort_unstable();
+                // First gap in the sorted ids, starting from 1
+                let mut next = 1;
+                for id in ids {
+                    if id > next {
+                        break;
+                    }
+                    next = id.checked_add(1)?;
+                }
+                Some(next)
+            }
+        }
+    }
+}
+
+impl GameSetting for SaveConfig {
+    const DEFAULT_CONF: &'static str = "save_setting.conf";
+}
+
+/// Path of [`SaveConfig`] with [`EncryptSavePlugin::with_config_in_save_dir`]
+fn config_in_save_dir(dirs: &SaveDirs) -> PathBuf {
+    dirs.resolve(Path::new("")).join(SaveConfig::DEFAULT_CONF)
+}
+
+fn tick_playtime(time: Res<Time>, mut playtime: ResMut<Playtime>) {
+    **playtime += time.delta();
+}
+
+fn collect_requests(
+    mut requests: ResMut<SaveRequests>,
+    mut save_message: MessageReader<SaveGame>,
+    mut quick_save_message: MessageReader<QuickSave>,
+    mut load_message: MessageReader<LoadGame>,
+    mut load_recent_message: MessageReader<LoadRecent>,
+    mut checkpoint_message: MessageReader<SaveCheckpoint>,
+    mut rollback_message: MessageReader<RollbackToCheckpoint>,
+    mut snapshot_message: MessageReader<Snapshot>,
+    mut restore_message: MessageReader<Restore>,
+    mut exit_message: MessageReader<AppExit>,
+    mut superseded: MessageWriter<SaveSuperseded>,
+    options: Res<SaveOptions>,
+    current_save: Res<CurrentSave>,
+    time: Res<Time<Real>>,
+) {
+    for msg in save_message.read() {
+        requests.saves.push_back(SaveRequest::Slot {
+            id: msg.id,
+            overwrite: msg.overwrite,
+            kind: msg.kind,
+            plain: msg.plain,
+        });
+    }
+    for _ in quick_save_message.read() {
+        requests.saves.push_back(SaveRequest::Quick);
+    }
+    for id in load_message.read() {
+        requests.loads.push_back(LoadRequest::Slot(**id));
+    }
+    for _ in load_recent_message.read() {
+        requests.loads.push_back(LoadRequest::Recent);
+    }
+    for _ in checkpoint_message.read() {
+        requests.saves.push_back(SaveRequest::Checkpoint);
+    }
+    for n in rollback_message.read() {
+        requests.loads.push_back(LoadRequest::Checkpoint(**n));
+    }
+    for _ in snapshot_message.read() {
+        requests.saves.push_back(SaveRequest::Snapshot);
+    }
+    for n in restore_message.read() {
+        requests.loads.push_back(LoadRequest::Snapshot(**n));
+    }
+
+    let Some(window) = options.coalesce_window else {
+        return;
+    };
+    let now = time.elapsed();
+    let saves = std::mem::take(&mut requests.saves);
+    for request in saves {
+        let Some(id) = request.coalesced_slot(**current_save) else {
+            requests.saves.push_back(request);
+            continue;
+        };
+        match requests.delayed.get_mut(&id) {
+            // Keep the end of the first window, so steady saves still run
+            Some((_, pending)) => {
+                *pending = request;
+                superseded.write(SaveSuperseded(id));
+            }
+            None => {
+                requests.delayed.insert(id, (now + window, request));
+            }
+        }
+    }
+
+    // Nothing is left waiting when the app exits
+    let exiting = exit_message.read().count() > 0;
+    let due: Vec<u32> = requests
+        .delayed
+        .iter()
+        .filter(|(_, (deadline, _))| exiting || *deadline <= now)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in due {
+        if let Some((_, request)) = requests.delayed.remove(&id) {
+            requests.saves.push_back(request);
+        }
+    }
+}
+
+fn has_saves(requests: Res<SaveRequests>) -> bool {
+    !requests.saves.is_empty()
+}
+
+fn has_loads(requests: Res<SaveRequests>) -> bool {
+    !requests.loads.is_empty()
+}
+
+fn drive_state(
+    requests: Res<SaveRequests>,
+    state: Res<State<SaveLoadState>>,
+    mut next_state: ResMut<NextState<SaveLoadState>>,
+) {
+    let wanted = if !requests.loads.is_empty() {
+        SaveLoadState::Loading
+    } else if !requests.saves.is_empty() {
+        SaveLoadState::Saving
+    } else {
+        SaveLoadState::Idle
+    };
+    if *state.get() != wanted {
+        next_state.set(wanted);
+    }
+}
+
+fn process_loads<T>(world: &mut World)
+where
+    T: Resource + EncryptSave,
+{
+    let loads = std::mem::take(&mut world.resource_mut::<SaveRequests>().loads);
+    let mut waiting = VecDeque::new();
+    let mut waiting_slots = BTreeSet::new();
+    for request in loads {
+        if let Some(id) = load_slot(world, &request).filter(|id| slot_being_written(world, *id)) {
+            if !world.resource::<SaveRequests>().waiting_loads.contains(&id) {
+                world.write_message(SlotBusy {
+                    slot: id,
+                    operation: SaveOperation::Load,
+                });
+                world
+                    .resource_mut::<LoadProgress>()
+                    .set(Some(id), LoadPhase::Waiting, 0);
+            }
+            waiting_slots.insert(id);
+            waiting.push_back(request);
+            continue;
+        }
+        match request {
+            LoadRequest::Slot(id) => {
+                if let Err(error) = load::<T>(world, id) {
+                    world.write_message(LoadFailed { slot: id, error });
+                }
+            }
+            LoadRequest::Recent => load_recent::<T>(world),
+            LoadRequest::Checkpoint(n) => rollback::<T>(world, n),
+            LoadRequest::Snapshot(n) => restore(world, n),
+        }
+    }
+    let mut requests = world.resource_mut::<SaveRequests>();
+    requests.waiting_loads = waiting_slots;
+    waiting.append(&mut requests.loads);
+    requests.loads = waiting;
+}
+
+/// Slot read first by a load request, `None` for requests which don't read a slot
+fn load_slot(world: &World, request: &LoadRequest) -> Option<u32> {
+    match request {
+        LoadRequest::Slot(id) => Some(*id),
+        LoadRequest::Recent => Some(world.resource::<SaveConfig>().last_saved),
+        LoadRequest::Checkpoint(_) | LoadRequest::Snapshot(_) => None,
+    }
+}
+
+/// Whether the file of slot `id` has a write in flight on the `IoTaskPool`
+fn slot_being_written(world: &World, id: u32) -> bool {
+    let storage = &world.resource::<SaveStorage>().0;
+    let sliced = world.resource::<SlicedSave>().0.as_ref().map(|pending| &pending.path);
+    world
+        .resource::<SaveConfig>()
+        .slot_path(world.resource::<SaveDirs>(), id)
+        .is_some_and(|path| world.resource::<PendingWrites>().is_writing(storage, &path) || sliced == Some(&path))
+}
+
+/// Try `last_saved` first, then every other slot from the most recently played
+fn load_recent<T>(world: &mut World)
+where
+    T: Resource + EncryptSave,
+{
+    let save_config = world.resource::<SaveConfig>();
+    let mut candidates: Vec<u32> = save_config.sorted_by_recency().into_iter().map(|(id, _)| id).collect();
+    if let Some(pos) = candidates.iter().position(|id| *id == save_config.last_saved) {
+        candidates.remove(pos);
+        candidates.insert(0, save_config.last_saved);
+    }
+
+    if candidates.is_empty() {
+        world.write_message(NoSaveFound);
+        return;
+    }
+
+    let mut errors = Vec::new();
+    for id in candidates {
+        match load::<T>(world, id) {
+            Ok(()) => return,
+            Err(e) => errors.push((id, e)),
+        }
+    }
+    world.write_message(LoadRecentFailed { errors });
+}
+
+fn load<T>(world: &mut World, save_id: u32) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let save_config = world.resource::<SaveConfig>();
+    let (Some(saved_path), Some(slot)) = (
+        save_config.slot_path(world.resource::<SaveDirs>(), save_id),
+        save_config.slot(save_id),
+    ) else {
+        return Err(SaveError::NotFound(save_id));
+    };
+    let playtime = slot.playtime;
+    let base = slot.base.clone();
+
+    let started = Instant::now();
+    match base {
+        Some(base) => read_delta_save::<T>(world, &saved_path, base, save_id)?,
+        None => read_save::<T>(world, &saved_path, Some(save_id))?,
+    }
+    let duration = started.elapsed();
+    world.resource_mut::<SaveStats>().record_load(duration);
+    if let Some(telemetry) = world.get_resource::<Telemetry>() {
+        telemetry.0.on_load(save_id, duration);
+    }
+    world.insert_resource(Playtime(playtime));
+    world.resource_mut::<CurrentSave>().0 = save_id;
+    if let Some(slot) = world.resource_mut::<SaveConfig>().slot_mut(save_id) {
+        slot.loaded_at = unix_now();
+    }
+    let size = world.resource::<SaveStorage>().size(&saved_path).unwrap_or(0);
+    world
+        .resource_mut::<LoadProgress>()
+        .set(Some(save_id), LoadPhase::Done, size);
+    world.write_message(GameSettingChanged);
+    Ok(())
+}
+
+fn rollback<T>(world: &mut World, n: usize)
+where
+    T: Resource + EncryptSave,
+{
+    let save_config = world.resource::<SaveConfig>();
+    let Some(checkpoint) = n.checked_sub(1).and_then(|i| save_config.checkpoints.get(i)) else {
+        world.write_message(CheckpointNotFound(n));
+        return;
+    };
+    let (saved_path, playtime) = (
+        save_config
+            .save_dir(world.resource::<SaveDirs>())
+            .join(&checkpoint.file),
+        checkpoint.playtime,
+    );
+
+    if let Err(error) = read_save::<T>(world, &saved_path, None) {
+        world.write_message(RollbackFailed { n, error });
+        return;
+    }
+    world.insert_resource(Playtime(playtime));
+
+    let dirs = world.resource::<SaveDirs>().clone();
+    let mut save_config = world.resource_mut::<SaveConfig>();
+    let discarded: Vec<PathBuf> = save_config
+        .checkpoints
+        .drain(..n - 1)
+        .map(|checkpoint| checkpoint.file)
+        .collect();
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    remove_files(world.resource::<SaveStorage>(), &save_dir, discarded);
+    world.write_message(GameSettingChanged);
+    world.write_message(CheckpointRestored(n));
+}
+
+fn restore(world: &mut World, n: usize) {
+    let Some(data) = world.resource::<Snapshots>().get(n).map(<[u8]>::to_vec) else {
+        world.write_message(SnapshotNotFound(n));
+        return;
+    };
+
+    if let Err(error) = apply_save(world, &data) {
+        #[cfg(feature = "log")]
+        warn!("Failed to restore snapshot {}: {}", n, error);
+        world.write_message(SnapshotRestoreFailed { n, error });
+        return;
+    }
+    world.resource_mut::<Snapshots>().pop(n);
+    world.write_message(SnapshotRestored(n));
+}
+
+fn read_save<T>(world: &mut World, saved_path: &Path, slot: Option<u32>) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    stage_save::<T>(world, saved_path, slot)
+        .and_then(|staged| apply_staged(world, staged))
+        .inspect_err(|_e| {
+            #[cfg(feature = "log")]
+            warn!("Failed to load save data {}: {}", saved_path.display(), _e);
+        })
+}
+
+/// Read, decrypt and decode the save at `saved_path`, streamed saves a chunk at a time
+fn stage_save<T>(world: &mut World, saved_path: &Path, slot: Option<u32>) -> Result<Vec<StagedSection>, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let storage = world.resource::<SaveStorage>().clone();
+    let cipher = world.resource::<SaveCipher>().clone();
+    let password = world.resource::<SavePassword>().clone();
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let limits = world.resource::<SaveOptions>().load_limits;
+    let plain = slot.is_some_and(|id| slot_is_plain(world, id));
+    let (header, mut reader, mut data) = {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("read", slot = ?slot).entered();
+        // Checked before reading, the file system gets the size from the metadata
+        LoadLimits::check(storage.size(saved_path)?, limits.max_file_size)?;
+        let mut reader = storage.reader(saved_path)?;
+        let (header, data) = SaveHeader::read_from(&mut reader)?;
+        (header, reader, data)
+    };
+    check_game_version(
+        world,
+        header.as_ref().and_then(|header| header.game_version.clone()),
+        slot,
+    );
+
+    let registry = world.resource::<SaveRegistry>();
+    if let Some(header) = header.filter(|header| header.chunk_size.is_some()) {
+        // Streamed saves are never signed
+        #[cfg(feature = "signing")]
+        if world.contains_resource::<crate::signing::SigningKey>() {
+            return Err(SaveError::InvalidSignature);
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("deserialize", slot = ?slot, streamed = true).entered();
+        let mut chunks = open_stream(
+            cipher.0.as_ref(),
+            &header,
+            reader,
+            &key,
+            password.get(),
+            plain,
+            limits.max_allocation,
+        )?;
+        let staged = registry.stage_from(world, &mut chunks);
+        // The error that cut the data short, rather than the decoding error it caused
+        chunks.check(Ok(()))?;
+        return staged;
+    }
+
+    reader.read_to_end(&mut data)?;
+    #[cfg(feature = "signing")]
+    crate::signing::verify_with(world.get_resource(), &data)?;
+    let data = {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("decrypt", slot = ?slot, bytes = data.len()).entered();
+        open(cipher.0.as_ref(), &data, &key, password.get(), plain)?
+    };
+    LoadLimits::check(data.len() as u64, limits.max_allocation)?;
+    #[cfg(feature = "trace")]
+    let _span = tracing::info_span!("deserialize", slot = ?slot, bytes = data.len()).entered();
+    registry.stage(world, &data)
+}
+
+/// Apply the delta save at `saved_path` on its `base` and load the result
+fn read_delta_save<T>(world: &mut World, saved_path: &Path, base: PathBuf, slot: u32) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let max_allocation = world.resource::<SaveOptions>().load_limits.max_allocation;
+    let plain = slot_is_plain(world, slot);
+    read_decrypted::<T>(world, saved_path, plain)
+        .and_then(|(game_version, delta)| {
+            check_game_version(world, game_version, Some(slot));
+            cache_base::<T>(world, &base, plain)?;
+            let base_data = world.resource::<DeltaBase>().get(&base).unwrap_or_default();
+            let data = Zeroizing::new(patch(base_data, &delta, max_allocation)?);
+            world.resource::<SaveRegistry>().stage(world, &data)
+        })
+        .and_then(|staged| apply_staged(world, staged))
+        .inspect_err(|_e| {
+            #[cfg(feature = "log")]
+            warn!("Failed to load delta save {}: {}", saved_path.display(), _e);
+        })
+}
+
+/// Keep the decrypted `base` in [`DeltaBase`], reading it unless it is already there
+fn cache_base<T>(world: &mut World, base: &Path, plain: bool) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    if world.resource::<DeltaBase>().get(base).is_none() {
+        // The base may still be in the background writes
+        world.resource::<PendingWrites>().flush();
+        let (_, data) = read_decrypted::<T>(
+            world,
+            &world
+                .resource::<SaveConfig>()
+                .save_dir(world.resource::<SaveDirs>())
+                .join(base),
+            plain,
+        )?;
+        world.resource_mut::<DeltaBase>().0 = Some((base.to_path_buf(), data));
+    }
+    Ok(())
+}
+
+/// Read and decrypt the whole file at `saved_path`, with the game version in its header.
+/// Plain files are only read if `plain`.
+fn read_decrypted<T>(
+    world: &World,
+    saved_path: &Path,
+    plain: bool,
+) -> Result<(Option<String>, Zeroizing<Vec<u8>>), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let storage = world.resource::<SaveStorage>();
+    let limits = world.resource::<SaveOptions>().load_limits;
+    LoadLimits::check(storage.size(saved_path)?, limits.max_file_size)?;
+    let data = storage.read(saved_path)?;
+    #[cfg(feature = "signing")]
+    crate::signing::verify_with(world.get_resource(), &data)?;
+    let game_version = SaveHeader::decode(&data).and_then(|(header, _)| header.game_version);
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let decrypted = open(
+        world.resource::<SaveCipher>().0.as_ref(),
+        &data,
+        &key,
+        world.resource::<SavePassword>().get(),
+        plain,
+    )?;
+    LoadLimits::check(decrypted.len() as u64, limits.max_allocation)?;
+    Ok((game_version, decrypted))
+}
+
+fn check_game_version(world: &mut World, saved: Option<String>, slot: Option<u32>) {
+    let Some(current) = world.resource::<SaveOptions>().game_version.clone() else {
+        return;
+    };
+    if saved.as_ref() != Some(&current) {
+        world.write_message(SaveVersionMismatch { slot, saved, current });
+    }
+}
+
+/// Decode every section first, so the world is only touched when the whole save is readable
+fn apply_save(world: &mut World, data: &[u8]) -> Result<(), SaveError> {
+    let staged = world.resource::<SaveRegistry>().stage(world, data)?;
+    apply_staged(world, staged)
+}
+
+/// Apply every section, or none: when one fails the sections applied before it are rolled back
+fn apply_staged(world: &mut World, staged: Vec<StagedSection>) -> Result<(), SaveError> {
+    let mut applied = Vec::with_capacity(staged.len());
+    for (apply, value) in staged {
+        match apply(world, value) {
+            Ok(undo) => applied.push(undo),
+            Err(e) => {
+                for undo in applied.into_iter().rev() {
+                    undo(world);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn process_saves<T>(world: &mut World)
+where
+    T: Resource + EncryptSave + Clone,
+{
+    let saves = std::mem::take(&mut world.resource_mut::<SaveRequests>().saves);
+    let read_only = *world.resource::<SaveManagerMode>() == SaveManagerMode::ReadOnly;
+    // Saves would be encrypted with a key the next runs won't use
+    #[cfg(feature = "keyring")]
+    let read_only = read_only || world.contains_resource::<crate::keyring::KeyringUnavailable>();
+    let mut waiting = VecDeque::new();
+    let mut waiting_slots = BTreeSet::new();
+    for request in saves {
+        let requests = world.resource::<SaveRequests>();
+        let slot = request.coalesced_slot(**world.resource::<CurrentSave>());
+        if let Some(id) = slot.filter(|id| requests.waiting_loads.contains(id)) {
+            if !requests.waiting_saves.contains(&id) {
+                world.write_message(SlotBusy {
+                    slot: id,
+                    operation: SaveOperation::Save,
+                });
+            }
+            waiting_slots.insert(id);
+            waiting.push_back(request);
+            continue;
+        }
+        if read_only {
+            let slot = match request {
+                SaveRequest::Slot { id, .. } => Some(id),
+                SaveRequest::Quick => Some(**world.resource::<CurrentSave>()),
+                SaveRequest::Checkpoint => None,
+                // Snapshots stay in memory
+                SaveRequest::Snapshot => {
+                    snapshot(world);
+                    continue;
+                }
+            };
+            world.write_message(SaveRefused(slot));
+            continue;
+        }
+        let (save_id, kind, plain) = match request {
+            SaveRequest::Slot {
+                id,
+                overwrite,
+                kind,
+                plain,
+            } => {
+                if !overwrite && world.resource::<SaveConfig>().saves.contains_key(&id) {
+                    world.write_message(SlotOccupied(id));
+                    continue;
+                }
+                (id, kind, plain)
+            }
+            SaveRequest::Quick => (**world.resource::<CurrentSave>(), SlotKind::Quick, false),
+            SaveRequest::Checkpoint => {
+                checkpoint::<T>(world);
+                continue;
+            }
+            SaveRequest::Snapshot => {
+                snapshot(world);
+                continue;
+            }
+        };
+        match save::<T>(world, save_id, kind, plain) {
+            // Only logged, the request didn't reach a file
+            Err(SaveFailed {
+                error: SaveError::NoFreeSlot | SaveError::NotFound(_),
+                ..
+            })
+            | Ok(_) => {}
+            Err(failure) => {
+                world.write_message(failure);
+            }
+        }
+    }
+    let mut requests = world.resource_mut::<SaveRequests>();
+    requests.waiting_saves = waiting_slots;
+    waiting.append(&mut requests.saves);
+    requests.saves = waiting;
+}
+
+/// Save to slot `save_id`, returning the id of the slot. `plain` slots stay unencrypted from then on.
+fn save<T>(world: &mut World, save_id: u32, kind: SlotKind, plain: bool) -> Result<u32, SaveFailed>
+where
+    T: Resource + EncryptSave + Clone,
+{
+    let save_config = world.resource::<SaveConfig>();
+    let options = world.resource::<SaveOptions>();
+    let (save_id, file) = if save_id == 0 {
+        let Some(id) = save_config.next_id(options.id_allocation) else {
+            #[cfg(feature = "log")]
+            error!("Failed to save data: {}", SaveError::NoFreeSlot);
+            return Err(SaveFailed {
+                slot: None,
+                path: save_config.save_dir(world.resource::<SaveDirs>()).into_owned(),
+                error: SaveError::NoFreeSlot,
+            });
+        };
+        (
+            id,
+            new_save_file(
+                &options.naming,
+                world.resource::<NamingRng>(),
+                world.resource::<CurrentProfile>(),
+                id,
+                |file| {
+                    save_config.file_taken(
+                        world.resource::<SaveDirs>(),
+                        world.resource::<SaveStorage>(),
+                        world.resource::<PendingWrites>(),
+                        file,
+                    )
+                },
+            ),
+        )
+    } else if let Some(slot) = save_config.saves.get(&save_id) {
+        (save_id, slot.file.clone())
+    } else {
+        return Err(SaveFailed {
+            slot: Some(save_id),
+            path: save_config.save_dir(world.resource::<SaveDirs>()).into_owned(),
+            error: SaveError::NotFound(save_id),
+        });
+    };
+    let saved_path = save_config.save_dir(world.resource::<SaveDirs>()).join(&file);
+
+    let old_base = save_config.saves.get(&save_id).and_then(|slot| slot.base.clone());
+    let delta_autosaves = options
+        .delta_autosaves
+        .filter(|_| kind == SlotKind::Auto && stream_chunk_size(world).is_none());
+
+    let cipher = if plain { SaveCipher(Arc::new(PlainCipher)) } else { slot_cipher(world, save_id) };
+    let sections = world.resource::<SaveRegistry>().sections.len();
+    world.resource_mut::<SaveProgress>().capturing(save_id, 0, sections, 0);
+
+    let started = Instant::now();
+    let written = match delta_autosaves {
+        Some(full_every) => write_delta_save::<T>(world, save_id, &file, full_every, &cipher)
+            .map(|(size, base, deltas)| (Some(size), base, deltas)),
+        None => write_save::<T>(world, saved_path.clone(), Some(save_id), &cipher).map(|size| (size, None, 0)),
+    };
+    let (size, base, deltas) = match written {
+        Ok(written) => written,
+        Err(e) => {
+            #[cfg(feature = "log")]
+            error!("Failed to save data {}: {}", saved_path.display(), e);
+            return Err(SaveFailed {
+                slot: Some(save_id),
+                path: saved_path,
+                error: e,
+            });
+        }
+    };
+    let duration = started.elapsed();
+    world.resource_mut::<SaveStats>().record_save(save_id, size, duration);
+    if let Some(telemetry) = world.get_resource::<Telemetry>() {
+        telemetry.0.on_save(save_id, duration, size);
+    }
+    if let Some(old_base) = old_base.filter(|old_base| Some(old_base) != base.as_ref()) {
+        let mut cache = world.resource_mut::<DeltaBase>();
+        if cache.get(&old_base).is_some() {
+            cache.0 = None;
+        }
+        let save_dir = world
+            .resource::<SaveConfig>()
+            .save_dir(world.resource::<SaveDirs>())
+            .into_owned();
+        remove_files(world.resource::<SaveStorage>(), &save_dir, vec![old_base]);
+    }
+
+    let playtime = **world.resource::<Playtime>();
+    let game_version = world.resource::<SaveOptions>().game_version.clone();
+    let mut fields = world.resource::<SlotFields>().0.clone();
+    if let Some(extract) = world.get_resource::<MetaExtractor<T>>() {
+        fields.extend((extract.0)(world.resource::<T>()).0);
+    }
+    let custom = match world.get_resource::<CustomMeta<T>>() {
+        Some(capture) => (capture.0)(world.resource::<T>()).map(Some).unwrap_or_else(|_e| {
+            #[cfg(feature = "log")]
+            warn!("Failed to serialize the metadata of slot {}: {}", save_id, _e);
+            None
+        }),
+        None => None,
+    };
+    let options = world.resource::<SaveOptions>();
+    let verify = options.verify_after_write;
+    // Streamed saves are verified before `write_save` returns
+    let streamed = stream_chunk_size(world).is_some();
+    let mut save_config = world.resource_mut::<SaveConfig>();
+    let now = unix_now();
+    let slot = save_config.saves.entry(save_id).or_insert_with(|| SaveSlot {
+        file,
+        created_at: now,
+        ..Default::default()
+    });
+    slot.playtime = playtime;
+    slot.saved_at = now;
+    slot.revision += 1;
+    slot.kind = kind;
+    slot.game_version = game_version;
+    slot.fields = fields;
+    slot.base = base;
+    slot.deltas = deltas;
+    slot.plain |= plain;
+    slot.custom = custom;
+    if verify {
+        world.resource_mut::<UnverifiedSave>().0 = Some(save_id);
+    } else {
+        save_config.last_saved = save_id;
+    }
+    world.resource_mut::<CurrentSave>().0 = save_id;
+    let sliced = world.resource::<SlicedSave>().0.is_some();
+    let mut progress = world.resource_mut::<SaveProgress>();
+    match size {
+        // Streamed saves are written before `write_save` returns
+        Some(size) if streamed => progress.done(save_id, size),
+        // The main resource was copied, the others are captured in the next frames
+        _ if sliced => progress.capturing(save_id, 1, sections, 0),
+        size => progress.capturing(save_id, sections, sections, size.unwrap_or(0)),
+    }
+    world.write_message(GameSettingChanged);
+    world.write_message(GameSaved(save_id));
+    if verify && streamed {
+        world.write_message(SaveVerified {
+            slot: save_id,
+            path: saved_path,
+        });
+    }
+    Ok(save_id)
+}
+
+fn checkpoint<T>(world: &mut World)
+where
+    T: Resource + EncryptSave + Clone,
+{
+    let size = world.resource::<SaveOptions>().checkpoints;
+    if size == 0 {
+        #[cfg(feature = "log")]
+        warn!("Checkpoints are disabled, see EncryptSavePlugin::with_checkpoints");
+        return;
+    }
+
+    let dir = world.resource::<CurrentProfile>().dir();
+    let file = unique_file(
+        || dir.join(format!("checkpoint_{}.dat", world.resource::<NamingRng>().string())),
+        |file| {
+            world.resource::<SaveConfig>().file_taken(
+                world.resource::<SaveDirs>(),
+                world.resource::<SaveStorage>(),
+                world.resource::<PendingWrites>(),
+                file,
+            )
+        },
+    );
+    let saved_path = world
+        .resource::<SaveConfig>()
+        .save_dir(world.resource::<SaveDirs>())
+        .join(&file);
+    let cipher = world.resource::<SaveCipher>().clone();
+    if let Err(e) = write_save::<T>(world, saved_path.clone(), None, &cipher) {
+        #[cfg(feature = "log")]
+        error!("Failed to save checkpoint {}: {}", saved_path.display(), e);
+        world.write_message(SaveFailed {
+            slot: None,
+            path: saved_path,
+            error: e,
+        });
+        return;
+    }
+
+    let playtime = **world.resource::<Playtime>();
+    let now = unix_now();
+    let dirs = world.resource::<SaveDirs>().clone();
+    let mut save_config = world.resource_mut::<SaveConfig>();
+    save_config.checkpoints.push_front(SaveSlot {
+        file,
+        playtime,
+        created_at: now,
+        saved_at: now,
+        kind: SlotKind::Checkpoint,
+        ..Default::default()
+    });
+    let expired: Vec<PathBuf> = if save_config.checkpoints.len() > size {
+        save_config
+            .checkpoints
+            .split_off(size)
+            .into_iter()
+            .map(|slot| slot.file)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    remove_files(world.resource::<SaveStorage>(), &save_dir, expired);
+    world.write_message(GameSettingChanged);
+    world.write_message(CheckpointSaved);
+}
+
+fn snapshot(world: &mut World) {
+    let options = world.resource::<SaveOptions>();
+    let (depth, budget) = (options.snapshot_depth, options.snapshot_budget);
+    if depth == 0 {
+        #[cfg(feature = "log")]
+        warn!("Snapshots are disabled, see EncryptSavePlugin::with_snapshots");
+        return;
+    }
+
+    match world.resource::<SaveRegistry>().encode(world) {
+        Ok(data) => world.resource_mut::<Snapshots>().push(data, depth, budget),
+        Err(_e) => {
+            #[cfg(feature = "log")]
+            error!("Failed to take snapshot: {}", _e);
+        }
+    }
+}
+
+/// Chunk size of streamed saves, `None` when saves are signed: the signature covers the whole file, which is
+/// never held in memory while streaming. Also `None` with [`LegacyCipher`], which derives a key for every chunk.
+fn stream_chunk_size(world: &World) -> Option<u32> {
+    #[cfg(feature = "signing")]
+    if world.contains_resource::<crate::signing::SigningKey>() {
+        return None;
+    }
+    if world.resource::<SaveCipher>().id() == LegacyCipher.id() {
+        return None;
+    }
+    world.resource::<SaveOptions>().stream_chunk_size
+}
+
+/// Serialize every section and hand the data over to be written, returning the size of the file
+/// unless it is only serialized in the background
+fn write_save<T>(
+    world: &mut World,
+    saved_path: PathBuf,
+    slot: Option<u32>,
+    cipher: &SaveCipher,
+) -> Result<Option<u64>, SaveError>
+where
+    T: Resource + EncryptSave + Clone,
+{
+    if let Some(chunk_size) = stream_chunk_size(world) {
+        return stream_save::<T>(world, &saved_path, chunk_size, cipher).map(Some);
+    }
+    if world.resource::<SaveOptions>().background_saves {
+        return spawn_background_save::<T>(world, saved_path, slot, cipher).map(|_| None);
+    }
+    let data = serialize(world, slot)?;
+    write_data::<T>(world, saved_path, slot, &data, cipher).map(Some)
+}
+
+/// Copy `T` and hand it over to be serialized, encrypted and written on the `IoTaskPool`,
+/// once the other sections are captured
+fn spawn_background_save<T>(
+    world: &mut World,
+    saved_path: PathBuf,
+    slot: Option<u32>,
+    cipher: &SaveCipher,
+) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave + Clone,
+{
+    // Sections still captured for an earlier save would be written after this one
+    finish_sliced_save(world);
+    let registry = world.resource::<SaveRegistry>().clone();
+    let main = world
+        .get_resource::<T>()
+        .ok_or(SaveError::MissingResource(std::any::type_name::<T>()))?
+        .clone();
+    let storage = world.resource::<SaveStorage>().0.clone();
+    let cipher = cipher.0.clone();
+    let password = world.resource::<SavePassword>().get().cloned();
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let options = world.resource::<SaveOptions>();
+    let (game_version, quota, slot_budget) = (options.game_version.clone(), options.quota, options.slot_budget);
+    let durability = Durability {
+        fsync: options.fsync,
+        verify: options.verify_after_write,
+    };
+    let save_dir = world
+        .resource::<SaveConfig>()
+        .save_dir(world.resource::<SaveDirs>())
+        .to_path_buf();
+    let (backend, path) = (storage.clone(), saved_path.clone());
+    #[cfg(feature = "signing")]
+    let signing = world.get_resource::<crate::signing::SigningKey>().cloned();
+
+    let slice_budget = options.slice_budget;
+
+    let encode = move |others| {
+        let data = {
+            #[cfg(feature = "trace")]
+            let _span = tracing::info_span!("serialize", slot = ?slot, background = true).entered();
+            Zeroizing::new(registry.encode_with_main(&main, others)?)
+        };
+        let enc_saved = {
+            #[cfg(feature = "trace")]
+            let _span = tracing::info_span!("encrypt", slot = ?slot, bytes = data.len()).entered();
+            seal(cipher.as_ref(), &data, &key, password.as_ref(), game_version.as_deref())?
+        };
+        #[cfg(feature = "signing")]
+        let enc_saved = crate::signing::sign_with(signing.as_ref(), enc_saved);
+        check_space(
+            backend.as_ref(),
+            &save_dir,
+            &path,
+            enc_saved.len() as u64,
+            quota,
+            slot_budget,
+        )?;
+        Ok(enc_saved)
+    };
+    if slice_budget.is_none() {
+        let others = world.resource::<SaveRegistry>().capture_others(world)?;
+        world.resource::<PendingWrites>().spawn_encoded_write(
+            storage,
+            saved_path,
+            Box::new(move || encode(others)),
+            slot,
+            durability,
+        );
+        return Ok(());
+    }
+    let path = saved_path.clone();
+    let writes = world.resource::<PendingWrites>().clone();
+    world.resource_mut::<SlicedSave>().0 = Some(PendingSlices {
+        path,
+        slot,
+        others: Vec::new(),
+        write: Box::new(move |others| {
+            writes.spawn_encoded_write(storage, saved_path, Box::new(move || encode(others)), slot, durability);
+        }),
+    });
+    Ok(())
+}
+
+fn has_slices(sliced: Res<SlicedSave>) -> bool {
+    sliced.0.is_some()
+}
+
+/// Capture sections of the pending sliced save until the budget of the frame is spent
+fn capture_slices(world: &mut World) {
+    let budget = world.resource::<SaveOptions>().slice_budget.unwrap_or_default();
+    let started = Instant::now();
+    capture_pending(world, || started.elapsed() >= budget);
+}
+
+/// Capture every section left of the pending sliced save and hand it over to be written
+fn finish_sliced_save(world: &mut World) {
+    capture_pending(world, || false);
+}
+
+/// Capture sections of the pending sliced save until `done`, checked before each section but the first
+fn capture_pending(world: &mut World, mut done: impl FnMut() -> bool) {
+    let Some(mut pending) = world.resource_mut::<SlicedSave>().0.take() else {
+        return;
+    };
+    let mut first = true;
+    loop {
+        let registry = world.resource::<SaveRegistry>();
+        // The main resource was copied when the save was requested
+        let Some(section) = registry.sections.get(pending.others.len() + 1) else {
+            if let Some(slot) = pending.slot {
+                let sections = registry.sections.len();
+                let bytes = pending.others.iter().map(|(_, data)| data.len() as u64).sum();
+                world
+                    .resource_mut::<SaveProgress>()
+                    .capturing(slot, sections, sections, bytes);
+            }
+            (pending.write)(pending.others);
+            return;
+        };
+        if !first && done() {
+            world.resource_mut::<SlicedSave>().0 = Some(pending);
+            return;
+        }
+        first = false;
+        let sections = registry.sections.len();
+        match (section.capture)(world, registry.encoding) {
+            Ok(data) => {
+                pending.others.push((section.name.clone(), data));
+                if let Some(slot) = pending.slot {
+                    let bytes = pending.others.iter().map(|(_, data)| data.len() as u64).sum();
+                    world
+                        .resource_mut::<SaveProgress>()
+                        .capturing(slot, pending.others.len() + 1, sections, bytes);
+                }
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                error!("Failed to save data {}: {}", pending.path.display(), error);
+                world.write_message(SaveFailed {
+                    slot: pending.slot,
+                    path: pending.path,
+                    error,
+                });
+                return;
+            }
+        }
+    }
+}
+
+fn serialize(world: &World, _slot: Option<u32>) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    #[cfg(feature = "trace")]
+    let span = tracing::info_span!("serialize", slot = ?_slot, bytes = tracing::field::Empty).entered();
+    let data = Zeroizing::new(world.resource::<SaveRegistry>().encode(world)?);
+    #[cfg(feature = "trace")]
+    span.record("bytes", data.len());
+    Ok(data)
+}
+
+/// Write slot `id` as a delta against its base, or as a new base with an empty delta once `full_every` deltas
+/// were written. Returns the size of the files, the base and the number of deltas written against it.
+fn write_delta_save<T>(
+    world: &mut World,
+    id: u32,
+    file: &Path,
+    full_every: u32,
+    cipher: &SaveCipher,
+) -> Result<(u64, Option<PathBuf>, u32), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let data = serialize(world, Some(id))?;
+    let save_dir = world
+        .resource::<SaveConfig>()
+        .save_dir(world.resource::<SaveDirs>())
+        .into_owned();
+    let current = world
+        .resource::<SaveConfig>()
+        .slot(id)
+        .and_then(|slot| Some((slot.base.clone()?, slot.deltas)))
+        .filter(|(_, deltas)| *deltas < full_every);
+
+    if let Some((base, deltas)) = current {
+        match cache_base::<T>(world, &base, slot_is_plain(world, id)) {
+            Ok(()) => {
+                let base_data = world.resource::<DeltaBase>().get(&base).unwrap_or_default();
+                let delta = Zeroizing::new(diff(base_data, &data)?);
+                let size = write_data::<T>(world, save_dir.join(file), Some(id), &delta, cipher)?;
+                return Ok((size, Some(base), deltas + 1));
+            }
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to read the base of slot {}, writing a full save: {}", id, _e);
+            }
+        }
+    }
+
+    let base = unique_file(
+        || file.with_file_name(format!("base_{}.dat", world.resource::<NamingRng>().string())),
+        |base| {
+            world.resource::<SaveConfig>().file_taken(
+                world.resource::<SaveDirs>(),
+                world.resource::<SaveStorage>(),
+                world.resource::<PendingWrites>(),
+                base,
+            )
+        },
+    );
+    let base_size = write_data::<T>(world, save_dir.join(&base), None, &data, cipher)?;
+    let delta = Zeroizing::new(diff(&data, &data)?);
+    let size = write_data::<T>(world, save_dir.join(file), Some(id), &delta, cipher)?;
+    world.resource_mut::<DeltaBase>().0 = Some((base.clone(), data));
+    Ok((base_size + size, Some(base), 0))
+}
+
+/// Encrypt `data` and hand it over to be written, returning the size of the file
+fn write_data<T>(
+    world: &World,
+    saved_path: PathBuf,
+    slot: Option<u32>,
+    data: &[u8],
+    cipher: &SaveCipher,
+) -> Result<u64, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let storage = world.resource::<SaveStorage>().0.clone();
+    let password = world.resource::<SavePassword>();
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let options = world.resource::<SaveOptions>();
+    let enc_saved = {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("encrypt", slot = ?slot, bytes = data.len()).entered();
+        seal(
+            cipher.0.as_ref(),
+            data,
+            &key,
+            password.get(),
+            options.game_version.as_deref(),
+        )?
+    };
+    #[cfg(feature = "signing")]
+    let enc_saved = crate::signing::sign_with(world.get_resource(), enc_saved);
+    let size = enc_saved.len() as u64;
+    let save_dir = world.resource::<SaveConfig>().save_dir(world.resource::<SaveDirs>());
+    check_space(
+        storage.as_ref(),
+        &save_dir,
+        &saved_path,
+        size,
+        options.quota,
+        options.slot_budget,
+    )?;
+    let durability = Durability {
+        fsync: options.fsync,
+        verify: options.verify_after_write,
+    };
+    world
+        .resource::<PendingWrites>()
+        .spawn_durable_write(storage, saved_path, enc_saved, slot, durability);
+    Ok(size)
+}
+
+/// Write the save through a chain of writers, serializing into chunks which are encrypted and written one by one
+fn stream_save<T>(world: &World, saved_path: &Path, chunk_size: u32, cipher: &SaveCipher) -> Result<u64, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let storage = world.resource::<SaveStorage>();
+    let password = world.resource::<SavePassword>();
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let options = world.resource::<SaveOptions>();
+    let registry = world.resource::<SaveRegistry>();
+    #[cfg(feature = "trace")]
+    let _span = tracing::info_span!("stream", path = %saved_path.display(), bytes = tracing::field::Empty).entered();
+
+    // A background write of the same file would interleave with this one
+    world.resource::<PendingWrites>().flush();
+    let mut size = 0;
+    retry(*world.resource::<RetryPolicy>(), saved_path, || {
+        let mut failure = None;
+        let result = storage.write_with(saved_path, &mut |out| {
+            seal_stream(
+                cipher.0.as_ref(),
+                &key,
+                password.get(),
+                options.game_version.as_deref(),
+                chunk_size,
+                out,
+                |writer| registry.encode_into(world, writer),
+            )
+            .map(|written| size = written)
+            .map_err(|e| {
+                let io_error = io::Error::other(e.to_string());
+                failure = Some(e);
+                io_error
+            })
+        });
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(result?),
+        }
+    })?;
+    #[cfg(feature = "trace")]
+    _span.record("bytes", size);
+
+    if options.fsync {
+        storage.sync(saved_path)?;
+    }
+    if options.verify_after_write {
+        let mut reader = storage.reader(saved_path)?;
+        let header = SaveHeader::read_from(&mut reader)?
+            .0
+            .ok_or_else(|| SaveError::Corrupted("Written file has no header".to_string()))?;
+        let plain = cipher.0.id() == PlainCipher.id();
+        let mut chunks = open_stream(
+            cipher.0.as_ref(),
+            &header,
+            reader,
+            &key,
+            password.get(),
+            plain,
+            u64::MAX,
+        )?;
+        let result = io::copy(&mut chunks, &mut io::sink());
+        chunks.check(result)?;
+    }
+    Ok(size)
+}
+
+/// Fail if writing `size` bytes to `path` would overflow the disk or `quota`, or if they are over `slot_budget`
+fn check_space(
+    storage: &dyn SaveBackend,
+    save_dir: &Path,
+    path: &Path,
+    size: u64,
+    quota: Option<u64>,
+    slot_budget: Option<u64>,
+) -> Result<(), SaveError> {
+    if let Some(limit) = slot_budget.filter(|limit| size > *limit) {
+        return Err(SaveError::SlotBudgetExceeded { size, limit });
+    }
+
+    // Overwritten files free their own space
+    let needed = size.saturating_sub(storage.size(path).unwrap_or_default());
+    let dir = path.parent().unwrap_or(save_dir);
+    if let Some(available) = storage.available_space(dir) {
+        if needed > available {
+            return Err(SaveError::DiskFull { needed, available });
+        }
+    }
+
+    if let Some(quota) = quota {
+        let mut files = storage.list(save_dir).unwrap_or_default();
+        if dir != save_dir {
+            files.extend(storage.list(dir).unwrap_or_default());
+        }
+        let used: u64 = files.iter().filter_map(|file| storage.size(file).ok()).sum();
+        let available = quota.saturating_sub(used);
+        if needed > available {
+            return Err(SaveError::QuotaExceeded { needed, available });
+        }
+    }
+    Ok(())
+}
+
+/// Sizes of background saves are only known once written
+fn record_written_size(mut written: MessageReader<SaveWritten>, mut stats: ResMut<SaveStats>) {
+    for msg in written.read() {
+        stats.slot_sizes.insert(msg.slot, msg.size);
+    }
+}
+
+/// Make the last save `last_saved` once its file is known to be intact
+fn on_save_verified(
+    mut verified: MessageReader<SaveVerified>,
+    mut unverified: ResMut<UnverifiedSave>,
+    mut save_config: ResMut<SaveConfig>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for msg in verified.read() {
+        if unverified.0 == Some(msg.slot) && save_config.saves.contains_key(&msg.slot) {
+            unverified.0 = None;
+            save_config.last_saved = msg.slot;
+            setting_changed.write(GameSettingChanged);
+        }
+    }
+}
+
+/// Decrypt the saves `local` and `remote`, merge their `T` and encrypt the result with the other resources of `local`
+pub(crate) fn merge_saves<T>(
+    world: &World,
+    local: &[u8],
+    remote: &[u8],
+    merge: fn(T, T) -> T,
+    sealing: &SaveCipher,
+) -> Result<Vec<u8>, SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let cipher = world.resource::<SaveCipher>().0.as_ref();
+    let password = world.resource::<SavePassword>().get();
+    let key = save_key::<T>(world.resource::<SaveKey>());
+    let options = world.resource::<SaveOptions>();
+    let registry = world.resource::<SaveRegistry>();
+    // Both sides are plain if the slot is
+    let plain = sealing.0.id() == PlainCipher.id();
+    let decode = |data: &[u8]| -> Result<(T, Zeroizing<Vec<u8>>), SaveError> {
+        #[cfg(feature = "signing")]
+        crate::signing::verify_with(world.get_resource(), data)?;
+        let decrypted = open(cipher, data, &key, password, plain)?;
+        LoadLimits::check(decrypted.len() as u64, options.load_limits.max_allocation)?;
+        Ok((registry.decode_main::<T>(world, &decrypted)?, decrypted))
+    };
+    let (local_main, local_data) = decode(local)?;
+    let (remote_main, _) = decode(remote)?;
+    let data = Zeroizing::new(registry.replace_main(&local_data, &merge(local_main, remote_main))?);
+    let sealed = seal(
+        sealing.0.as_ref(),
+        &data,
+        &key,
+        password,
+        options.game_version.as_deref(),
+    )?;
+    #[cfg(feature = "signing")]
+    let sealed = crate::signing::sign_with(world.get_resource(), sealed);
+    Ok(sealed)
+}
+
+/// Cipher of the next save of slot `id`, [`PlainCipher`] if the slot is plain
+pub(crate) fn slot_cipher(world: &World, id: u32) -> SaveCipher {
+    if slot_is_plain(world, id) {
+        SaveCipher(Arc::new(PlainCipher))
+    } else {
+        world.resource::<SaveCipher>().clone()
+    }
+}
+
+/// Whether slot `id` is saved without encryption, the only slots whose files may be plain
+fn slot_is_plain(world: &World, id: u32) -> bool {
+    world.resource::<SaveConfig>().slot(id).is_some_and(|slot| slot.plain)
+}
+
+/// [`SaveKey`] if it is set, otherwise the key of `T`
+pub(crate) fn save_key<T>(key: &SaveKey) -> SecretKey
+where
+    T: EncryptSave,
+{
+    key.get().cloned().unwrap_or_else(|| SecretKey::from(T::ENCR_KEY))
+}
+
+fn remove_files(storage: &SaveStorage, save_dir: &Path, files: Vec<PathBuf>) {
+    for file in files {
+        let path = save_dir.join(file);
+        if let Err(_e) = storage.remove(&path) {
+            #[cfg(feature = "log")]
+            warn!("Failed to delete {}: {}", path.display(), _e);
+        }
+    }
+}
+
+fn on_delete(
+    mut current_save: ResMut<CurrentSave>,
+    mut delete_event: MessageReader<DeleteSave>,
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for saved_id in delete_event.read() {
+        if let Some(saved_path) = save_config.slot_path(&dirs, **saved_id) {
+            if let Err(_e) = storage.remove(&saved_path) {
+                #[cfg(feature = "log")]
+                error!("Failed to delete save data {}: {}", saved_path.display(), _e);
+            } else if let Some(slot) = save_config.saves.remove(saved_id) {
+                if slot.synced_revision != 0 {
+                    save_config.deleted.insert(**saved_id);
+                }
+                for file in slot.thumbnail.into_iter().chain(slot.base) {
+                    let _ = storage.remove(&save_config.save_dir(&dirs).join(file));
+                }
+                current_save.0 = 0;
+                if save_config.last_saved == **saved_id {
+                    save_config.last_saved = 0;
+                }
+                setting_changed.write(GameSettingChanged);
+            }
+        }
+    }
+}
+
+pub(crate) fn prune_saves(
+    options: Res<SaveOptions>,
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    profile: Res<CurrentProfile>,
+    mut pruned: MessageWriter<SavesPruned>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    let missing: Vec<u32> = save_config
+        .saves
+        .iter()
+        .filter(|(_, slot)| !storage.exists(&save_config.save_dir(&dirs).join(&slot.file)))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in &missing {
+        save_config.saves.remove(id);
+        if save_config.last_saved == *id {
+            save_config.last_saved = 0;
+        }
+    }
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    let checkpoints = save_config.checkpoints.len();
+    save_config
+        .checkpoints
+        .retain(|checkpoint| storage.exists(&save_dir.join(&checkpoint.file)));
+    let missing_checkpoints = checkpoints != save_config.checkpoints.len();
+
+    let mut orphans = Vec::new();
+    // Never sweep the working directory when no save directory is configured
+    if options.delete_orphans && !save_config.save_dir.as_os_str().is_empty() {
+        if let Ok(entries) = storage.list(&save_config.save_dir(&dirs).join(profile.dir())) {
+            for path in entries {
+                let is_save_file = path.extension().is_some_and(|ext| ext == "dat");
+                let referenced = save_config
+                    .saves
+                    .values()
+                    .chain(&save_config.checkpoints)
+                    .flat_map(|slot| slot.base.iter().chain([&slot.file]))
+                    .any(|file| save_config.save_dir(&dirs).join(file) == path);
+                if !is_save_file || referenced {
+                    continue;
+                }
+
+                if let Err(_e) = storage.remove(&path) {
+                    #[cfg(feature = "log")]
+                    warn!("Failed to delete orphaned save file {}: {}", path.display(), _e);
+                } else {
+                    orphans.push(path);
+                }
+            }
+        }
+    }
+
+    if !missing.is_empty() || missing_checkpoints {
+        setting_changed.write(GameSettingChanged);
+    }
+    if !missing.is_empty() || !orphans.is_empty() {
+        pruned.write(SavesPruned { missing, orphans });
+    }
+}
+
+fn refresh_metadata(
+    mut refresh_message: MessageReader<RefreshSaveMetadata>,
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    mut stats: ResMut<SaveStats>,
+    mut refreshed: MessageWriter<SaveMetadataRefreshed>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    let mut ids = BTreeSet::new();
+    for RefreshSaveMetadata(id) in refresh_message.read() {
+        match id {
+            Some(id) if save_config.saves.contains_key(id) => {
+                ids.insert(*id);
+            }
+            Some(_) => {}
+            None => ids.extend(save_config.saves.keys()),
+        }
+    }
+
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    let mut result = SaveMetadataRefreshed::default();
+    for id in ids {
+        let Some(slot) = save_config.saves.get_mut(&id) else {
+            continue;
+        };
+        let Ok(size) = storage.size(&save_dir.join(&slot.file)) else {
+            save_config.saves.remove(&id);
+            if save_config.last_saved == id {
+                save_config.last_saved = 0;
+            }
+            stats.slot_sizes.remove(&id);
+            result.missing.push(id);
+            continue;
+        };
+        stats.slot_sizes.insert(id, size);
+        if let Ok(meta) = crate::meta::read_meta(storage.0.as_ref(), &save_dir.join(&slot.file)) {
+            slot.name = meta.name;
+            slot.description = meta.description;
+            slot.created_at = meta.created_at;
+            slot.saved_at = meta.saved_at;
+            slot.playtime = meta.playtime;
+            slot.thumbnail = meta.thumbnail;
+            slot.kind = meta.kind;
+            slot.tags = meta.tags;
+            slot.game_version = meta.game_version;
+            slot.fields = meta.fields;
+            slot.custom = meta.custom;
+        }
+        result.slots.push(id);
+    }
+
+    // Menus watching the slots are rebuilt even if nothing changed
+    save_config.set_changed();
+    setting_changed.write(GameSettingChanged);
+    refreshed.write(result);
+}
+
+fn on_copy(
+    mut copy_message: MessageReader<CopySave>,
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    writes: Res<PendingWrites>,
+    rng: Res<NamingRng>,
+    profile: Res<CurrentProfile>,
+    options: Res<SaveOptions>,
+    mut copied: MessageWriter<SaveCopied>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for msg in copy_message.read() {
+        let Some(source) = save_config.saves.get(&msg.from).cloned() else {
+            continue;
+        };
+
+        let (to, file, revision, synced_revision) = if msg.to == 0 {
+            let Some(to) = save_config.next_id(options.id_allocation) else {
+                continue;
+            };
+            let file = new_save_file(&options.naming, &rng, &profile, to, |file| {
+                save_config.file_taken(&dirs, &storage, &writes, file)
+            });
+            (to, file, 0, 0)
+        } else if let Some(target) = save_config.saves.get(&msg.to) {
+            (msg.to, target.file.clone(), target.revision, target.synced_revision)
+        } else {
+            continue;
+        };
+
+        // The base is copied first, a delta is useless without it
+        let base = match &source.base {
+            Some(base) => {
+                let copied = unique_file(
+                    || file.with_file_name(format!("base_{}.dat", rng.string())),
+                    |copied| save_config.file_taken(&dirs, &storage, &writes, copied),
+                );
+                match storage.copy(
+                    &save_config.save_dir(&dirs).join(base),
+                    &save_config.save_dir(&dirs).join(&copied),
+                ) {
+                    Ok(()) => Some(copied),
+                    Err(_e) => {
+                        #[cfg(feature = "log")]
+                        error!("Failed to copy the base of slot {}: {}", msg.from, _e);
+                        continue;
+                    }
+                }
+            }
+            None => None,
+        };
+        let source_path = save_config.save_dir(&dirs).join(&source.file);
+        let target_path = save_config.save_dir(&dirs).join(&file);
+        if let Err(_e) = storage.copy(&source_path, &target_path) {
+            #[cfg(feature = "log")]
+            error!(
+                "Failed to copy save data {} to {}: {}",
+                source_path.display(),
+                target_path.display(),
+                _e
+            );
+            if let Some(base) = base {
+                let _ = storage.remove(&save_config.save_dir(&dirs).join(base));
+            }
+        } else {
+            let thumbnail = source.thumbnail.as_ref().and_then(|thumbnail| {
+                let copied = file.with_extension("png");
+                let target = save_config.save_dir(&dirs).join(&copied);
+                storage
+                    .copy(&save_config.save_dir(&dirs).join(thumbnail), &target)
+                    .ok()
+                    .map(|_| copied)
+            });
+            let old_base = save_config.saves.get(&to).and_then(|target| target.base.clone());
+            save_config.saves.insert(
+                to,
+                SaveSlot {
+                    file,
+                    thumbnail,
+                    base,
+                    created_at: unix_now(),
+                    revision: revision + 1,
+                    synced_revision,
+                    ..source
+                },
+            );
+            if let Some(old_base) = old_base {
+                let _ = storage.remove(&save_config.save_dir(&dirs).join(old_base));
+            }
+            copied.write(SaveCopied { from: msg.from, to });
+            setting_changed.write(GameSettingChanged);
+        }
+    }
+}
+
+fn on_rename(
+    mut rename_message: MessageReader<RenameSave>,
+    mut save_config: ResMut<SaveConfig>,
+    mut renamed: MessageWriter<SaveRenamed>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for msg in rename_message.read() {
+        if let Some(slot) = save_config.slot_mut(msg.id) {
+            slot.name = msg.name.clone();
+            slot.revision += 1;
+            renamed.write(SaveRenamed(msg.id));
+            setting_changed.write(GameSettingChanged);
+        }
+    }
+}
+
+fn on_set_tags(
+    mut tags_message: MessageReader<SetSlotTags>,
+    mut save_config: ResMut<SaveConfig>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for msg in tags_message.read() {
+        if let Some(slot) = save_config.slot_mut(msg.id) {
+            slot.tags = msg.tags.clone();
+            slot.revision += 1;
+            setting_changed.write(GameSettingChanged);
+        }
+    }
+}
+
+fn on_set_description(
+    mut description_message: MessageReader<SetSlotDescription>,
+    mut save_config: ResMut<SaveConfig>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+) {
+    for msg in description_message.read() {
+        if let Some(slot) = save_config.slot_mut(msg.id) {
+            slot.description = msg.text.clone();
+            slot.revision += 1;
+            setting_changed.write(GameSettingChanged);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn on_reencrypt<T>(
+    mut reencrypt: MessageReader<ReEncryptSaves>,
+    mut save_config: ResMut<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    writes: Res<PendingWrites>,
+    cipher: Res<SaveCipher>,
+    password: Res<SavePassword>,
+    options: Res<SaveOptions>,
+    mut key: ResMut<SaveKey>,
+    mut delta_base: ResMut<DeltaBase>,
+    mut reencrypted: MessageWriter<SavesReEncrypted>,
+    mut failed: MessageWriter<ReEncryptFailed>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+    #[cfg(feature = "signing")] signing: Option<Res<crate::signing::SigningKey>>,
+) where
+    T: Resource + EncryptSave,
+{
+    for msg in reencrypt.read() {
+        // Files still being written would be overwritten with the old key
+        writes.flush();
+
+        let save_dir = save_config.save_dir(&dirs).into_owned();
+        // Plain slots have no key to change
+        let files = save_config.sealed_files();
+
+        let total = files.len();
+        let limits = options.load_limits;
+        let new_key_id = key_id(&msg.new_key);
+        let mut sealed = Vec::with_capacity(files.len());
+        let mut failures = Vec::new();
+        for (slot, file) in files {
+            let path = save_dir.join(file);
+            let result = if password.get().is_some() {
+                Err(SaveError::Invalid(
+                    "The saves are encrypted with the password rather than the key".to_string(),
+                ))
+            } else {
+                storage
+                    .size(&path)
+                    .map_err(SaveError::from)
+                    .and_then(|size| LoadLimits::check(size, limits.max_file_size))
+                    .and_then(|_| Ok(storage.read(&path)?))
+                    .and_then(|data| {
+                        // Already written by an interrupted rotation
+                        if SaveHeader::decode(&data).is_some_and(|(header, _)| header.key_id == Some(new_key_id)) {
+                            return Ok(None);
+                        }
+                        let sealed = reseal(cipher.0.as_ref(), &data, &msg.old_key, &msg.new_key, limits)?;
+                        #[cfg(feature = "signing")]
+                        let sealed = crate::signing::sign_with(signing.as_deref(), sealed);
+                        Ok(Some(sealed))
+                    })
+            };
+            match result {
+                Ok(Some(data)) => sealed.push((slot, path, data)),
+                Ok(None) => {}
+                Err(error) => failures.push(ReEncryptFailed { slot, path, error }),
+            }
+        }
+        // Nothing is written unless every file opens, the files left behind would be lost with the old key
+        if !failures.is_empty() {
+            failed.write_batch(failures);
+            continue;
+        }
+
+        // Written now rather than on the `IoTaskPool`, files are never read with a key they aren't sealed with
+        let mut count = 0;
+        for (slot, path, data) in sealed {
+            if let Err(error) = storage.write(&path, &data) {
+                failures.push(ReEncryptFailed {
+                    slot,
+                    path,
+                    error: error.into(),
+                });
+                continue;
+            }
+            if let Some(slot) = slot.and_then(|id| save_config.slot_mut(id)) {
+                slot.revision += 1;
+            }
+            count += 1;
+        }
+        // The base of the next delta is read again
+        delta_base.0 = None;
+        // The files written can only be read with the new key, the others are finished by sending the message again
+        let rekeyed = failures.len() < total || total == 0;
+        failed.write_batch(failures);
+        if !rekeyed {
+            continue;
+        }
+
+        *key = if msg.new_key.as_bytes() == T::ENCR_KEY.as_bytes() {
+            SaveKey::default()
+        } else {
+            SaveKey::new(msg.new_key.clone())
+        };
+        reencrypted.write(SavesReEncrypted(count));
+        setting_changed.write(GameSettingChanged);
+    }
+}
+
+/// Decrypt the file `data` with `old_key` and encrypt it again with `new_key`, keeping its game version
+pub(crate) fn reseal(
+    cipher: &dyn Cipher,
+    data: &[u8],
+    old_key: &SecretKey,
+    new_key: &SecretKey,
+    limits: LoadLimits,
+) -> Result<Vec<u8>, SaveError> {
+    let game_version = SaveHeader::decode(data).and_then(|(header, _)| header.game_version);
+    let decrypted = open(cipher, data, old_key, None, false)?;
+    LoadLimits::check(decrypted.len() as u64, limits.max_allocation)?;
+    seal(cipher, &decrypted, new_key, None, game_version.as_deref())
+}
+
+#[cfg(feature = "derive")]
+pub use bevy_save_manager_derive::EncryptSave;
+pub use crate::naming::NamingStrategy;
+
+/// Can be implemented with `#[derive(EncryptSave)]` and the `derive` feature
+pub trait EncryptSave: Serialize + for<'de> Deserialize<'de> {
+    const ENCR_KEY: &'static str = "0123456789abcdef";
+    /// Recorded in each save slot. Slots of another version are loaded through [`Self::migrate`].
+    const VERSION: u32 = 0;
+
+    /// Decode `data` of a slot saved with `version`, e.g. with [`SaveEncoding::decode`] into an older type then converted
+    fn migrate(version: u32, _data: &[u8], _encoding: SaveEncoding) -> Result<Self, SaveError>
+    where
+        Self: Sized,
+    {
+        Err(SaveError::VersionMismatch {
+            saved: version.to_string(),
+            current: Self::VERSION.to_string(),
+        })
+    }
+
+    /// Check the decoded data of a slot before any resource of the save is replaced, e.g. with
+    /// [`SaveError::Invalid`]. The load fails and the world is left as it was on error.
+    fn validate(&self) -> Result<(), SaveError> {
+        Ok(())
+    }
+
+    fn load_from(&mut self, config_path: &Path) -> Result<(), SaveError> {
+        self.load_with(&FsBackend, config_path)
+    }
+
+    fn load_with(&mut self, backend: &dyn SaveBackend, saved_path: &Path) -> Result<(), SaveError> {
+        let decrypted = read_encrypted(
+            backend,
+            SaveCipher::default().0.as_ref(),
+            None,
+            saved_path,
+            &SecretKey::from(Self::ENCR_KEY),
+        )?;
+        *self = SaveEncoding::Legacy.decode(decrypted.as_slice())?;
+        Ok(())
+    }
+
+    fn save_to(&self, saved_path: PathBuf) -> Result<(), SaveError> {
+        self.save_with(Arc::new(FsBackend), saved_path)
+    }
+
+    /// Encrypt on the caller thread and write the file at `saved_path` on the `IoTaskPool`.
+    /// A failed write is only logged, see [`Self::save_with_blocking`] to handle it.
+    fn save_with(&self, backend: Arc<dyn SaveBackend>, saved_path: PathBuf) -> Result<(), SaveError> {
+        let data = encrypt_legacy(self)?;
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Err(_e) = backend.write(&saved_path, &data) {
+                    #[cfg(feature = "log")]
+                    error!("Failed to write {}: {}", saved_path.display(), _e);
+                }
+            })
+            .detach();
+        Ok(())
+    }
+
+    /// Write the file at `saved_path` before returning, e.g. on exit or to a backend which must not be written
+    /// from another thread
+    fn save_with_blocking(&self, backend: &dyn SaveBackend, saved_path: &Path) -> Result<(), SaveError> {
+        backend.write(saved_path, &encrypt_legacy(self)?)?;
+        Ok(())
+    }
+}
+
+/// Bytes the decoder may allocate for a save, so a corrupted length fails instead of exhausting the memory
+const DECODE_LIMIT: usize = u32::MAX as usize;
+
+/// Binary layout of the resources in a save slot, recorded in the slot.
+/// [`EncryptSave::save_with`], [`EncryptSave::save_with_blocking`] and [`EncryptSave::load_with`] always use
+/// [`SaveEncoding::Legacy`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SaveEncoding {
+    /// bincode 1 compatible: little-endian, fixed-size integers
+    #[default]
+    Legacy,
+    /// bincode 2 standard: little-endian, variable-size integers. Smaller saves.
+    Standard,
+}
+
+impl SaveEncoding {
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            Self::Legacy => 0,
+            Self::Standard => 1,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Legacy),
+            1 => Some(Self::Standard),
+            _ => None,
+        }
+    }
+
+    pub fn encode<R>(&self, value: &R) -> Result<Vec<u8>, SaveError>
+    where
+        R: Serialize + ?Sized,
+    {
+        Ok(match self {
+            Self::Legacy => bincode::serde::encode_to_vec(value, bincode::config::legacy())?,
+            Self::Standard => bincode::serde::encode_to_vec(value, bincode::config::standard())?,
+        })
+    }
+
+    pub fn encode_into<R>(&self, value: &R, mut writer: &mut dyn Write) -> Result<(), SaveError>
+    where
+        R: Serialize + ?Sized,
+    {
+        match self {
+            Self::Legacy => bincode::serde::encode_into_std_write(value, &mut writer, bincode::config::legacy())?,
+            Self::Standard => bincode::serde::encode_into_std_write(value, &mut writer, bincode::config::standard())?,
+        };
+        Ok(())
+    }
+
+    pub fn decode<R>(&self, data: &[u8]) -> Result<R, SaveError>
+    where
+        R: DeserializeOwned,
+    {
+        Ok(self.decode_partial(data)?.0)
+    }
+
+    pub(crate) fn decode_from<R>(&self, mut reader: &mut dyn Read) -> Result<R, SaveError>
+    where
+        R: DeserializeOwned,
+    {
+        Ok(match self {
+            Self::Legacy => bincode::serde::decode_from_std_read(
+                &mut reader,
+                bincode::config::legacy().with_limit::<DECODE_LIMIT>(),
+            )?,
+            Self::Standard => bincode::serde::decode_from_std_read(
+                &mut reader,
+                bincode::config::standard().with_limit::<DECODE_LIMIT>(),
+            )?,
+        })
+    }
+
+    /// Decode `R` from the start of `data`, also returning the number of bytes read
+    pub(crate) fn decode_partial<R>(&self, data: &[u8]) -> Result<(R, usize), SaveError>
+    where
+        R: DeserializeOwned,
+    {
+        Ok(match self {
+            Self::Legacy => {
+                bincode::serde::decode_from_slice(data, bincode::config::legacy().with_limit::<DECODE_LIMIT>())?
+            }
+            Self::Standard => {
+                bincode::serde::decode_from_slice(data, bincode::config::standard().with_limit::<DECODE_LIMIT>())?
+            }
+        })
+    }
+}
+
+pub(crate) fn read_encrypted(
+    backend: &dyn SaveBackend,
+    cipher: &dyn Cipher,
+    password: Option<&SecretKey>,
+    saved_path: &Path,
+    key: &SecretKey,
+) -> Result<Zeroizing<Vec<u8>>, SaveError> {
+    let enc_saved = backend.read(saved_path)?;
+    open(cipher, &enc_saved, key, password, false)
+}
+
+/// File written by [`EncryptSave::save_with`]
+pub(crate) fn encrypt_legacy<T>(value: &T) -> Result<Vec<u8>, SaveError>
+where
+    T: EncryptSave,
+{
+    let data = Zeroizing::new(SaveEncoding::Legacy.encode(value)?);
+    seal(
+        SaveCipher::default().0.as_ref(),
+        &data,
+        &SecretKey::from(T::ENCR_KEY),
+        None,
+        None,
+    )
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// How ids of new slots are chosen, set with [`EncryptSavePlugin::with_id_allocation`]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum IdAllocation {
+    /// One more than the highest id in use, the lowest free id once `u32::MAX` is taken
+    #[default]
+    Monotonic,
+    /// Lowest free id, reusing the ones of deleted slots
+    ReuseFreed,
 }
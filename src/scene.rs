@@ -0,0 +1,119 @@
+use crate::error::SaveError;
+use crate::registry::{
+    SaveSection,
+    Staged,
+    Undo,
+};
+use crate::save::SaveEncoding;
+use bevy::app::App;
+use bevy::ecs::entity::EntityHashMap;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    AppTypeRegistry,
+    Component,
+    Entity,
+    Reflect,
+    ReflectComponent,
+    ReflectDefault,
+    With,
+    World,
+};
+use bevy::scene::serde::SceneDeserializer;
+use bevy::scene::{
+    DynamicScene,
+    DynamicSceneBuilder,
+};
+use serde::de::DeserializeSeed;
+
+const SCENE_SECTION: &str = "bevy_save_manager::scene";
+
+/// Entities with this component are written into every save and respawned on load.
+/// Only reflected components registered in the `AppTypeRegistry` are stored.
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component, Default)]
+pub struct Persist;
+
+pub(crate) fn scene_section() -> SaveSection {
+    SaveSection {
+        name: SCENE_SECTION.to_string(),
+        init,
+        capture,
+        capture_into: None,
+        stage,
+        apply,
+        migrate: None,
+    }
+}
+
+fn init(app: &mut App) {
+    app.register_type::<Persist>();
+}
+
+/// Scenes are stored as RON whatever the encoding
+fn capture(world: &World, _encoding: SaveEncoding) -> Result<Vec<u8>, SaveError> {
+    let mut builder = DynamicSceneBuilder::from_world(world);
+    // The query can't be built before any `Persist` has been spawned
+    if let Some(mut persisted) = world.try_query_filtered::<Entity, With<Persist>>() {
+        builder = builder.extract_entities(persisted.iter(world));
+    }
+
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+    let scene = builder
+        .build()
+        .serialize(&type_registry)
+        .map_err(|e| SaveError::Serialize(e.into()))?;
+    Ok(scene.into_bytes())
+}
+
+fn stage(world: &World, data: &[u8], _encoding: SaveEncoding) -> Result<Staged, SaveError> {
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+    let mut deserializer = ron::de::Deserializer::from_bytes(data).map_err(|e| SaveError::Deserialize(e.into()))?;
+    let scene = SceneDeserializer {
+        type_registry: &type_registry,
+    }
+    .deserialize(&mut deserializer)
+    .map_err(|e| SaveError::Deserialize(e.into()))?;
+    Ok(Box::new(scene))
+}
+
+/// Replace the persisted entities, which are respawned from a copy if the scene or a later section fails
+fn apply(world: &mut World, staged: Staged) -> Result<Undo, SaveError> {
+    let scene = staged
+        .downcast::<DynamicScene>()
+        .map_err(|_| SaveError::Corrupted("Staged data is not a scene".to_string()))?;
+    let previous = take_persisted(world);
+    let mut entity_map = EntityHashMap::default();
+    if let Err(e) = scene.write_to_world(world, &mut entity_map) {
+        restore_persisted(world, entity_map.values().copied().collect(), &previous);
+        return Err(SaveError::Deserialize(e.into()));
+    }
+    let spawned: Vec<Entity> = entity_map.values().copied().collect();
+    Ok(Box::new(move |world: &mut World| {
+        restore_persisted(world, spawned, &previous)
+    }))
+}
+
+/// Despawn the persisted entities, returning a copy of them
+fn take_persisted(world: &mut World) -> DynamicScene {
+    let persisted: Vec<Entity> = world.query_filtered::<Entity, With<Persist>>().iter(world).collect();
+    let copy = DynamicSceneBuilder::from_world(world)
+        .extract_entities(persisted.iter().copied())
+        .build();
+    for entity in persisted {
+        // Children may already be gone with their parent
+        let _ = world.try_despawn(entity);
+    }
+    copy
+}
+
+/// Despawn `spawned` and respawn the entities copied by [`take_persisted`]
+fn restore_persisted(world: &mut World, spawned: Vec<Entity>, previous: &DynamicScene) {
+    for entity in spawned {
+        let _ = world.try_despawn(entity);
+    }
+    if let Err(_e) = previous.write_to_world(world, &mut EntityHashMap::default()) {
+        #[cfg(feature = "log")]
+        warn!("Failed to respawn the entities replaced by a failed load: {}", _e);
+    }
+}
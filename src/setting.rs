@@ -1,31 +1,123 @@
+use crate::backend::{
+    FsBackend,
+    SaveBackend,
+    SaveStorage,
+};
+use crate::cipher::{
+    open,
+    seal,
+    SaveCipher,
+    SaveHeader,
+    SecretKey,
+};
+use crate::error::{
+    SaveError,
+    SettingError,
+};
+use crate::io::{
+    flush_pending_writes,
+    FlushSaves,
+    PendingIoPlugin,
+    PendingWrites,
+    WriteNotifier,
+};
+use crate::options::{
+    set_from_ron,
+    SettingUi,
+};
+#[cfg(feature = "remote-config")]
+use crate::overlay::RemoteOverlay;
+use crate::paths::SaveDirs;
+use crate::profile::{
+    on_switch,
+    CurrentProfile,
+    ProfileSwitched,
+};
 use bevy::app::App;
-use bevy::asset::ron::de::from_reader;
-use bevy::asset::ron::ser::{
-    to_string_pretty,
-    PrettyConfig,
+use bevy::reflect::serde::{
+    ReflectDeserializerProcessor,
+    TypedReflectDeserializer,
+};
+use bevy::reflect::{
+    DynamicStruct,
+    FromReflect,
+    GetTypeRegistration,
+    PartialReflect,
+    ReflectMut,
+    ReflectRef,
+    Struct,
+    StructInfo,
+    TypeInfo,
+    TypeRegistration,
+    TypeRegistry,
 };
+use ron::ser::PrettyConfig;
 #[cfg(feature = "log")]
-use bevy::prelude::warn;
+use bevy::prelude::{
+    error,
+    warn,
+};
 use bevy::prelude::{
     on_message,
+    AppExit,
     IntoScheduleConfigs,
+    Last,
     Message,
+    MessageReader,
     MessageWriter,
     Plugin,
+    Real,
     Res,
     ResMut,
     Resource,
     Startup,
+    SystemCondition,
+    Time,
     Update,
 };
 use bevy::tasks::IoTaskPool;
+#[cfg(any(feature = "toml", feature = "json"))]
+use serde::de::DeserializeSeed;
+use serde::de::{
+    DeserializeOwned,
+    Error as _,
+    IgnoredAny,
+    MapAccess,
+    Visitor,
+};
 use serde::{
     Deserialize,
+    Deserializer,
     Serialize,
 };
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{
+    DefaultHasher,
+    Hash,
+    Hasher,
+};
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "derive")]
+pub use bevy_save_manager_derive::GameSetting;
 
 #[derive(Default)]
 pub struct GameSettingSupportPlugin<T>
@@ -33,6 +125,39 @@ where
     T: Resource + Default + GameSetting + Clone,
 {
     _config: Option<T>,
+    env_overrides: Option<SettingEnvOverrides<T>>,
+    file: SettingFile<T>,
+}
+
+impl<T> GameSettingSupportPlugin<T>
+where
+    T: Resource + Default + GameSetting + Clone,
+{
+    /// Override fields with the environment variables `{prefix}__{FIELD}` after each load, nested fields being
+    /// separated by `__` too, as in `MYGAME_AUDIO__MASTER=0.5`. Names are case-insensitive and values are written
+    /// in RON, strings may go without quotes. The overridden values are written to the file with the next change.
+    pub fn with_env_overrides(mut self, prefix: impl Into<String>) -> Self
+    where
+        T: Struct + GetTypeRegistration,
+    {
+        self.env_overrides = Some(SettingEnvOverrides {
+            prefix: prefix.into(),
+            apply: override_fields::<T>,
+        });
+        self
+    }
+
+    /// Store the file at `path` instead of [`GameSetting::config_path`]
+    pub(crate) fn with_config_path(mut self, path: fn(&SaveDirs) -> PathBuf) -> Self {
+        self.file.path = Some(path);
+        self
+    }
+
+    /// Seal the file like a save with `key`, for settings without [`GameSetting::SECTIONS`]
+    pub(crate) fn with_key(mut self, key: SecretKey) -> Self {
+        self.file.key = Some(key);
+        self
+    }
 }
 
 impl<T> Plugin for GameSettingSupportPlugin<T>
@@ -40,11 +165,61 @@ where
     T: Resource + Default + GameSetting + Clone,
 {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<PendingIoPlugin>() {
+            app.add_plugins(PendingIoPlugin);
+        }
+
         app.insert_resource(T::default())
+            .init_resource::<SaveStorage>()
+            .init_resource::<CurrentProfile>()
             .add_message::<GameSettingChanged>()
             .add_message::<GameSettingLoaded>()
+            .add_message::<GameSettingSaved<T>>()
+            .add_message::<GameSettingSaveFailed<T>>()
+            .add_message::<GameSettingRecovered<T>>()
+            .add_message::<GameSettingInvalid<T>>()
+            .add_message::<FlushGameSettings>()
+            .add_message::<ProfileSwitched>()
+            .insert_resource(SettingWrites::<T>::default())
+            .insert_resource(SettingDebounce::<T>::default())
+            .insert_resource(self.file.clone())
             .add_systems(Startup, load_config::<T>)
-            .add_systems(Update, save_config::<T>.run_if(on_message::<GameSettingChanged>));
+            .add_systems(
+                Update,
+                mark_changed::<T>
+                    .after(load_config::<T>)
+                    .run_if(on_message::<GameSettingChanged>),
+            )
+            .add_systems(
+                Last,
+                (
+                    save_config::<T>
+                        .run_if(|debounce: Res<SettingDebounce<T>>| debounce.changed)
+                        .before(flush_pending_writes),
+                    report_writes::<T>,
+                ),
+            );
+
+        if T::PER_PROFILE {
+            app.add_systems(
+                Update,
+                (reset_config::<T>, load_config::<T>)
+                    .chain()
+                    .after(on_switch)
+                    .run_if(on_message::<ProfileSwitched>),
+            );
+        }
+
+        if let Some(env_overrides) = &self.env_overrides {
+            app.insert_resource(env_overrides.clone())
+                .add_systems(Startup, apply_env_overrides::<T>.after(load_config::<T>))
+                .add_systems(
+                    Update,
+                    apply_env_overrides::<T>
+                        .after(load_config::<T>)
+                        .run_if(on_message::<GameSettingLoaded>.or(on_message::<ProfileSwitched>)),
+                );
+        }
     }
 }
 
@@ -54,79 +229,1135 @@ pub struct GameSettingChanged;
 #[derive(Message)]
 pub struct GameSettingLoaded;
 
-fn load_config<T>(mut config: ResMut<T>, mut event: MessageWriter<GameSettingLoaded>)
+/// The file of setting `T` could not be parsed, it was restored from the backup of the last file loaded
+#[derive(Message, Debug)]
+pub struct GameSettingRecovered<T> {
+    pub path: PathBuf,
+    /// Why the file could not be loaded
+    pub error: SettingError,
+    _setting: PhantomData<T>,
+}
+
+/// Values of setting `T` found invalid by [`GameSetting::validate`] once loaded
+#[derive(Message, Debug)]
+pub struct GameSettingInvalid<T> {
+    pub path: PathBuf,
+    pub issues: Vec<SettingIssue>,
+    _setting: PhantomData<T>,
+}
+
+/// A value rejected by [`GameSetting::validate`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettingIssue {
+    /// Name of the field holding the value
+    pub field: String,
+    pub message: String,
+}
+
+impl SettingIssue {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SettingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Write the settings held back by [`GameSetting::DEBOUNCE`] now, e.g. when an options menu is closed.
+/// Also done on [`FlushSaves`] and `AppExit`.
+#[derive(Message)]
+pub struct FlushGameSettings;
+
+/// The file of setting `T` was written. Writes which wouldn't change the file are skipped and not reported.
+#[derive(Message)]
+pub struct GameSettingSaved<T> {
+    pub path: PathBuf,
+    _setting: PhantomData<T>,
+}
+
+/// Setting `T` could not be serialized or written
+#[derive(Message, Debug)]
+pub struct GameSettingSaveFailed<T> {
+    pub path: PathBuf,
+    pub error: SettingError,
+    _setting: PhantomData<T>,
+}
+
+/// Outcome of the writes of setting `T`, sent back from the `IoTaskPool`
+#[derive(Resource)]
+struct SettingWrites<T> {
+    sender: WriteNotifier,
+    receiver: Mutex<Receiver<(PathBuf, std::io::Result<()>)>>,
+    _setting: PhantomData<T>,
+}
+
+impl<T> Default for SettingWrites<T> {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+            _setting: PhantomData,
+        }
+    }
+}
+
+/// Where the file of setting `T` is stored, when not where [`GameSetting`] puts it
+#[derive(Resource)]
+pub(crate) struct SettingFile<T> {
+    /// Replaces [`GameSetting::config_path`]
+    path: Option<fn(&SaveDirs) -> PathBuf>,
+    /// Seals the file like a save, plain files are refused
+    key: Option<SecretKey>,
+    _setting: PhantomData<T>,
+}
+
+impl<T> Default for SettingFile<T> {
+    fn default() -> Self {
+        Self {
+            path: None,
+            key: None,
+            _setting: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for SettingFile<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path,
+            key: self.key.clone(),
+            _setting: PhantomData,
+        }
+    }
+}
+
+impl<T> SettingFile<T>
 where
+    T: GameSetting,
+{
+    /// [`GameSetting::profile_config_path`] from the path of this file
+    pub(crate) fn profile_config_path(&self, dirs: &SaveDirs, profile: &CurrentProfile) -> PathBuf {
+        match self.path {
+            Some(path) => in_profile::<T>(path(dirs), profile),
+            None => T::profile_config_path(dirs, profile),
+        }
+    }
+
+    /// [`GameSetting::load_with`], opening the file if it was sealed. Plain files are refused when this file has
+    /// a key, so they can't replace a sealed one.
+    pub(crate) fn load_with(&self, config: &mut T, backend: &dyn SaveBackend, path: &Path) -> Result<(), SettingError> {
+        let Some(key) = &self.key else {
+            return config.load_with(backend, path);
+        };
+        let data = read_file(backend, path)?;
+        *config = T::decode(&open_setting(&data, Some(key))?)?;
+        Ok(())
+    }
+}
+
+/// `data` sealed like a save if there is a `key`. Sealed files always start with a [`SaveHeader`], which records
+/// the id of the key.
+fn seal_setting(data: Vec<u8>, key: Option<&SecretKey>) -> Result<Vec<u8>, SettingError> {
+    let Some(key) = key else {
+        return Ok(data);
+    };
+    let data = Zeroizing::new(data);
+    seal(SaveCipher::default().0.as_ref(), &data, key, None, None).map_err(|e| SettingError::Format(e.into()))
+}
+
+/// Data of a file written by [`seal_setting`] with `key`
+fn open_setting(data: &[u8], key: Option<&SecretKey>) -> Result<Zeroizing<Vec<u8>>, SettingError> {
+    let Some(key) = key else {
+        return Ok(Zeroizing::new(data.to_vec()));
+    };
+    if SaveHeader::decode(data).is_none() {
+        return Err(SettingError::Format(
+            SaveError::Corrupted("Setting file is not sealed".to_string()).into(),
+        ));
+    }
+    open(SaveCipher::default().0.as_ref(), data, key, None, false).map_err(|e| SettingError::Format(e.into()))
+}
+
+/// Changes of setting `T` not written yet, and when and what was last written
+#[derive(Resource)]
+struct SettingDebounce<T> {
+    changed: bool,
+    written_at: Option<Duration>,
+    /// Hash of the content of each file, as last written
+    written: HashMap<PathBuf, u64>,
+    _setting: PhantomData<T>,
+}
+
+impl<T> Default for SettingDebounce<T> {
+    fn default() -> Self {
+        Self {
+            changed: false,
+            written_at: None,
+            written: HashMap::new(),
+            _setting: PhantomData,
+        }
+    }
+}
+
+/// Load setting `T`, each file from its backup if it is corrupted, and back up the files once loaded
+pub(crate) fn load_config<T>(
+    mut config: ResMut<T>,
+    storage: Res<SaveStorage>,
+    profile: Res<CurrentProfile>,
+    dirs: Res<SaveDirs>,
+    file: Res<SettingFile<T>>,
+    mut event: MessageWriter<GameSettingLoaded>,
+    mut recovered: MessageWriter<GameSettingRecovered<T>>,
+    mut invalid: MessageWriter<GameSettingInvalid<T>>,
+) where
     T: Resource + GameSetting,
 {
-    if let Err(_e) = config.load() {
+    let config_path = file.profile_config_path(&dirs, &profile);
+    let mut loaded = false;
+    for (section, path) in setting_files::<T>(&config_path) {
+        let backup = backup_path(&path);
+        match load_file(&mut *config, &file, storage.0.as_ref(), section, &path) {
+            Ok(()) => {
+                if let Err(_e) = storage.copy(&path, &backup) {
+                    #[cfg(feature = "log")]
+                    warn!("Failed to back up game config {}: {}", backup.display(), _e);
+                }
+                loaded = true;
+            }
+            // Sections which were never written keep their defaults
+            Err(SettingError::NotFound(_)) if section.is_some() => {}
+            Err(error @ (SettingError::Deserialize(_) | SettingError::Format(_)))
+                if load_file(&mut *config, &file, storage.0.as_ref(), section, &backup).is_ok() =>
+            {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Game config {} is corrupted, restored from its backup: {}",
+                    path.display(),
+                    error
+                );
+                if let Err(_e) = storage.copy(&backup, &path) {
+                    #[cfg(feature = "log")]
+                    warn!("Failed to restore game config {}: {}", path.display(), _e);
+                }
+                recovered.write(GameSettingRecovered {
+                    path,
+                    error,
+                    _setting: PhantomData,
+                });
+                loaded = true;
+            }
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Failed to load game config {} : {}",
+                    path.as_path().to_str().unwrap_or_default(),
+                    _e
+                );
+            }
+        }
+    }
+    if !loaded {
+        return;
+    }
+
+    if let Err(issues) = config.validate() {
         #[cfg(feature = "log")]
-        warn!(
-            "Failed to load game config {} : {}",
-            T::config_path().as_path().to_str().unwrap_or_default(),
-            _e
-        );
-    } else {
-        event.write(GameSettingLoaded);
+        for issue in &issues {
+            warn!("Invalid game config {}: {}", config_path.display(), issue);
+        }
+        invalid.write(GameSettingInvalid {
+            path: config_path,
+            issues,
+            _setting: PhantomData,
+        });
+    }
+    event.write(GameSettingLoaded);
+}
+
+/// Files of setting `T` at `config_path`, with their section if it has [`GameSetting::SECTIONS`]
+fn setting_files<T>(config_path: &Path) -> Vec<(Option<&'static str>, PathBuf)>
+where
+    T: GameSetting,
+{
+    if T::SECTIONS.is_empty() {
+        return vec![(None, config_path.to_path_buf())];
+    }
+    T::SECTIONS
+        .iter()
+        .map(|section| (Some(*section), config_path.with_file_name(section)))
+        .collect()
+}
+
+/// Data of each file of `setting` at `config_path`
+fn encode_files<T>(setting: &T, config_path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>, SettingError>
+where
+    T: GameSetting,
+{
+    setting_files::<T>(config_path)
+        .into_iter()
+        .map(|(section, path)| {
+            let data = match section {
+                Some(section) => setting.encode_section(section)?,
+                None => setting.encode()?,
+            };
+            Ok((path, data))
+        })
+        .collect()
+}
+
+fn load_file<T>(
+    config: &mut T,
+    file: &SettingFile<T>,
+    backend: &dyn SaveBackend,
+    section: Option<&str>,
+    path: &Path,
+) -> Result<(), SettingError>
+where
+    T: GameSetting,
+{
+    match section {
+        Some(section) => config.decode_section(section, &read_file(backend, path)?),
+        None => file.load_with(config, backend, path),
+    }
+}
+
+fn read_file(backend: &dyn SaveBackend, path: &Path) -> Result<Vec<u8>, SettingError> {
+    backend.read(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => SettingError::NotFound(path.to_path_buf()),
+        _ => e.into(),
+    })
+}
+
+/// Copy of the last file of a setting which could be loaded
+fn backup_path(config_path: &Path) -> PathBuf {
+    let mut backup = config_path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Environment variables overriding the fields of setting `T`, see
+/// [`GameSettingSupportPlugin::with_env_overrides`]
+#[derive(Resource)]
+struct SettingEnvOverrides<T> {
+    prefix: String,
+    /// Set the fields at the paths after the prefix to the values
+    apply: fn(&mut T, &[(String, String)]),
+}
+
+impl<T> Clone for SettingEnvOverrides<T> {
+    fn clone(&self) -> Self {
+        Self {
+            prefix: self.prefix.clone(),
+            apply: self.apply,
+        }
+    }
+}
+
+fn apply_env_overrides<T>(mut config: ResMut<T>, overrides: Res<SettingEnvOverrides<T>>)
+where
+    T: Resource,
+{
+    let prefix = format!("{}__", overrides.prefix);
+    let vars: Vec<(String, String)> = std::env::vars()
+        .filter_map(|(name, value)| Some((name.strip_prefix(&prefix)?.to_string(), value)))
+        .collect();
+    if !vars.is_empty() {
+        (overrides.apply)(&mut config, &vars);
     }
 }
 
-fn save_config<T>(config: Res<T>)
+fn override_fields<T>(config: &mut T, vars: &[(String, String)])
 where
+    T: Struct + GetTypeRegistration,
+{
+    let mut registry = TypeRegistry::new();
+    registry.register::<T>();
+    for (path, value) in vars {
+        let result = field_at_path(config.as_partial_reflect_mut(), path)
+            .ok_or_else(|| "no such field".to_string())
+            .and_then(|field| set_from_ron(field, value, &registry));
+        if let Err(_e) = result {
+            #[cfg(feature = "log")]
+            warn!("Failed to override game config field {}: {}", path, _e);
+        }
+    }
+}
+
+/// Field at `path`, the names of nested fields separated by `__` and compared ignoring case
+fn field_at_path<'a>(value: &'a mut dyn PartialReflect, path: &str) -> Option<&'a mut dyn PartialReflect> {
+    path.split("__").try_fold(value, |value, name| {
+        let ReflectMut::Struct(fields) = value.reflect_mut() else {
+            return None;
+        };
+        let index = (0..fields.field_len()).find(|index| {
+            fields
+                .name_at(*index)
+                .is_some_and(|field| field.eq_ignore_ascii_case(name))
+        })?;
+        fields.field_at_mut(index)
+    })
+}
+
+fn mark_changed<T>(mut debounce: ResMut<SettingDebounce<T>>)
+where
+    T: Resource,
+{
+    debounce.changed = true;
+}
+
+/// Write setting `T` at most once every [`GameSetting::DEBOUNCE`], unless flushed
+#[allow(clippy::too_many_arguments)]
+fn save_config<T>(
+    config: Res<T>,
+    storage: Res<SaveStorage>,
+    profile: Res<CurrentProfile>,
+    dirs: Res<SaveDirs>,
+    file: Res<SettingFile<T>>,
+    writes: Res<SettingWrites<T>>,
+    pending: Res<PendingWrites>,
+    mut debounce: ResMut<SettingDebounce<T>>,
+    time: Option<Res<Time<Real>>>,
+    mut flush_settings: MessageReader<FlushGameSettings>,
+    mut flush_saves: MessageReader<FlushSaves>,
+    mut exit: MessageReader<AppExit>,
+    mut failed: MessageWriter<GameSettingSaveFailed<T>>,
+    #[cfg(feature = "remote-config")] overlay: Option<Res<RemoteOverlay<T>>>,
+) where
     T: Resource + GameSetting,
 {
-    if let Err(_e) = config.save() {
-        #[cfg(feature = "log")]
-        warn!(
-            "Failed to save game config {}: {}",
-            T::config_path().as_path().to_str().unwrap_or_default(),
-            _e
-        );
+    let flush = flush_settings.read().count() + flush_saves.read().count() + exit.read().count() > 0;
+    let now = time.map(|time| time.elapsed());
+    if let (Some(now), Some(written_at)) = (now, debounce.written_at) {
+        if !flush && now < written_at + T::DEBOUNCE {
+            return;
+        }
+    }
+    debounce.changed = false;
+    debounce.written_at = now;
+
+    let config: &T = &config;
+    #[cfg(feature = "remote-config")]
+    let local = overlay.and_then(|overlay| overlay.local_values(config));
+    #[cfg(feature = "remote-config")]
+    let config = local.as_ref().unwrap_or(config);
+
+    let config_path = file.profile_config_path(&dirs, &profile);
+    for (section, path) in setting_files::<T>(&config_path) {
+        // Settings with sections are never sealed, see `with_key`
+        let (data, key) = match section {
+            Some(section) => (config.encode_section(section), None),
+            None => (config.encode(), file.key.as_ref()),
+        };
+        let data = data.and_then(|data| {
+            // Hashed before sealing, which uses a new nonce each time
+            let hash = content_hash(&data);
+            // Compared to the file itself until it is written once, settings files are small
+            let unchanged = match debounce.written.get(&path) {
+                Some(written) => *written == hash,
+                None => storage
+                    .read(&path)
+                    .is_ok_and(|file| open_setting(&file, key).is_ok_and(|file| *file == data)),
+            };
+            let data = match unchanged {
+                true => None,
+                false => Some(seal_setting(data, key)?),
+            };
+            debounce.written.insert(path.clone(), hash);
+            Ok(data)
+        });
+        match data {
+            Ok(Some(data)) => pending.spawn_notified_write(storage.0.clone(), path, data, writes.sender.clone()),
+            Ok(None) => {}
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!(
+                    "Failed to save game config {}: {}",
+                    path.as_path().to_str().unwrap_or_default(),
+                    error
+                );
+                failed.write(GameSettingSaveFailed {
+                    path,
+                    error,
+                    _setting: PhantomData,
+                });
+            }
+        }
+    }
+}
+
+fn content_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn report_writes<T>(
+    writes: Res<SettingWrites<T>>,
+    mut debounce: ResMut<SettingDebounce<T>>,
+    mut saved: MessageWriter<GameSettingSaved<T>>,
+    mut failed: MessageWriter<GameSettingSaveFailed<T>>,
+) where
+    T: Resource,
+{
+    let receiver = writes.receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for (path, outcome) in receiver.try_iter() {
+        match outcome {
+            Ok(()) => {
+                saved.write(GameSettingSaved {
+                    path,
+                    _setting: PhantomData,
+                });
+            }
+            Err(e) => {
+                // The file may still hold an older content
+                debounce.written.remove(&path);
+                failed.write(GameSettingSaveFailed {
+                    path,
+                    error: e.into(),
+                    _setting: PhantomData,
+                });
+            }
+        }
     }
 }
 
+/// Settings the new profile doesn't have yet start from their defaults
+fn reset_config<T>(mut config: ResMut<T>)
+where
+    T: Resource + Default,
+{
+    *config = T::default();
+}
+
+/// File format of a [`GameSetting`]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SettingFormat {
+    #[default]
+    Ron,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl SettingFormat {
+    pub fn serialize<T>(&self, setting: &T) -> Result<Vec<u8>, SettingError>
+    where
+        T: Serialize,
+    {
+        let text = match self {
+            Self::Ron => ron::ser::to_string_pretty(setting, PrettyConfig::default())?,
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::to_string_pretty(setting).map_err(|e| SettingError::Format(e.into()))?,
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::to_string_pretty(setting).map_err(|e| SettingError::Format(e.into()))?,
+        };
+        Ok(text.into_bytes())
+    }
+
+    pub fn deserialize<T>(&self, data: &[u8]) -> Result<T, SettingError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            Self::Ron => Ok(ron::de::from_bytes(data)?),
+            #[cfg(feature = "toml")]
+            Self::Toml => std::str::from_utf8(data)
+                .map_err(|e| SettingError::Format(e.into()))
+                .and_then(|text| toml::from_str(text).map_err(|e| SettingError::Format(e.into()))),
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_slice(data).map_err(|e| SettingError::Format(e.into())),
+        }
+    }
+
+    /// `data` with a line holding `version`, a comment or a `$version` field depending on the format
+    pub(crate) fn add_version(&self, data: Vec<u8>, version: u32) -> Vec<u8> {
+        if version == 0 {
+            return data;
+        }
+        match self {
+            Self::Ron => [format!("// version: {}\n", version).as_bytes(), &data].concat(),
+            #[cfg(feature = "toml")]
+            Self::Toml => [format!("# version: {}\n", version).as_bytes(), &data].concat(),
+            #[cfg(feature = "json")]
+            Self::Json => match data.strip_prefix(b"{") {
+                Some(b"}") => format!("{{\n  \"$version\": {}\n}}", version).into_bytes(),
+                Some(fields) => [format!("{{\n  \"$version\": {},", version).as_bytes(), fields].concat(),
+                None => data,
+            },
+        }
+    }
+
+    /// Version written by [`Self::add_version`], 0 if there is none, and the data to deserialize
+    pub(crate) fn split_version<'a>(&self, data: &'a [u8]) -> (u32, Cow<'a, [u8]>) {
+        let comment = match self {
+            Self::Ron => "// version: ",
+            #[cfg(feature = "toml")]
+            Self::Toml => "# version: ",
+            #[cfg(feature = "json")]
+            Self::Json => {
+                let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_slice(data) else {
+                    return (0, Cow::Borrowed(data));
+                };
+                let version = fields.remove("$version").and_then(|version| version.as_u64());
+                return match version.and_then(|version| u32::try_from(version).ok()) {
+                    Some(version) => (
+                        version,
+                        Cow::Owned(serde_json::to_vec(&fields).expect("JSON object can be serialized")),
+                    ),
+                    None => (0, Cow::Borrowed(data)),
+                };
+            }
+        };
+        let version = std::str::from_utf8(data)
+            .ok()
+            .and_then(|text| text.lines().next())
+            .and_then(|line| line.strip_prefix(comment))
+            .and_then(|version| version.trim().parse().ok())
+            .unwrap_or(0);
+        (version, Cow::Borrowed(data))
+    }
+
+    /// Deserialize the fields of `T` found in `data` over `T::default()`, ignoring fields `T` doesn't have.
+    /// Every field must implement `Reflect`, and nested structs are merged the same way.
+    pub fn deserialize_lenient<T>(&self, data: &[u8]) -> Result<T, SettingError>
+    where
+        T: Default + FromReflect + GetTypeRegistration,
+    {
+        let value = self.deserialize_partial::<T>(data)?;
+        T::from_reflect(fill_missing(value.as_ref(), &T::default()).as_ref()).ok_or_else(|| {
+            SettingError::Format(
+                format!(
+                    "Setting doesn't fit {}",
+                    T::get_type_registration().type_info().type_path()
+                )
+                .into(),
+            )
+        })
+    }
+
+    /// Fields of `T` found in `data`, ignoring fields `T` doesn't have. Structs, nested ones included,
+    /// are returned as a [`DynamicStruct`] of the fields found.
+    pub(crate) fn deserialize_partial<T>(&self, data: &[u8]) -> Result<Box<dyn PartialReflect>, SettingError>
+    where
+        T: GetTypeRegistration,
+    {
+        let mut registry = TypeRegistry::new();
+        registry.register::<T>();
+        let registration = registry
+            .get(TypeId::of::<T>())
+            .expect("setting type was just registered");
+        let mut processor = LenientProcessor;
+        let seed = TypedReflectDeserializer::with_processor(registration, &registry, &mut processor);
+        let value = match self {
+            Self::Ron => ron::Options::default().from_bytes_seed(data, seed)?,
+            #[cfg(feature = "toml")]
+            Self::Toml => std::str::from_utf8(data)
+                .map_err(|e| SettingError::Format(e.into()))
+                .and_then(|text| toml::Deserializer::parse(text).map_err(|e| SettingError::Format(e.into())))
+                .and_then(|deserializer| {
+                    seed.deserialize(deserializer)
+                        .map_err(|e| SettingError::Format(e.into()))
+                })?,
+            #[cfg(feature = "json")]
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(data);
+                let value = seed
+                    .deserialize(&mut deserializer)
+                    .map_err(|e| SettingError::Format(e.into()))?;
+                deserializer.end().map_err(|e| SettingError::Format(e.into()))?;
+                value
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Deserializes structs from the fields they have, skipping unknown fields
+struct LenientProcessor;
+
+impl ReflectDeserializerProcessor for LenientProcessor {
+    fn try_deserialize<'de, D>(
+        &mut self,
+        registration: &TypeRegistration,
+        registry: &TypeRegistry,
+        deserializer: D,
+    ) -> Result<Result<Box<dyn PartialReflect>, D>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let TypeInfo::Struct(info) = registration.type_info() else {
+            return Ok(Err(deserializer));
+        };
+        let name = info.type_path_table().ident().unwrap_or_default();
+        let fields = deserializer.deserialize_struct(name, info.field_names(), LenientStruct { info, registry })?;
+        Ok(Ok(Box::new(fields)))
+    }
+}
+
+struct LenientStruct<'a> {
+    info: &'static StructInfo,
+    registry: &'a TypeRegistry,
+}
+
+impl<'de> Visitor<'de> for LenientStruct<'_> {
+    type Value = DynamicStruct;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "struct {}", self.info.type_path())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = DynamicStruct::default();
+        while let Some(FieldName(name)) = map.next_key()? {
+            let Some(field) = self.info.field(&name) else {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            };
+            let registration = self
+                .registry
+                .get(field.type_id())
+                .ok_or_else(|| A::Error::custom(format_args!("{} is not registered", field.type_path())))?;
+            let mut processor = LenientProcessor;
+            let value = map.next_value_seed(TypedReflectDeserializer::with_processor(
+                registration,
+                self.registry,
+                &mut processor,
+            ))?;
+            fields.insert_boxed(&name, value);
+        }
+        Ok(fields)
+    }
+}
+
+struct FieldName(String);
+
+impl<'de> Deserialize<'de> for FieldName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// `value` with the struct fields it lacks taken from `default`
+fn fill_missing(value: &dyn PartialReflect, default: &dyn PartialReflect) -> Box<dyn PartialReflect> {
+    let (ReflectRef::Struct(fields), ReflectRef::Struct(default)) = (value.reflect_ref(), default.reflect_ref()) else {
+        return value.to_dynamic();
+    };
+    let mut merged = DynamicStruct::default();
+    merged.set_represented_type(default.get_represented_type_info());
+    for (i, default_field) in default.iter_fields().enumerate() {
+        let name = default.name_at(i).expect("field index is in range");
+        let field = match fields.field(name) {
+            Some(field) => fill_missing(field, default_field),
+            None => default_field.to_dynamic(),
+        };
+        merged.insert_boxed(name, field);
+    }
+    Box::new(merged)
+}
+
+/// `config_path` inside the directory of `profile`, if setting `T` is [`GameSetting::PER_PROFILE`]
+fn in_profile<T>(config_path: PathBuf, profile: &CurrentProfile) -> PathBuf
+where
+    T: GameSetting,
+{
+    if !T::PER_PROFILE || profile.is_default() {
+        return config_path;
+    }
+    match (config_path.parent(), config_path.file_name()) {
+        (Some(parent), Some(file_name)) => parent.join(profile.dir()).join(file_name),
+        _ => config_path,
+    }
+}
+
+/// Can be implemented with `#[derive(GameSetting)]` and the `derive` feature
 pub trait GameSetting: Serialize + for<'de> Deserialize<'de> {
     const DEFAULT_CONF: &'static str = "game_setting.conf";
+    /// Subdirectory of the settings directory holding [`Self::DEFAULT_CONF`]
+    const DIR: &'static str = "";
+    const FORMAT: SettingFormat = SettingFormat::Ron;
+    /// Keep a separate file for each [`CurrentProfile`]
+    const PER_PROFILE: bool = true;
+    /// Minimum time between two writes of the file, changes in between are written once it has elapsed
+    /// or on [`FlushGameSettings`]
+    const DEBOUNCE: Duration = Duration::ZERO;
+    /// Files next to [`Self::DEFAULT_CONF`] each holding a part of the setting, written by [`Self::encode_section`]
+    /// instead of a single file. A missing file leaves its part unchanged, so deleting it resets that part.
+    /// [`Self::VERSION`] is not written in sections.
+    const SECTIONS: &'static [&'static str] = &[];
+    /// Written at the top of the file if not 0. Files of another version are loaded through [`Self::migrate`],
+    /// files written before a version was set have version 0.
+    const VERSION: u32 = 0;
+    /// Hints of options menus for the fields, read by [`setting_fields`](crate::options::setting_fields)
+    const UI: &'static [SettingUi] = &[];
+
+    /// Read the content of the file. Override it with [`SettingFormat::deserialize_lenient`], or use
+    /// `#[setting(lenient)]`, to keep the values of files written before fields were added or removed.
+    fn parse(data: &[u8]) -> Result<Self, SettingError> {
+        Self::FORMAT.deserialize(data)
+    }
 
-    fn config_path() -> PathBuf {
-        if cfg!(target_os = "android") {
-            // It should be /data/data/com.yourapp.package/setting.txt
-            PathBuf::from(Self::DEFAULT_CONF)
-        } else if let Some(data_local_dir) = dirs::data_local_dir() {
-            data_local_dir.join(Self::DEFAULT_CONF)
+    /// Read `data` of a file written with `version`, e.g. with [`SettingFormat::deserialize`] into an older type
+    /// then converted
+    fn migrate(version: u32, _data: &[u8]) -> Result<Self, SettingError> {
+        Err(SettingError::VersionMismatch {
+            saved: version,
+            current: Self::VERSION,
+        })
+    }
+
+    /// Called once loaded by [`GameSettingSupportPlugin`], to clamp or reset out of range values.
+    /// The issues returned are sent in [`GameSettingInvalid`].
+    fn validate(&mut self) -> Result<(), Vec<SettingIssue>> {
+        Ok(())
+    }
+
+    /// Content of the file `section` of [`Self::SECTIONS`]
+    fn encode_section(&self, section: &str) -> Result<Vec<u8>, SettingError> {
+        Err(SettingError::Format(
+            format!("Unknown setting section {}", section).into(),
+        ))
+    }
+
+    /// Read the content of the file `section` of [`Self::SECTIONS`] into `self`
+    fn decode_section(&mut self, section: &str, _data: &[u8]) -> Result<(), SettingError> {
+        Err(SettingError::Format(
+            format!("Unknown setting section {}", section).into(),
+        ))
+    }
+
+    /// Content of the file, with [`Self::VERSION`]
+    fn encode(&self) -> Result<Vec<u8>, SettingError> {
+        Ok(Self::FORMAT.add_version(Self::FORMAT.serialize(self)?, Self::VERSION))
+    }
+
+    /// Read a file written by [`Self::encode`], through [`Self::migrate`] if it has another version
+    fn decode(data: &[u8]) -> Result<Self, SettingError> {
+        let (version, data) = Self::FORMAT.split_version(data);
+        if version == Self::VERSION {
+            Self::parse(&data)
         } else {
-            PathBuf::from(Self::DEFAULT_CONF)
+            Self::migrate(version, &data)
         }
     }
 
-    fn load(&mut self) -> anyhow::Result<()> {
-        self.load_from(&Self::config_path())
+    /// Inside [`SaveDirs::config_dir`], next to the executable in portable mode
+    fn config_path(dirs: &SaveDirs) -> PathBuf {
+        dirs.config_dir().join(Self::DIR).join(Self::DEFAULT_CONF)
     }
 
-    fn load_from(&mut self, config_path: &PathBuf) -> anyhow::Result<()> {
-        let file = File::open(config_path)?;
-        *self = from_reader(file)?;
-        Ok(())
+    /// [`Self::config_path`] inside the directory of `profile`
+    fn profile_config_path(dirs: &SaveDirs, profile: &CurrentProfile) -> PathBuf {
+        in_profile::<Self>(Self::config_path(dirs), profile)
     }
 
-    fn save(&self) -> anyhow::Result<()> {
-        self.save_to(Self::config_path())
+    fn load(&mut self, dirs: &SaveDirs) -> Result<(), SettingError> {
+        self.load_from(&Self::config_path(dirs))
     }
 
-    fn save_to(&self, config_path: PathBuf) -> anyhow::Result<()> {
-        let pretty = PrettyConfig::default();
-        let ron_str = to_string_pretty(self, pretty)?;
+    fn load_from(&mut self, config_path: &Path) -> Result<(), SettingError> {
+        self.load_with(&FsBackend, config_path)
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
+    /// Read the file at `config_path`, or the files of [`Self::SECTIONS`] next to it
+    fn load_with(&mut self, backend: &dyn SaveBackend, config_path: &Path) -> Result<(), SettingError> {
+        if Self::SECTIONS.is_empty() {
+            *self = Self::decode(&read_file(backend, config_path)?)?;
+            return Ok(());
+        }
+        let mut found = false;
+        for section in Self::SECTIONS {
+            match read_file(backend, &config_path.with_file_name(section)) {
+                Ok(data) => {
+                    self.decode_section(section, &data)?;
+                    found = true;
+                }
+                Err(SettingError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if found {
+            Ok(())
+        } else {
+            Err(SettingError::NotFound(config_path.to_path_buf()))
+        }
+    }
+
+    fn save(&self, dirs: &SaveDirs) -> Result<(), SettingError> {
+        self.save_to(Self::config_path(dirs))
+    }
+
+    fn save_to(&self, config_path: PathBuf) -> Result<(), SettingError> {
+        self.save_with(Arc::new(FsBackend), config_path)
+    }
+
+    /// Encode on the caller thread and write the file at `config_path`, or the files of [`Self::SECTIONS`] next to
+    /// it, on the `IoTaskPool`. A failed write is only logged, see [`Self::save_with_blocking`] to handle it.
+    fn save_with(&self, backend: Arc<dyn SaveBackend>, config_path: PathBuf) -> Result<(), SettingError> {
+        let files = encode_files(self, &config_path)?;
         IoTaskPool::get()
             .spawn(async move {
-                if let Some(parent_dir) = config_path.parent() {
-                    std::fs::create_dir_all(parent_dir)?;
+                for (path, data) in files {
+                    if let Err(_e) = backend.write(&path, &data) {
+                        #[cfg(feature = "log")]
+                        error!("Failed to write {}: {}", path.display(), _e);
+                    }
                 }
-                let mut file = File::create(config_path)?;
-                file.write_all(ron_str.as_bytes()).map_err(|e| anyhow::anyhow!(e))
             })
             .detach();
+        Ok(())
+    }
 
+    /// Write the file at `config_path`, or the files of [`Self::SECTIONS`] next to it, before returning
+    fn save_with_blocking(&self, backend: &dyn SaveBackend, config_path: &Path) -> Result<(), SettingError> {
+        for (path, data) in encode_files(self, config_path)? {
+            backend.write(&path, &data)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+    use bevy::prelude::{
+        Messages,
+        MinimalPlugins,
+        Reflect,
+    };
+
+    #[derive(Resource, Serialize, Deserialize, Reflect, Clone, Debug, PartialEq)]
+    struct Volume {
+        master: f32,
+        muted: bool,
+    }
+
+    impl Default for Volume {
+        fn default() -> Self {
+            Self {
+                master: 1.0,
+                muted: false,
+            }
+        }
+    }
+
+    impl GameSetting for Volume {
+        const VERSION: u32 = 2;
+
+        fn migrate(version: u32, data: &[u8]) -> Result<Self, SettingError> {
+            #[derive(Deserialize)]
+            struct VolumeV1 {
+                volume: f32,
+            }
+
+            match version {
+                1 => Ok(Self {
+                    master: Self::FORMAT.deserialize::<VolumeV1>(data)?.volume,
+                    ..Self::default()
+                }),
+                _ => Err(SettingError::VersionMismatch {
+                    saved: version,
+                    current: Self::VERSION,
+                }),
+            }
+        }
+    }
+
+    #[derive(Resource, Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+    struct Split {
+        audio: u32,
+        video: u32,
+    }
+
+    impl GameSetting for Split {
+        const SECTIONS: &'static [&'static str] = &["audio.ron", "video.ron"];
+
+        fn encode_section(&self, section: &str) -> Result<Vec<u8>, SettingError> {
+            match section {
+                "audio.ron" => Self::FORMAT.serialize(&self.audio),
+                _ => Self::FORMAT.serialize(&self.video),
+            }
+        }
+
+        fn decode_section(&mut self, section: &str, data: &[u8]) -> Result<(), SettingError> {
+            match section {
+                "audio.ron" => self.audio = Self::FORMAT.deserialize(data)?,
+                _ => self.video = Self::FORMAT.deserialize(data)?,
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn version_line_round_trip() {
+        let volume = Volume {
+            master: 0.5,
+            muted: true,
+        };
+        let data = volume.encode().unwrap();
+        assert!(data.starts_with(b"// version: 2\n"));
+        assert_eq!(SettingFormat::Ron.split_version(&data).0, 2);
+        assert_eq!(Volume::decode(&data).unwrap(), volume);
+
+        let unversioned = SettingFormat::Ron.add_version(b"()".to_vec(), 0);
+        assert_eq!(unversioned, b"()");
+        assert_eq!(
+            SettingFormat::Ron.split_version(&unversioned),
+            (0, Cow::Borrowed(b"()".as_slice()))
+        );
+    }
+
+    #[test]
+    fn older_version_is_migrated() {
+        let volume = Volume::decode(b"// version: 1\n(volume: 0.25)").unwrap();
+        assert_eq!(volume.master, 0.25);
+        assert!(matches!(
+            Volume::decode(b"(master: 0.25, muted: false)"),
+            Err(SettingError::VersionMismatch { saved: 0, current: 2 })
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_version_field() {
+        let data = SettingFormat::Json.add_version(b"{\n  \"muted\": true\n}".to_vec(), 3);
+        let (version, data) = SettingFormat::Json.split_version(&data);
+        assert_eq!(version, 3);
+        let fields: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(fields, serde_json::json!({ "muted": true }));
+    }
+
+    #[test]
+    fn lenient_keeps_the_known_fields() {
+        let volume: Volume = SettingFormat::Ron
+            .deserialize_lenient(b"(muted: true, removed: 3)")
+            .unwrap();
+        assert_eq!(
+            volume,
+            Volume {
+                master: 1.0,
+                muted: true
+            }
+        );
+        assert!(SettingFormat::Ron.deserialize_lenient::<Volume>(b"(muted: 3)").is_err());
+    }
+
+    #[test]
+    fn sections_are_separate_files() {
+        let backend = MemoryBackend::default();
+        let path = Path::new("settings").join(Split::DEFAULT_CONF);
+        let split = Split { audio: 1, video: 2 };
+        split.save_with_blocking(&backend, &path).unwrap();
+        assert_eq!(backend.paths().len(), 2);
+
+        let mut loaded = Split::default();
+        loaded.load_with(&backend, &path).unwrap();
+        assert_eq!(loaded, split);
+
+        // A missing section keeps its value
+        backend.remove(&path.with_file_name("video.ron")).unwrap();
+        let mut loaded = Split { audio: 0, video: 9 };
+        loaded.load_with(&backend, &path).unwrap();
+        assert_eq!(loaded, Split { audio: 1, video: 9 });
+
+        backend.remove(&path.with_file_name("audio.ron")).unwrap();
+        assert!(matches!(
+            Split::default().load_with(&backend, &path),
+            Err(SettingError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn protected_setting_round_trip() {
+        let backend = MemoryBackend::default();
+        let path = Path::new("settings").join(Volume::DEFAULT_CONF);
+        let file = SettingFile::<Volume> {
+            key: Some(SecretKey::from("key")),
+            ..SettingFile::default()
+        };
+        let volume = Volume {
+            master: 0.5,
+            muted: true,
+        };
+        let sealed = seal_setting(volume.encode().unwrap(), file.key.as_ref()).unwrap();
+        assert!(SaveHeader::decode(&sealed).is_some());
+        backend.write(&path, &sealed).unwrap();
+
+        let mut loaded = Volume::default();
+        file.load_with(&mut loaded, &backend, &path).unwrap();
+        assert_eq!(loaded, volume);
+
+        // A plain file can't replace it
+        backend.write(&path, &volume.encode().unwrap()).unwrap();
+        assert!(matches!(
+            file.load_with(&mut Volume::default(), &backend, &path),
+            Err(SettingError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn unchanged_protected_setting_is_not_written() {
+        let backend = MemoryBackend::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SaveStorage(Arc::new(backend.clone())))
+            .init_resource::<SaveDirs>()
+            .add_plugins(GameSettingSupportPlugin::<Volume>::default().with_key(SecretKey::from("key")));
+
+        let path = Volume::config_path(app.world().resource::<SaveDirs>());
+        let sealed = seal_setting(Volume::default().encode().unwrap(), Some(&SecretKey::from("key"))).unwrap();
+        backend.write(&path, &sealed).unwrap();
+        app.update();
+        app.world_mut().write_message(GameSettingChanged);
+        app.world_mut().write_message(FlushGameSettings);
+        app.update();
+
+        assert_eq!(backend.read(&path).unwrap(), sealed);
+        assert!(app
+            .world()
+            .resource::<Messages<GameSettingSaved<Volume>>>()
+            .is_empty());
+    }
+
+    #[test]
+    fn corrupted_file_is_restored_from_its_backup() {
+        let backend = MemoryBackend::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SaveStorage(Arc::new(backend.clone())))
+            .init_resource::<SaveDirs>()
+            .add_plugins(GameSettingSupportPlugin::<Volume>::default());
+
+        let path = Volume::config_path(app.world().resource::<SaveDirs>());
+        let backup = Volume {
+            master: 0.25,
+            muted: true,
+        }
+        .encode()
+        .unwrap();
+        backend.write(&path, b"// version: 2\n(master: ").unwrap();
+        backend.write(&backup_path(&path), &backup).unwrap();
+        app.update();
+
+        assert_eq!(app.world().resource::<Volume>().master, 0.25);
+        assert!(!app
+            .world()
+            .resource::<Messages<GameSettingRecovered<Volume>>>()
+            .is_empty());
+        assert_eq!(backend.read(&path).unwrap(), backup);
+    }
+}
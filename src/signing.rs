@@ -0,0 +1,114 @@
+//! Signatures of save files, so that a server can tell whether a save uploaded for a leaderboard was edited.
+//! The HMAC-SHA256 of the header and the data after it is stored in the [`SaveHeader`], computed with a key kept
+//! apart from the encryption key.
+use crate::cipher::{
+    SaveHeader,
+    SecretKey,
+    SIGNATURE_LEN,
+};
+use crate::error::SaveError;
+use bevy::prelude::{
+    Deref,
+    Resource,
+};
+use hmac::{
+    Hmac,
+    Mac,
+};
+use sha2::Sha256;
+
+/// Key signing the slot saves, set with [`EncryptSavePlugin::with_signing_key`](crate::save::EncryptSavePlugin::with_signing_key).
+/// Loading a save fails with [`SaveError::InvalidSignature`] if its signature doesn't match.
+#[derive(Resource, Clone, Deref)]
+pub struct SigningKey(pub SecretKey);
+
+/// HMAC of `header` without its signature, so that its fields can't be edited either, followed by `data`
+fn mac(key: &SecretKey, header: &SaveHeader, data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any size");
+    let header = SaveHeader {
+        signature: None,
+        ..header.clone()
+    };
+    mac.update(&header.encode());
+    mac.update(data);
+    mac
+}
+
+/// `file` with the signature of its data added to its header. Files without a header are given one.
+pub fn sign(file: &[u8], key: &SecretKey) -> Vec<u8> {
+    let (mut header, data) = SaveHeader::decode(file).unwrap_or((SaveHeader::default(), file));
+    let signature: [u8; SIGNATURE_LEN] = mac(key, &header, data).finalize().into_bytes().into();
+    header.signature = Some(signature);
+    [header.encode(), data.to_vec()].concat()
+}
+
+/// Check that `file` was signed with `key`, without decrypting it, e.g. on the server receiving the save
+pub fn verify_signature(file: &[u8], key: &SecretKey) -> Result<(), SaveError> {
+    let (header, data) = SaveHeader::decode(file).ok_or(SaveError::InvalidSignature)?;
+    let signature = header.signature.ok_or(SaveError::InvalidSignature)?;
+    mac(key, &header, data)
+        .verify_slice(&signature)
+        .map_err(|_| SaveError::InvalidSignature)
+}
+
+/// Sign `file` if there is a key
+pub(crate) fn sign_with(key: Option<&SigningKey>, file: Vec<u8>) -> Vec<u8> {
+    match key {
+        Some(key) => sign(&file, key),
+        None => file,
+    }
+}
+
+/// Verify `file` if there is a key. Streamed saves are written before their signature is known, they are refused
+/// when there is one.
+pub(crate) fn verify_with(key: Option<&SigningKey>, file: &[u8]) -> Result<(), SaveError> {
+    match key {
+        Some(key) => verify_signature(file, key),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed() -> (Vec<u8>, SecretKey) {
+        let header = SaveHeader {
+            cipher: 1,
+            key_id: Some(7),
+            game_version: Some("1.0".to_string()),
+            ..SaveHeader::default()
+        };
+        let key = SecretKey::from("signing key");
+        (sign(&[header.encode(), b"data".to_vec()].concat(), &key), key)
+    }
+
+    #[test]
+    fn signed_file_verifies() {
+        let (file, key) = signed();
+        assert!(verify_signature(&file, &key).is_ok());
+        assert!(verify_signature(&file, &SecretKey::from("other key")).is_err());
+    }
+
+    #[test]
+    fn edited_header_fails_verification() {
+        let (file, key) = signed();
+        let (mut header, data) = SaveHeader::decode(&file).unwrap();
+        header.game_version = Some("2.0".to_string());
+        let edited = [header.encode(), data.to_vec()].concat();
+        assert!(matches!(
+            verify_signature(&edited, &key),
+            Err(SaveError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn edited_data_fails_verification() {
+        let (mut file, key) = signed();
+        *file.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            verify_signature(&file, &key),
+            Err(SaveError::InvalidSignature)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//! Import of the snapshot files written by `bevy_save`, to move a shipped game to this crate without losing
+//! the saves of its players. Snapshots share the layout of scenes, they are read as a [`DynamicScene`].
+use crate::error::SaveError;
+use crate::save::{
+    LoadLimits,
+    SaveGame,
+    SaveOptions,
+    SaveSet,
+};
+use bevy::app::App;
+use bevy::ecs::system::SystemState;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    on_message,
+    AppTypeRegistry,
+    Entity,
+    IntoScheduleConfigs,
+    Message,
+    MessageReader,
+    Plugin,
+    Resource,
+    Update,
+    World,
+};
+use bevy::reflect::TypeRegistry;
+use bevy::scene::serde::SceneDeserializer;
+use bevy::scene::DynamicScene;
+use serde::de::DeserializeSeed;
+use std::fs;
+use std::path::PathBuf;
+
+/// Copy the content of a snapshot into the saved resources, e.g. by converting the resources it has
+pub type SnapshotMapping = fn(&DynamicScene, &mut World) -> Result<(), SaveError>;
+
+/// Import snapshots written with the `JSONFormat` of `bevy_save` on [`ImportSnapshot`]. Each one is converted by
+/// the mapping then saved to a new slot, whose id is sent in [`GameSaved`](crate::save::GameSaved).
+pub struct SnapshotImportPlugin {
+    mapping: SnapshotMapping,
+}
+
+impl SnapshotImportPlugin {
+    pub fn new(mapping: SnapshotMapping) -> Self {
+        Self { mapping }
+    }
+}
+
+impl Plugin for SnapshotImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SnapshotImport(self.mapping))
+            .add_message::<ImportSnapshot>()
+            .add_message::<SnapshotImported>()
+            .add_message::<SnapshotImportFailed>()
+            .add_message::<SaveGame>()
+            .add_systems(
+                Update,
+                import_snapshots
+                    .before(SaveSet::Capture)
+                    .run_if(on_message::<ImportSnapshot>),
+            );
+    }
+}
+
+/// Import the snapshot at `src` as a new slot
+#[derive(Message)]
+pub struct ImportSnapshot {
+    pub src: PathBuf,
+}
+
+/// The snapshot at `src` was converted, its slot is written next
+#[derive(Message)]
+pub struct SnapshotImported {
+    pub src: PathBuf,
+}
+
+#[derive(Message, Debug)]
+pub struct SnapshotImportFailed {
+    pub src: PathBuf,
+    pub error: SaveError,
+}
+
+#[derive(Resource)]
+struct SnapshotImport(SnapshotMapping);
+
+/// Entities and resources of a `bevy_save` snapshot in JSON. Rollbacks are ignored.
+pub fn read_snapshot(data: &[u8], type_registry: &TypeRegistry) -> Result<DynamicScene, SaveError> {
+    let mut snapshot: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(data).map_err(|e| SaveError::Deserialize(e.into()))?;
+    snapshot.retain(|field, _| field == "entities" || field == "resources");
+    // Ids written by older versions of Bevy may be invalid now, they only tell the entities apart
+    if let Some(serde_json::Value::Object(entities)) = snapshot.get_mut("entities") {
+        let valid = |id: &String| id.parse().ok().and_then(Entity::try_from_bits).is_some();
+        if !entities.keys().all(valid) {
+            *entities = std::mem::take(entities)
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, (_, entity))| {
+                    Some((Entity::from_raw_u32(index as u32)?.to_bits().to_string(), entity))
+                })
+                .collect();
+        }
+    }
+    SceneDeserializer { type_registry }
+        .deserialize(serde_json::Value::Object(snapshot))
+        .map_err(|e| SaveError::Deserialize(e.into()))
+}
+
+fn import_snapshots(world: &mut World, imports: &mut SystemState<MessageReader<ImportSnapshot>>) {
+    let sources: Vec<PathBuf> = imports.get_mut(world).read().map(|import| import.src.clone()).collect();
+    let mapping = world.resource::<SnapshotImport>().0;
+    let max_file_size = world
+        .get_resource::<SaveOptions>()
+        .map_or(u64::MAX, |options| options.load_limits.max_file_size);
+    for src in sources {
+        let result = fs::metadata(&src)
+            .map_err(SaveError::from)
+            .and_then(|metadata| LoadLimits::check(metadata.len(), max_file_size))
+            .and_then(|_| Ok(fs::read(&src)?))
+            .and_then(|data| read_snapshot(&data, &world.resource::<AppTypeRegistry>().read()))
+            .and_then(|snapshot| mapping(&snapshot, world));
+        match result {
+            Ok(()) => {
+                world.write_message(SaveGame::new(0));
+                world.write_message(SnapshotImported { src });
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to import snapshot {}: {}", src.display(), error);
+                world.write_message(SnapshotImportFailed { src, error });
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::backend::SaveBackend;
+use crate::paths::{
+    update_save_dirs,
+    SaveDirs,
+};
+use crate::save::SaveConfig;
+use crate::setting::load_config;
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    resource_changed,
+    First,
+    IntoScheduleConfigs,
+    Message,
+    MessageWriter,
+    Plugin,
+    PreStartup,
+    Res,
+    Resource,
+    Startup,
+};
+use std::io;
+use std::io::{
+    ErrorKind,
+    Read,
+    Write,
+};
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    RwLock,
+};
+use steamworks::{
+    Client,
+    SteamFile,
+};
+
+/// Remote file written this much later than recorded in [`SaveConfig`] is considered a conflict.
+/// Writes are asynchronous, so the file timestamp is always a bit behind `saved_at`.
+const CONFLICT_TOLERANCE_SECS: i64 = 10;
+
+/// Store saves and settings in Steam Cloud through the Remote Storage API.
+///
+/// Files are stored under their path relative to [`SaveDirs::data_dir`] or [`SaveDirs::config_dir`], so the files
+/// of each profile stay apart. Files written by earlier versions under their file name only are still read.
+/// Steam callbacks still have to be run by the game, e.g. by `bevy_steamworks`.
+#[derive(Clone)]
+pub struct SteamBackend {
+    client: Client,
+    quota: Option<u64>,
+    /// Directories absolute paths are made relative to, the most nested one first. Shared by the clones of the
+    /// backend, and set from [`SaveDirs`] by `EncryptSavePlugin::with_steam_cloud`.
+    roots: Arc<RwLock<Vec<PathBuf>>>,
+}
+
+impl SteamBackend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            quota: None,
+            roots: Arc::default(),
+        }
+    }
+
+    /// Refuse writes which would take the app above `bytes`, the per-user quota configured in Steamworks
+    pub fn with_quota(mut self, bytes: u64) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
+
+    /// Last time `path` was written to Steam Cloud, in unix seconds
+    pub fn timestamp(&self, path: &Path) -> Option<i64> {
+        let file = self.remote_file(path).ok()?;
+        file.exists().then(|| file.timestamp())
+    }
+
+    /// File of `path` in Steam Cloud, the one named after its file name only if that's where it was written
+    fn remote_file(&self, path: &Path) -> io::Result<SteamFile> {
+        let remote_storage = self.client.remote_storage();
+        let file = remote_storage.file(&self.remote_name(path)?);
+        if file.exists() {
+            return Ok(file);
+        }
+        Ok(file_name(path)
+            .map(|name| remote_storage.file(&name))
+            .filter(SteamFile::exists)
+            .unwrap_or(file))
+    }
+
+    /// `path` relative to the data or settings directory, with `/` separators
+    fn remote_name(&self, path: &Path) -> io::Result<String> {
+        let invalid = || {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is outside of the data and settings directories", path.display()),
+            )
+        };
+        let relative = if path.is_absolute() {
+            let roots = self.roots.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            roots
+                .iter()
+                .find_map(|root| path.strip_prefix(root).ok())
+                .ok_or_else(invalid)?
+                .to_path_buf()
+        } else {
+            path.to_path_buf()
+        };
+        let parts = relative
+            .components()
+            .map(|component| match component {
+                Component::Normal(part) => Ok(part.to_string_lossy()),
+                _ => Err(invalid()),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(parts.join("/"))
+    }
+
+    fn set_roots(&self, dirs: &SaveDirs) {
+        let mut roots = vec![dirs.data_dir().to_path_buf(), dirs.config_dir().to_path_buf()];
+        // The most nested one first, when one is inside the other
+        roots.sort_by_key(|root| std::cmp::Reverse(root.components().count()));
+        *self.roots.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = roots;
+    }
+
+    /// Bytes used by every file of the app in Steam Cloud, except `skip`
+    fn used_bytes(&self, skip: &str) -> u64 {
+        self.client
+            .remote_storage()
+            .files()
+            .iter()
+            .filter(|info| info.name != skip)
+            .map(|info| info.size)
+            .sum()
+    }
+}
+
+impl SaveBackend for SteamBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let file = self.remote_file(path)?;
+        if !file.exists() {
+            return Err(ErrorKind::NotFound.into());
+        }
+
+        let mut data = Vec::new();
+        file.read().read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let name = self.remote_name(path)?;
+        if let Some(quota) = self.quota {
+            if self.used_bytes(&name) + data.len() as u64 > quota {
+                return Err(io::Error::new(
+                    ErrorKind::QuotaExceeded,
+                    format!("Steam Cloud quota of {} bytes exceeded", quota),
+                ));
+            }
+        }
+
+        // The file is committed when the writer is dropped
+        self.client.remote_storage().file(&name).write().write_all(data)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if self.remote_file(path)?.delete() {
+            Ok(())
+        } else {
+            Err(ErrorKind::NotFound.into())
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.remote_file(path).is_ok_and(|file| file.exists())
+    }
+
+    /// Files directly in `dir`
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let prefix = self.remote_name(dir)?;
+        Ok(self
+            .client
+            .remote_storage()
+            .files()
+            .into_iter()
+            .filter_map(|info| {
+                let name = match prefix.as_str() {
+                    "" => info.name.as_str(),
+                    prefix => info.name.strip_prefix(prefix)?.strip_prefix('/')?,
+                };
+                (!name.contains('/')).then(|| dir.join(name))
+            })
+            .collect())
+    }
+}
+
+/// A save file in Steam Cloud was written after the slot was last saved on this machine,
+/// usually by another machine whose settings were not synced
+#[derive(Message, Debug)]
+pub struct SteamCloudConflict {
+    pub slot: u32,
+    /// When the slot was saved according to [`SaveConfig`], in unix seconds
+    pub saved_at: u64,
+    /// When the file was last written to Steam Cloud, in unix seconds
+    pub remote_saved_at: i64,
+}
+
+#[derive(Resource)]
+struct SteamCloud(SteamBackend);
+
+pub(crate) struct SteamCloudPlugin {
+    pub backend: SteamBackend,
+}
+
+impl Plugin for SteamCloudPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SteamCloud(self.backend.clone()))
+            .add_message::<SteamCloudConflict>()
+            .add_systems(PreStartup, set_roots.after(update_save_dirs))
+            .add_systems(First, set_roots.run_if(resource_changed::<SaveDirs>))
+            .add_systems(Startup, detect_conflicts.after(load_config::<SaveConfig>));
+    }
+}
+
+fn set_roots(cloud: Res<SteamCloud>, dirs: Res<SaveDirs>) {
+    cloud.0.set_roots(&dirs);
+}
+
+fn detect_conflicts(
+    cloud: Res<SteamCloud>,
+    save_config: Res<SaveConfig>,
+    mut conflicts: MessageWriter<SteamCloudConflict>,
+) {
+    for (id, slot) in save_config.slots() {
+        let Some(remote_saved_at) = cloud.0.timestamp(&slot.file) else {
+            continue;
+        };
+        if remote_saved_at > slot.saved_at as i64 + CONFLICT_TOLERANCE_SECS {
+            #[cfg(feature = "log")]
+            warn!("Save slot {} was modified in Steam Cloud since it was last saved", id);
+            conflicts.write(SteamCloudConflict {
+                slot: *id,
+                saved_at: slot.saved_at,
+                remote_saved_at,
+            });
+        }
+    }
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().map(|name| name.to_string_lossy().into_owned())
+}
@@ -0,0 +1,1061 @@
+use crate::backend::SaveStorage;
+use crate::delta::{
+    DeltaFlattener,
+    DeltaSaves,
+};
+use crate::error::SaveError;
+use crate::io::PendingWrites;
+use crate::paths::SaveDirs;
+use crate::profile::{
+    CurrentProfile,
+    ProfileSwitched,
+};
+use crate::save::{
+    merge_saves,
+    slot_cipher,
+    unix_now,
+    DeleteSave,
+    EncryptSave,
+    GameSaved,
+    SaveConfig,
+    SaveSet,
+    SaveSlot,
+};
+use crate::setting::{
+    load_config,
+    GameSettingChanged,
+};
+use bevy::app::App;
+#[cfg(feature = "log")]
+use bevy::prelude::{
+    error,
+    warn,
+};
+use bevy::prelude::{
+    on_message,
+    IntoScheduleConfigs,
+    Message,
+    MessageReader,
+    MessageWriter,
+    Plugin,
+    Res,
+    ResMut,
+    Resource,
+    Startup,
+    SystemCondition,
+    Update,
+    World,
+};
+use bevy::tasks::IoTaskPool;
+use ron::ser::PrettyConfig;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::collections::{
+    BTreeSet,
+    HashMap,
+    VecDeque,
+};
+use std::io;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+
+/// Remote file listing every synced slot of a profile
+const MANIFEST: &str = "manifest.ron";
+
+/// Remote storage mirrored by [`CloudSync`]. Files are addressed by their path relative to the save directory,
+/// with `/` separators.
+pub trait RemoteBackend: Send + Sync + 'static {
+    fn upload(&self, name: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Fails with [`ErrorKind::NotFound`] if there is no such file
+    fn download(&self, name: &str) -> io::Result<Vec<u8>>;
+
+    /// Names of every remote file, including the ones in subdirectories
+    fn list(&self) -> io::Result<Vec<String>>;
+
+    /// Files replaced by another one are left behind if not implemented
+    fn remove(&self, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How to settle a slot changed both locally and remotely since the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStrategy {
+    /// Keep the side saved last
+    #[default]
+    NewestWins,
+    /// Send [`SyncConflict`] and wait for [`ResolveSyncConflict`]
+    Manual,
+    /// Combine both sides with the resolver of
+    /// [`EncryptSavePlugin::with_sync_merge`](crate::save::EncryptSavePlugin::with_sync_merge) and upload the result.
+    /// Same as [`ConflictStrategy::NewestWins`] without one.
+    Merge,
+}
+
+/// Mirror the save directory to a [`RemoteBackend`], each profile with its own manifest.
+/// Syncs at startup, after every save, when the profile is switched and on [`SyncSaves`], on the `IoTaskPool` once
+/// the saves are written.
+/// Thumbnails are not synced. A deleted slot is deleted remotely, then on the other machines unless they changed it.
+#[derive(Resource, Clone)]
+pub struct CloudSync {
+    remote: Arc<dyn RemoteBackend>,
+    pub strategy: ConflictStrategy,
+}
+
+impl CloudSync {
+    pub fn new(remote: impl RemoteBackend, strategy: ConflictStrategy) -> Self {
+        Self {
+            remote: Arc::new(remote),
+            strategy,
+        }
+    }
+}
+
+#[derive(Message)]
+pub struct SyncSaves;
+
+/// Slots uploaded, downloaded or deleted by a sync
+#[derive(Message, Debug)]
+pub struct SavesSynced {
+    pub uploaded: Vec<u32>,
+    pub downloaded: Vec<u32>,
+    /// Local slots deleted because they were deleted on another machine
+    pub deleted: Vec<u32>,
+}
+
+#[derive(Message, Debug)]
+pub struct SyncFailed {
+    /// `None` when the remote manifest could not be read or written
+    pub slot: Option<u32>,
+    pub error: SaveError,
+}
+
+/// What is known about one side of a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncMeta {
+    pub revision: u64,
+    pub saved_at: u64,
+    pub playtime: Duration,
+}
+
+impl From<&SaveSlot> for SyncMeta {
+    fn from(slot: &SaveSlot) -> Self {
+        Self {
+            revision: slot.revision,
+            saved_at: slot.saved_at,
+            playtime: slot.playtime,
+        }
+    }
+}
+
+/// Slot changed both locally and remotely, with [`ConflictStrategy::Manual`]
+#[derive(Message, Debug)]
+pub struct SyncConflict {
+    pub slot: u32,
+    pub local_meta: SyncMeta,
+    pub remote_meta: SyncMeta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
+
+/// Settle a [`SyncConflict`] by keeping one side and overwriting the other
+#[derive(Message)]
+pub struct ResolveSyncConflict {
+    pub slot: u32,
+    pub keep: SyncSide,
+}
+
+/// Remote work of [`CloudSync`], run on the `IoTaskPool` one job at a time as every job rewrites the manifest
+#[derive(Resource)]
+struct SyncJobs {
+    queue: VecDeque<SyncJob>,
+    /// A job was spawned and its outcome hasn't been received yet
+    running: bool,
+    sender: Sender<SyncOutcome>,
+    receiver: Mutex<Receiver<SyncOutcome>>,
+}
+
+impl Default for SyncJobs {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            queue: VecDeque::new(),
+            running: false,
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+impl SyncJobs {
+    fn push(&mut self, job: SyncJob) {
+        if !self.queue.contains(&job) {
+            self.queue.push_back(job);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SyncJob {
+    /// Mirror every slot of the current profile
+    Sync,
+    /// Overwrite the other side of a slot with this one
+    Keep(u32, SyncSide),
+}
+
+/// Both sides of a slot, downloaded for [`merge_conflicts`]
+struct MergeInput {
+    id: u32,
+    /// Revision of the local slot when it was read
+    base: u64,
+    local: Vec<u8>,
+    remote: Vec<u8>,
+    /// Revision of the merged slot
+    revision: u64,
+    playtime: Duration,
+}
+
+/// Remote file of a slot, written next to the local saves until [`receive_sync`] moves it in place
+struct Download {
+    id: u32,
+    /// Revision of the local slot when the job started, `None` if there was none
+    base: Option<u64>,
+    slot: SaveSlot,
+    temp: PathBuf,
+}
+
+/// Slots waiting for [`merge_conflicts`]
+#[derive(Resource, Default)]
+struct PendingMerges(Vec<MergeInput>);
+
+#[derive(Resource)]
+struct SyncMerge<T>(fn(T, T) -> T);
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Manifest {
+    slots: HashMap<u32, SaveSlot>,
+    /// Slots deleted on a machine, until one is uploaded again with the same id
+    deleted: BTreeSet<u32>,
+}
+
+/// Copy of the resources a job reads, taken when it starts
+struct SyncContext {
+    remote: Arc<dyn RemoteBackend>,
+    storage: SaveStorage,
+    dirs: SaveDirs,
+    writes: PendingWrites,
+    profile: CurrentProfile,
+    save_config: SaveConfig,
+    /// Delta slots are uploaded as full saves
+    flattener: DeltaFlattener,
+    strategy: ConflictStrategy,
+    /// Conflicts are downloaded for [`merge_conflicts`] under [`ConflictStrategy::Merge`]
+    merge: bool,
+}
+
+/// What a job did, applied to [`SaveConfig`] by [`receive_sync`]. Results are based on the revision of the
+/// local slot when the job started, and dropped if it was saved since.
+#[derive(Default)]
+struct SyncOutcome {
+    /// Slots uploaded, with the local revision uploaded and the revision recorded remotely
+    uploaded: Vec<(u32, u64, u64)>,
+    downloaded: Vec<Download>,
+    /// Local slots deleted on another machine, with their revision
+    deleted: Vec<(u32, u64)>,
+    /// Local deletions recorded in the manifest
+    tombstones: Vec<u32>,
+    conflicts: Vec<SyncConflict>,
+    merges: Vec<MergeInput>,
+    failed: Vec<(Option<u32>, SaveError)>,
+    /// Jobs to run again, for slots which were being saved
+    retry: Vec<SyncJob>,
+}
+
+pub(crate) struct CloudSyncPlugin<T> {
+    pub sync: CloudSync,
+    pub _save: PhantomData<T>,
+}
+
+impl<T> Plugin for CloudSyncPlugin<T>
+where
+    T: Resource + EncryptSave,
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.sync.clone())
+            .init_resource::<SyncJobs>()
+            .add_message::<SyncSaves>()
+            .add_message::<SavesSynced>()
+            .add_message::<SyncFailed>()
+            .add_message::<SyncConflict>()
+            .add_message::<ResolveSyncConflict>()
+            .add_systems(Startup, request_sync)
+            .add_systems(
+                Update,
+                (
+                    request_sync.run_if(
+                        on_message::<SyncSaves>
+                            .or(on_message::<GameSaved>)
+                            .or(on_message::<ProfileSwitched>),
+                    ),
+                    queue_resolutions.run_if(on_message::<ResolveSyncConflict>),
+                    receive_sync,
+                    start_sync_job::<T>.run_if(has_sync_jobs),
+                )
+                    .chain()
+                    .after(load_config::<SaveConfig>)
+                    .after(SaveSet::Write),
+            );
+    }
+}
+
+/// Merge conflicting slots with the resolver of [`EncryptSavePlugin`](crate::save::EncryptSavePlugin)
+pub(crate) struct SyncMergePlugin<T> {
+    pub merge: fn(T, T) -> T,
+}
+
+impl<T> Plugin for SyncMergePlugin<T>
+where
+    T: Resource + EncryptSave,
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SyncMerge(self.merge))
+            .init_resource::<PendingMerges>()
+            .add_systems(
+                Update,
+                merge_conflicts::<T>
+                    .after(receive_sync)
+                    .before(start_sync_job::<T>)
+                    .after(SaveSet::Write)
+                    .run_if(has_pending_merges),
+            );
+    }
+}
+
+fn request_sync(mut jobs: ResMut<SyncJobs>) {
+    jobs.push(SyncJob::Sync);
+}
+
+fn queue_resolutions(mut resolutions: MessageReader<ResolveSyncConflict>, mut jobs: ResMut<SyncJobs>) {
+    for resolution in resolutions.read() {
+        jobs.push(SyncJob::Keep(resolution.slot, resolution.keep));
+    }
+}
+
+fn has_sync_jobs(jobs: Res<SyncJobs>) -> bool {
+    !jobs.running && !jobs.queue.is_empty()
+}
+
+/// Spawn the next job once saves are written in the background, so it only reads complete files
+fn start_sync_job<T>(
+    cloud: Res<CloudSync>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    writes: Res<PendingWrites>,
+    profile: Res<CurrentProfile>,
+    save_config: Res<SaveConfig>,
+    delta_saves: DeltaSaves,
+    pending_merges: Option<Res<PendingMerges>>,
+    mut jobs: ResMut<SyncJobs>,
+) where
+    T: Resource + EncryptSave,
+{
+    if !writes.is_idle() {
+        return;
+    }
+    let Some(job) = jobs.queue.pop_front() else {
+        return;
+    };
+    jobs.running = true;
+
+    let context = SyncContext {
+        remote: cloud.remote.clone(),
+        storage: storage.clone(),
+        dirs: dirs.clone(),
+        writes: writes.clone(),
+        profile: profile.clone(),
+        save_config: save_config.clone(),
+        flattener: delta_saves.flattener::<T>(),
+        strategy: cloud.strategy,
+        merge: pending_merges.is_some(),
+    };
+    let sender = jobs.sender.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            let _ = sender.send(context.run(job));
+        })
+        .detach();
+}
+
+/// Apply the outcome of the running job and report it. Slots saved while it ran keep their new data,
+/// and are synced again.
+fn receive_sync(
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    mut jobs: ResMut<SyncJobs>,
+    mut save_config: ResMut<SaveConfig>,
+    mut pending_merges: Option<ResMut<PendingMerges>>,
+    mut synced: MessageWriter<SavesSynced>,
+    mut conflicts: MessageWriter<SyncConflict>,
+    mut failed: MessageWriter<SyncFailed>,
+    mut setting_changed: MessageWriter<GameSettingChanged>,
+    mut delete: MessageWriter<DeleteSave>,
+) {
+    let Some(outcome) = jobs.receiver.lock().ok().and_then(|receiver| receiver.try_recv().ok()) else {
+        return;
+    };
+    jobs.running = false;
+    let mut stale = false;
+    let revision = |save_config: &SaveConfig, id| save_config.slot(id).map(|slot| slot.revision);
+
+    for (slot, error) in outcome.failed {
+        report(&mut failed, slot, error);
+    }
+    conflicts.write_batch(outcome.conflicts);
+    if let Some(pending) = pending_merges.as_mut() {
+        pending.0.extend(outcome.merges);
+    }
+
+    let mut deleted = Vec::new();
+    for (id, base) in outcome.deleted {
+        if revision(&save_config, id) == Some(base) {
+            delete.write(DeleteSave(id));
+            deleted.push(id);
+        } else {
+            stale = true;
+        }
+    }
+
+    let mut uploaded = Vec::new();
+    for (id, base, uploaded_revision) in outcome.uploaded {
+        // The remote holds this revision even if the slot was saved since, the next sync uploads the new one
+        if let Some(slot) = save_config.slot_mut(id) {
+            slot.revision =
+                if slot.revision == base { uploaded_revision } else { slot.revision.max(uploaded_revision + 1) };
+            slot.synced_revision = uploaded_revision;
+            stale |= slot.revision != uploaded_revision;
+            uploaded.push(id);
+        }
+    }
+
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    let mut downloaded = Vec::new();
+    for mut download in outcome.downloaded {
+        if revision(&save_config, download.id) != download.base {
+            let _ = storage.remove(&download.temp);
+            stale = true;
+            continue;
+        }
+        match place_download(&storage, &save_dir, save_config.slot(download.id), &mut download) {
+            Ok(()) => {
+                save_config.insert_slot(download.id, download.slot);
+                downloaded.push(download.id);
+            }
+            Err(e) => {
+                let _ = storage.remove(&download.temp);
+                report(&mut failed, Some(download.id), e);
+            }
+        }
+    }
+
+    for job in outcome.retry {
+        jobs.push(job);
+    }
+    if stale {
+        jobs.push(SyncJob::Sync);
+    }
+    if !uploaded.is_empty() || !downloaded.is_empty() || !outcome.tombstones.is_empty() {
+        for id in &outcome.tombstones {
+            save_config.forget_deleted(*id);
+        }
+        setting_changed.write(GameSettingChanged);
+    }
+    if !uploaded.is_empty() || !downloaded.is_empty() || !deleted.is_empty() {
+        synced.write(SavesSynced {
+            uploaded,
+            downloaded,
+            deleted,
+        });
+    }
+}
+
+/// Move the downloaded file of a slot over its local file, and remove the local files it replaces
+fn place_download(
+    storage: &SaveStorage,
+    save_dir: &Path,
+    local_slot: Option<&SaveSlot>,
+    download: &mut Download,
+) -> Result<(), SaveError> {
+    storage.copy(&download.temp, &save_dir.join(&download.slot.file))?;
+    let _ = storage.remove(&download.temp);
+
+    let Some(local_slot) = local_slot else {
+        return Ok(());
+    };
+    download.slot.loaded_at = local_slot.loaded_at;
+    if local_slot.file != download.slot.file {
+        let _ = storage.remove(&save_dir.join(&local_slot.file));
+    }
+    // Thumbnails and bases are never uploaded, files named there would be deleted along with the slot
+    for file in local_slot.thumbnail.iter().chain(&local_slot.base) {
+        let _ = storage.remove(&save_dir.join(file));
+    }
+    Ok(())
+}
+
+impl SyncContext {
+    fn run(self, job: SyncJob) -> SyncOutcome {
+        match job {
+            SyncJob::Sync => self.sync(),
+            SyncJob::Keep(id, side) => self.keep(id, side),
+        }
+    }
+
+    fn sync(&self) -> SyncOutcome {
+        let mut outcome = SyncOutcome::default();
+        let remote = self.remote.as_ref();
+        let (mut manifest, remote_files) =
+            match read_manifest(remote, &self.profile).and_then(|m| Ok((m, remote.list()?))) {
+                Ok(remote_state) => remote_state,
+                Err(e) => {
+                    outcome.failed.push((None, e));
+                    return outcome;
+                }
+            };
+
+        // Slots deleted here, a slot created since with the same id is uploaded below
+        let tombstones: Vec<u32> = self.save_config.deleted().iter().copied().collect();
+        for id in &tombstones {
+            if let Some(stale) = manifest.slots.remove(id) {
+                if let Err(e) = remote_name(&stale.file).and_then(|name| Ok(remote.remove(&name)?)) {
+                    outcome.failed.push((Some(*id), e));
+                }
+            }
+            manifest.deleted.insert(*id);
+        }
+
+        let ids: BTreeSet<u32> = self
+            .save_config
+            .slots()
+            .keys()
+            .chain(manifest.slots.keys())
+            .copied()
+            .collect();
+        let mut uploaded = Vec::new();
+        for id in ids {
+            let side = match (self.save_config.slot(id), manifest.slots.get(&id)) {
+                // Deleted on another machine, kept if changed here since
+                (Some(local_slot), None)
+                    if manifest.deleted.contains(&id)
+                        && local_slot.synced_revision != 0
+                        && local_slot.revision == local_slot.synced_revision =>
+                {
+                    outcome.deleted.push((id, local_slot.revision));
+                    continue;
+                }
+                (Some(_), None) => SyncSide::Local,
+                (None, Some(remote_slot)) => {
+                    if !remote_name(&remote_slot.file).is_ok_and(|name| remote_files.contains(&name)) {
+                        continue;
+                    }
+                    SyncSide::Remote
+                }
+                (Some(local_slot), Some(remote_slot)) => {
+                    let local_changed = local_slot.revision != local_slot.synced_revision;
+                    let remote_changed = remote_slot.revision != local_slot.synced_revision;
+                    match (local_changed, remote_changed) {
+                        (false, false) => continue,
+                        (true, false) => SyncSide::Local,
+                        (false, true) => SyncSide::Remote,
+                        (true, true) => match self.strategy {
+                            ConflictStrategy::Merge if self.merge => {
+                                match self.fetch_sides(&manifest, id) {
+                                    Ok(Some(input)) => outcome.merges.push(input),
+                                    Ok(None) => outcome.retry.push(SyncJob::Sync),
+                                    Err(e) => outcome.failed.push((Some(id), e)),
+                                }
+                                continue;
+                            }
+                            ConflictStrategy::NewestWins | ConflictStrategy::Merge
+                                if local_slot.saved_at >= remote_slot.saved_at =>
+                            {
+                                SyncSide::Local
+                            }
+                            ConflictStrategy::NewestWins | ConflictStrategy::Merge => SyncSide::Remote,
+                            ConflictStrategy::Manual => {
+                                #[cfg(feature = "log")]
+                                warn!("Save slot {} was changed both locally and remotely", id);
+                                outcome.conflicts.push(SyncConflict {
+                                    slot: id,
+                                    local_meta: local_slot.into(),
+                                    remote_meta: remote_slot.into(),
+                                });
+                                continue;
+                            }
+                        },
+                    }
+                }
+                (None, None) => continue,
+            };
+
+            let result = match side {
+                SyncSide::Local => self.upload(&mut manifest, id).map(|revision| match revision {
+                    Some((base, revision)) => uploaded.push((id, base, revision)),
+                    None => outcome.retry.push(SyncJob::Sync),
+                }),
+                SyncSide::Remote => self
+                    .download(&manifest, id)
+                    .map(|download| outcome.downloaded.push(download)),
+            };
+            if let Err(e) = result {
+                outcome.failed.push((Some(id), e));
+            }
+        }
+
+        if !uploaded.is_empty() || !tombstones.is_empty() {
+            if let Err(e) = write_manifest(remote, &self.profile, &manifest) {
+                outcome.failed.push((None, e));
+                return outcome;
+            }
+        }
+        outcome.uploaded = uploaded;
+        outcome.tombstones = tombstones;
+        outcome
+    }
+
+    fn keep(&self, id: u32, side: SyncSide) -> SyncOutcome {
+        let mut outcome = SyncOutcome::default();
+        let remote = self.remote.as_ref();
+        let result = read_manifest(remote, &self.profile).and_then(|mut manifest| match side {
+            SyncSide::Local => {
+                match self.upload(&mut manifest, id)? {
+                    Some((base, revision)) => {
+                        write_manifest(remote, &self.profile, &manifest)?;
+                        outcome.uploaded.push((id, base, revision));
+                    }
+                    None => outcome.retry.push(SyncJob::Keep(id, side)),
+                }
+                Ok(())
+            }
+            SyncSide::Remote => {
+                outcome.downloaded.push(self.download(&manifest, id)?);
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            outcome.failed.push((Some(id), e));
+        }
+        outcome
+    }
+
+    /// Full save of the local `slot`, `None` if it is being saved again and may be cut short
+    fn read_local(&self, slot: &SaveSlot) -> Result<Option<Vec<u8>>, SaveError> {
+        let save_dir = self.save_config.save_dir(&self.dirs);
+        let data = self.flattener.full_save(&self.storage, &save_dir, slot)?;
+        if slot
+            .base
+            .iter()
+            .chain([&slot.file])
+            .any(|file| self.writes.is_writing(&self.storage.0, &save_dir.join(file)))
+        {
+            return Ok(None);
+        }
+        Ok(Some(data))
+    }
+
+    /// Read both sides of slot `id` for [`merge_conflicts`], `None` if the local file is being saved
+    fn fetch_sides(&self, manifest: &Manifest, id: u32) -> Result<Option<MergeInput>, SaveError> {
+        let (Some(local_slot), Some(remote_slot)) = (self.save_config.slot(id), manifest.slots.get(&id)) else {
+            return Err(SaveError::NotFound(id));
+        };
+        let Some(local) = self.read_local(local_slot)? else {
+            return Ok(None);
+        };
+        Ok(Some(MergeInput {
+            id,
+            base: local_slot.revision,
+            local,
+            remote: self.remote.download(&remote_name(&remote_slot.file)?)?,
+            revision: local_slot.revision.max(remote_slot.revision) + 1,
+            playtime: local_slot.playtime.max(remote_slot.playtime),
+        }))
+    }
+
+    /// Copy the local file of slot `id` to the remote and record it in `manifest`, returning its revision.
+    /// Returns `None` without uploading if the file is being saved.
+    fn upload(&self, manifest: &mut Manifest, id: u32) -> Result<Option<(u64, u64)>, SaveError> {
+        let Some(slot) = self.save_config.slot(id) else {
+            return Err(SaveError::NotFound(id));
+        };
+        let name = remote_name(&slot.file)?;
+        let Some(data) = self.read_local(slot)? else {
+            return Ok(None);
+        };
+        self.remote.upload(&name, &data)?;
+
+        // Overwriting a remote save made elsewhere, other machines must see a revision above theirs
+        let revision = match manifest.slots.get(&id) {
+            Some(remote_slot) if remote_slot.revision != slot.synced_revision => {
+                slot.revision.max(remote_slot.revision + 1)
+            }
+            _ => slot.revision,
+        };
+        manifest.deleted.remove(&id);
+        let replaced = manifest.slots.insert(
+            id,
+            SaveSlot {
+                thumbnail: None,
+                base: None,
+                revision,
+                synced_revision: revision,
+                ..slot.clone()
+            },
+        );
+        if let Some(stale) = replaced.filter(|stale| stale.file != slot.file) {
+            let _ = self.remote.remove(&remote_name(&stale.file)?);
+        }
+        Ok(Some((slot.revision, revision)))
+    }
+
+    /// Copy the remote file of slot `id` next to the local saves, under a temporary name
+    fn download(&self, manifest: &Manifest, id: u32) -> Result<Download, SaveError> {
+        let Some(remote_slot) = manifest.slots.get(&id) else {
+            return Err(SaveError::NotFound(id));
+        };
+        // The manifest comes from the remote, `remote_name` fails unless its file stays inside the save directory
+        let data = self.remote.download(&remote_name(&remote_slot.file)?)?;
+        let mut temp = self
+            .save_config
+            .save_dir(&self.dirs)
+            .join(&remote_slot.file)
+            .into_os_string();
+        temp.push(".download");
+        let temp = PathBuf::from(temp);
+        self.storage.write(&temp, &data)?;
+
+        Ok(Download {
+            id,
+            base: self.save_config.slot(id).map(|slot| slot.revision),
+            slot: SaveSlot {
+                synced_revision: remote_slot.revision,
+                thumbnail: None,
+                base: None,
+                ..remote_slot.clone()
+            },
+            temp,
+        })
+    }
+}
+
+fn has_pending_merges(pending: Res<PendingMerges>) -> bool {
+    !pending.0.is_empty()
+}
+
+/// Merge the sides downloaded by a sync, then upload the result
+fn merge_conflicts<T>(world: &mut World)
+where
+    T: Resource + EncryptSave,
+{
+    let inputs = std::mem::take(&mut world.resource_mut::<PendingMerges>().0);
+    let mut merged = false;
+    for input in inputs {
+        let id = input.id;
+        let current = world.resource::<SaveConfig>().slot(id).map(|slot| slot.revision);
+        // Saved since the sides were read, the next sync settles the new save
+        if current != Some(input.base) {
+            world.resource_mut::<SyncJobs>().push(SyncJob::Sync);
+            continue;
+        }
+        match merge_slot::<T>(world, input) {
+            Ok(()) => {
+                world
+                    .resource_mut::<SyncJobs>()
+                    .push(SyncJob::Keep(id, SyncSide::Local));
+                merged = true;
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                error!("Failed to sync saves: {}", error);
+                world.write_message(SyncFailed { slot: Some(id), error });
+            }
+        }
+    }
+    if merged {
+        world.write_message(GameSettingChanged);
+    }
+}
+
+/// Write the merge of both sides of a slot over its local file in the background.
+/// A slot being played keeps its resources until it is loaded again.
+fn merge_slot<T>(world: &mut World, input: MergeInput) -> Result<(), SaveError>
+where
+    T: Resource + EncryptSave,
+{
+    let dirs = world.resource::<SaveDirs>().clone();
+    let Some(path) = world.resource::<SaveConfig>().slot_path(&dirs, input.id) else {
+        return Err(SaveError::NotFound(input.id));
+    };
+    let merge = world.resource::<SyncMerge<T>>().0;
+    let data = merge_saves::<T>(world, &input.local, &input.remote, merge, &slot_cipher(world, input.id))?;
+    let storage = world.resource::<SaveStorage>().clone();
+    world
+        .resource::<PendingWrites>()
+        .spawn_write(storage.0.clone(), path, data, Some(input.id));
+
+    let mut save_config = world.resource_mut::<SaveConfig>();
+    let save_dir = save_config.save_dir(&dirs).into_owned();
+    if let Some(slot) = save_config.slot_mut(input.id) {
+        slot.revision = input.revision;
+        slot.saved_at = unix_now();
+        slot.playtime = input.playtime;
+        // The merge is a full save, of a delta slot too
+        if let Some(base) = slot.base.take() {
+            slot.deltas = 0;
+            let _ = storage.remove(&save_dir.join(base));
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(remote: &dyn RemoteBackend, profile: &CurrentProfile) -> Result<Manifest, SaveError> {
+    match remote.download(&manifest_name(profile)?) {
+        Ok(data) => ron::de::from_bytes(&data).map_err(|e| SaveError::Deserialize(e.into())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_manifest(remote: &dyn RemoteBackend, profile: &CurrentProfile, manifest: &Manifest) -> Result<(), SaveError> {
+    let ron_str =
+        ron::ser::to_string_pretty(manifest, PrettyConfig::default()).map_err(|e| SaveError::Serialize(e.into()))?;
+    Ok(remote.upload(&manifest_name(profile)?, ron_str.as_bytes())?)
+}
+
+fn manifest_name(profile: &CurrentProfile) -> Result<String, SaveError> {
+    remote_name(&profile.dir().join(MANIFEST))
+}
+
+/// `file`, relative to the save directory, with `/` separators. Fails for absolute files and files with `..`.
+fn remote_name(file: &Path) -> Result<String, SaveError> {
+    let parts = file
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => Ok(part.to_string_lossy()),
+            _ => Err(SaveError::Corrupted(format!(
+                "{} is not a relative file",
+                file.display()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(parts.join("/"))
+}
+
+fn report(failed: &mut MessageWriter<SyncFailed>, slot: Option<u32>, error: SaveError) {
+    #[cfg(feature = "log")]
+    error!("Failed to sync saves: {}", error);
+    failed.write(SyncFailed { slot, error });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::{
+        EncryptSavePlugin,
+        LoadFailed,
+        LoadGame,
+        SaveGame,
+    };
+    use crate::testing::TestSaveHarness;
+    use std::sync::MutexGuard;
+
+    #[derive(Resource, Serialize, Deserialize, Default, Clone)]
+    struct Progress {
+        level: u32,
+    }
+
+    impl EncryptSave for Progress {}
+
+    #[derive(Clone, Default)]
+    struct MemoryRemote {
+        files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        /// Held back the downloads of saves while locked
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl MemoryRemote {
+        fn hold(&self) -> MutexGuard<'_, ()> {
+            self.gate.lock().unwrap()
+        }
+    }
+
+    impl RemoteBackend for MemoryRemote {
+        fn upload(&self, name: &str, data: &[u8]) -> io::Result<()> {
+            self.files.lock().unwrap().insert(name.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn download(&self, name: &str) -> io::Result<Vec<u8>> {
+            if name != MANIFEST {
+                drop(self.gate.lock().unwrap());
+            }
+            self.files
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ErrorKind::NotFound.into())
+        }
+
+        fn list(&self) -> io::Result<Vec<String>> {
+            Ok(self.files.lock().unwrap().keys().cloned().collect())
+        }
+    }
+
+    fn harness(remote: &MemoryRemote, strategy: ConflictStrategy) -> TestSaveHarness {
+        TestSaveHarness::new(
+            EncryptSavePlugin::<Progress>::new().with_cloud_sync(CloudSync::new(remote.clone(), strategy)),
+        )
+    }
+
+    /// Update until the running job has completed and nothing is left to do, returning the slots in conflict
+    fn settle(harness: &mut TestSaveHarness) -> Vec<u32> {
+        let mut conflicts = Vec::new();
+        for _ in 0..500 {
+            harness.update();
+            conflicts.extend(harness.messages::<SyncConflict>().iter().map(|conflict| conflict.slot));
+            let jobs = harness.resource::<SyncJobs>();
+            if !jobs.running && jobs.queue.is_empty() {
+                return conflicts;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        panic!("Sync jobs never completed");
+    }
+
+    fn save(harness: &mut TestSaveHarness, level: u32) -> u32 {
+        harness.resource_mut::<Progress>().level = level;
+        harness.send(SaveGame::new(0)).update();
+        **harness.assert_sent::<GameSaved>()
+    }
+
+    fn overwrite(harness: &mut TestSaveHarness, id: u32, level: u32) {
+        harness.resource_mut::<Progress>().level = level;
+        harness.send(SaveGame::overwrite(id)).update();
+        harness.assert_sent::<GameSaved>();
+    }
+
+    /// Two apps syncing slot `id`, changed by both since they last synced: level 2 by the second one, which synced
+    /// it, then level 3 by the first one. Returns the conflicts of the sync of the first one.
+    fn conflicting(strategy: ConflictStrategy) -> (TestSaveHarness, TestSaveHarness, u32, Vec<u32>) {
+        let remote = MemoryRemote::default();
+        let mut first = harness(&remote, strategy);
+        let id = save(&mut first, 1);
+        settle(&mut first);
+        let mut second = harness(&remote, strategy);
+        settle(&mut second);
+        assert_eq!(load(&mut second, id), 1);
+
+        overwrite(&mut second, id, 2);
+        settle(&mut second);
+        overwrite(&mut first, id, 3);
+        let conflicts = settle(&mut first);
+        (first, second, id, conflicts)
+    }
+
+    fn sync(harness: &mut TestSaveHarness) {
+        harness.send(SyncSaves);
+        settle(harness);
+    }
+
+    fn load(harness: &mut TestSaveHarness, id: u32) -> u32 {
+        harness.send(LoadGame(id)).update();
+        harness.assert_not_sent::<LoadFailed>();
+        harness.resource::<Progress>().level
+    }
+
+    #[test]
+    fn save_made_during_a_download_is_kept() {
+        let remote = MemoryRemote::default();
+        let mut first = harness(&remote, ConflictStrategy::Manual);
+        let id = save(&mut first, 1);
+        settle(&mut first);
+
+        let gate = remote.hold();
+        // Starts the startup sync, which waits for the gate to download the slot
+        let mut second = harness(&remote, ConflictStrategy::Manual);
+        // The download may hold the only thread of the pool, the write of the save waits for it
+        second.resource_mut::<Progress>().level = 2;
+        second.send(SaveGame::new(0)).app().update();
+        assert_eq!(**second.assert_sent::<GameSaved>(), id);
+        drop(gate);
+        settle(&mut second);
+
+        assert_eq!(load(&mut second, id), 2);
+        assert!(second
+            .backend()
+            .paths()
+            .iter()
+            .all(|path| path.extension().is_none_or(|ext| ext != "download")));
+    }
+
+    #[test]
+    fn newest_save_wins_a_conflict() {
+        let (mut first, mut second, id, conflicts) = conflicting(ConflictStrategy::NewestWins);
+        assert!(conflicts.is_empty());
+        sync(&mut second);
+        assert_eq!(load(&mut first, id), 3);
+        assert_eq!(load(&mut second, id), 3);
+    }
+
+    #[test]
+    fn manual_conflict_waits_for_a_resolution() {
+        let (mut first, mut second, id, conflicts) = conflicting(ConflictStrategy::Manual);
+        assert_eq!(conflicts, [id]);
+        assert_eq!(load(&mut first, id), 3);
+
+        first.send(ResolveSyncConflict {
+            slot: id,
+            keep: SyncSide::Remote,
+        });
+        assert!(settle(&mut first).is_empty());
+        assert_eq!(load(&mut first, id), 2);
+        sync(&mut first);
+        sync(&mut second);
+        assert_eq!(load(&mut second, id), 2);
+    }
+
+    #[test]
+    fn kept_local_side_overwrites_the_remote() {
+        let (mut first, mut second, id, _) = conflicting(ConflictStrategy::Manual);
+        first.send(ResolveSyncConflict {
+            slot: id,
+            keep: SyncSide::Local,
+        });
+        settle(&mut first);
+        sync(&mut second);
+        assert_eq!(load(&mut second, id), 3);
+        assert_eq!(load(&mut first, id), 3);
+    }
+}
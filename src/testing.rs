@@ -0,0 +1,122 @@
+//! Drive the plugins in integration tests, without a window or the file system
+use crate::backend::MemoryBackend;
+use crate::io::PendingWrites;
+use crate::save::{
+    EncryptSave,
+    EncryptSavePlugin,
+};
+use bevy::app::App;
+use bevy::ecs::message::{
+    MessageRegistry,
+    ShouldUpdateMessages,
+};
+use bevy::prelude::{
+    Message,
+    Messages,
+    MinimalPlugins,
+    Mut,
+    Resource,
+};
+use std::any::type_name;
+
+/// [`App`] with [`MinimalPlugins`] and an [`EncryptSavePlugin`] storing files in a [`MemoryBackend`].
+/// Each update waits for the writes it started, so files can be checked right after it.
+pub struct TestSaveHarness {
+    app: App,
+    backend: MemoryBackend,
+}
+
+impl TestSaveHarness {
+    /// Build the app and run its startup
+    pub fn new<T>(plugin: EncryptSavePlugin<T>) -> Self
+    where
+        T: Resource + Default + EncryptSave + Clone,
+    {
+        let backend = MemoryBackend::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(plugin.with_backend(backend.clone()));
+        // Messages are otherwise kept until a fixed update runs, see `messages`
+        app.world_mut().resource_mut::<MessageRegistry>().should_update = ShouldUpdateMessages::Always;
+        let mut harness = Self { app, backend };
+        harness.update();
+        harness
+    }
+
+    pub fn app(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn backend(&self) -> &MemoryBackend {
+        &self.backend
+    }
+
+    /// Queue `message` for the next update
+    pub fn send<M>(&mut self, message: M) -> &mut Self
+    where
+        M: Message,
+    {
+        self.app.world_mut().write_message(message);
+        self
+    }
+
+    pub fn update(&mut self) -> &mut Self {
+        self.app.update();
+        self.app.world().resource::<PendingWrites>().flush();
+        self
+    }
+
+    pub fn updates(&mut self, count: usize) -> &mut Self {
+        for _ in 0..count {
+            self.update();
+        }
+        self
+    }
+
+    pub fn resource<R>(&self) -> &R
+    where
+        R: Resource,
+    {
+        self.app.world().resource::<R>()
+    }
+
+    pub fn resource_mut<R>(&mut self) -> Mut<'_, R>
+    where
+        R: Resource,
+    {
+        self.app.world_mut().resource_mut::<R>()
+    }
+
+    /// Messages `M` sent during the last update
+    pub fn messages<M>(&self) -> Vec<&M>
+    where
+        M: Message,
+    {
+        self.app
+            .world()
+            .get_resource::<Messages<M>>()
+            .map(|messages| messages.iter_current_update_messages().collect())
+            .unwrap_or_default()
+    }
+
+    /// First message `M` sent during the last update
+    #[track_caller]
+    pub fn assert_sent<M>(&self) -> &M
+    where
+        M: Message,
+    {
+        match self.messages::<M>().first() {
+            Some(message) => message,
+            None => panic!("No {} was sent", type_name::<M>()),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_not_sent<M>(&self)
+    where
+        M: Message,
+    {
+        let count = self.messages::<M>().len();
+        assert!(count == 0, "{} {} were sent", count, type_name::<M>());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::backend::{
+    SaveBackend,
+    SaveStorage,
+};
+use crate::io::PendingWrites;
+use crate::paths::SaveDirs;
+use crate::save::{
+    GameSaved,
+    SaveConfig,
+    SaveSet,
+};
+use crate::setting::GameSettingChanged;
+use bevy::app::App;
+use bevy::asset::{
+    Assets,
+    Handle,
+    RenderAssetUsages,
+};
+use bevy::image::Image;
+use bevy::math::UVec2;
+#[cfg(feature = "log")]
+use bevy::prelude::warn;
+use bevy::prelude::{
+    on_message,
+    resource_changed,
+    Commands,
+    IntoScheduleConfigs,
+    MessageReader,
+    MessageWriter,
+    On,
+    Plugin,
+    Res,
+    ResMut,
+    Resource,
+    Update,
+};
+use bevy::render::view::screenshot::{
+    Screenshot,
+    ScreenshotCaptured,
+};
+use image::{
+    DynamicImage,
+    ImageFormat,
+};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Thumbnails of save slots, loaded from their sidecar files
+#[derive(Resource, Default)]
+pub struct SaveThumbnails(HashMap<u32, Handle<Image>>);
+
+impl SaveThumbnails {
+    pub fn get(&self, id: u32) -> Option<&Handle<Image>> {
+        self.0.get(&id)
+    }
+}
+
+#[derive(Resource)]
+struct ThumbnailSize(UVec2);
+
+pub(crate) struct ThumbnailPlugin {
+    pub size: UVec2,
+}
+
+impl Plugin for ThumbnailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveThumbnails>()
+            .insert_resource(ThumbnailSize(self.size))
+            .add_systems(
+                Update,
+                (
+                    capture_thumbnails.after(SaveSet::Write).run_if(on_message::<GameSaved>),
+                    sync_thumbnails.run_if(resource_changed::<SaveConfig>),
+                ),
+            );
+    }
+}
+
+fn capture_thumbnails(mut commands: Commands, mut saved: MessageReader<GameSaved>, size: Res<ThumbnailSize>) {
+    for id in saved.read() {
+        let (slot, size) = (**id, size.0);
+        commands.spawn(Screenshot::primary_window()).observe(
+            move |captured: On<ScreenshotCaptured>,
+                  mut save_config: ResMut<SaveConfig>,
+                  storage: Res<SaveStorage>,
+                  dirs: Res<SaveDirs>,
+                  writes: Res<PendingWrites>,
+                  mut images: ResMut<Assets<Image>>,
+                  mut thumbnails: ResMut<SaveThumbnails>,
+                  mut setting_changed: MessageWriter<GameSettingChanged>| {
+                let Some(file) = save_config.slot(slot).map(|saved| saved.file.with_extension("png")) else {
+                    return;
+                };
+
+                let thumbnail = match captured.image.clone().try_into_dynamic() {
+                    // Drop the alpha channel, it holds brightness when HDR is enabled
+                    Ok(screenshot) => DynamicImage::ImageRgb8(screenshot.thumbnail(size.x, size.y).to_rgb8()),
+                    Err(_e) => {
+                        #[cfg(feature = "log")]
+                        warn!("Failed to convert screenshot of slot {}: {}", slot, _e);
+                        return;
+                    }
+                };
+
+                let mut png = Cursor::new(Vec::new());
+                if let Err(_e) = thumbnail.write_to(&mut png, ImageFormat::Png) {
+                    #[cfg(feature = "log")]
+                    warn!("Failed to encode thumbnail of slot {}: {}", slot, _e);
+                    return;
+                }
+                writes.spawn_write(
+                    storage.0.clone(),
+                    save_config.save_dir(&dirs).join(&file),
+                    png.into_inner(),
+                    Some(slot),
+                );
+
+                let handle = images.add(Image::from_dynamic(thumbnail, true, RenderAssetUsages::default()));
+                thumbnails.0.insert(slot, handle);
+                if let Some(saved) = save_config.slot_mut(slot) {
+                    if saved.thumbnail.as_ref() != Some(&file) {
+                        saved.thumbnail = Some(file);
+                        setting_changed.write(GameSettingChanged);
+                    }
+                }
+            },
+        );
+    }
+}
+
+/// Drop thumbnails of deleted slots and load the ones which aren't in memory yet
+fn sync_thumbnails(
+    save_config: Res<SaveConfig>,
+    storage: Res<SaveStorage>,
+    dirs: Res<SaveDirs>,
+    mut thumbnails: ResMut<SaveThumbnails>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    thumbnails
+        .0
+        .retain(|id, _| save_config.slot(*id).is_some_and(|slot| slot.thumbnail.is_some()));
+
+    for (id, slot) in save_config.slots() {
+        let Some(file) = &slot.thumbnail else {
+            continue;
+        };
+        if thumbnails.0.contains_key(id) {
+            continue;
+        }
+
+        let path = save_config.save_dir(&dirs).join(file);
+        match read_thumbnail(storage.0.as_ref(), &path) {
+            Ok(image) => {
+                thumbnails.0.insert(*id, images.add(image));
+            }
+            Err(_e) => {
+                #[cfg(feature = "log")]
+                warn!("Failed to load thumbnail {}: {}", path.display(), _e);
+            }
+        }
+    }
+}
+
+fn read_thumbnail(backend: &dyn SaveBackend, path: &Path) -> Result<Image, Box<dyn std::error::Error>> {
+    let png = backend.read(path)?;
+    let thumbnail = image::load_from_memory_with_format(&png, ImageFormat::Png)?;
+    Ok(Image::from_dynamic(thumbnail, true, RenderAssetUsages::default()))
+}
@@ -0,0 +1,46 @@
+//! Custom stages between serializing a save and encrypting it, see [`SaveTransform`]
+
+use crate::error::SaveError;
+use std::sync::Arc;
+
+/// Step of the save pipeline run on the serialized data of every save before it is encrypted,
+/// e.g. compression, extra obfuscation or sampling for analytics. Loads run [`SaveTransform::decode`] on the way back.
+///
+/// Register it with [`EncryptSavePlugin::with_transform`](crate::save::EncryptSavePlugin::with_transform),
+/// transforms are applied in the order they were registered and undone in reverse.
+/// The names of the transforms are written in the save, so saves made before a transform was added still load.
+pub trait SaveTransform: Send + Sync + 'static {
+    /// Unique name recorded in saves, to find the transform again on load
+    fn name(&self) -> &str;
+
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>, SaveError>;
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>, SaveError>;
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Transforms(pub Vec<Arc<dyn SaveTransform>>);
+
+impl Transforms {
+    /// Run every transform in order, with the names of the transforms that were run
+    pub fn encode(&self, mut data: Vec<u8>) -> Result<(Vec<String>, Vec<u8>), SaveError> {
+        for transform in &self.0 {
+            data = transform.encode(data)?;
+        }
+        let names = self.0.iter().map(|transform| transform.name().to_string()).collect();
+        Ok((names, data))
+    }
+
+    /// Undo the transforms named in `names`, in reverse
+    pub fn decode(&self, names: &[String], mut data: Vec<u8>) -> Result<Vec<u8>, SaveError> {
+        for name in names.iter().rev() {
+            let transform = self
+                .0
+                .iter()
+                .find(|transform| transform.name() == name)
+                .ok_or_else(|| SaveError::Corrupted(format!("Unknown transform {}", name)))?;
+            data = transform.decode(data)?;
+        }
+        Ok(data)
+    }
+}
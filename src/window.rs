@@ -0,0 +1,182 @@
+//! Resolution, window mode and vsync of the primary window, kept in a settings file by [`WindowSettingsPlugin`]
+use crate::setting::{
+    load_config,
+    GameSetting,
+    GameSettingChanged,
+    GameSettingSupportPlugin,
+};
+use bevy::app::App;
+use bevy::prelude::{
+    resource_changed,
+    Changed,
+    DetectChangesMut,
+    IntoScheduleConfigs,
+    MessageWriter,
+    Plugin,
+    Query,
+    Reflect,
+    Res,
+    ResMut,
+    Resource,
+    Single,
+    Startup,
+    Update,
+    With,
+};
+use bevy::window::{
+    MonitorSelection,
+    PresentMode,
+    PrimaryWindow,
+    VideoModeSelection,
+    Window,
+    WindowMode,
+    WindowPosition,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::time::Duration;
+
+/// Settings of the primary window. Until a file is written, they are those the window was created with.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct WindowSettings {
+    /// Physical size of the window when [`WindowSettingsMode::Windowed`]
+    pub resolution: (u32, u32),
+    pub mode: WindowSettingsMode,
+    pub vsync: bool,
+    /// Index of the monitor showing the window, `None` to leave it where it is
+    pub monitor: Option<usize>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: (1280, 720),
+            mode: WindowSettingsMode::default(),
+            vsync: true,
+            monitor: None,
+        }
+    }
+}
+
+impl GameSetting for WindowSettings {
+    const DEFAULT_CONF: &'static str = "window.conf";
+    /// Depends on the screens of the machine rather than the player
+    const PER_PROFILE: bool = false;
+    /// Dragging the window border resizes it every frame
+    const DEBOUNCE: Duration = Duration::from_secs(1);
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum WindowSettingsMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    /// Exclusive fullscreen, in the current video mode of the monitor
+    Fullscreen,
+}
+
+impl WindowSettings {
+    fn monitor_selection(&self) -> MonitorSelection {
+        self.monitor.map_or(MonitorSelection::Current, MonitorSelection::Index)
+    }
+
+    fn window_mode(&self) -> WindowMode {
+        match self.mode {
+            WindowSettingsMode::Windowed => WindowMode::Windowed,
+            WindowSettingsMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen(self.monitor_selection()),
+            WindowSettingsMode::Fullscreen => {
+                WindowMode::Fullscreen(self.monitor_selection(), VideoModeSelection::Current)
+            }
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    /// Settings matching `window`, keeping the windowed resolution while fullscreen and unknown monitors
+    fn read_window(&self, window: &Window) -> Self {
+        let (mode, monitor) = match window.mode {
+            WindowMode::Windowed => (WindowSettingsMode::Windowed, None),
+            WindowMode::BorderlessFullscreen(monitor) => (WindowSettingsMode::BorderlessFullscreen, Some(monitor)),
+            WindowMode::Fullscreen(monitor, _) => (WindowSettingsMode::Fullscreen, Some(monitor)),
+        };
+        Self {
+            resolution: if mode == WindowSettingsMode::Windowed {
+                (window.physical_width(), window.physical_height())
+            } else {
+                self.resolution
+            },
+            mode,
+            vsync: matches!(window.present_mode, PresentMode::AutoVsync | PresentMode::Fifo),
+            monitor: match monitor {
+                Some(MonitorSelection::Index(index)) => Some(index),
+                _ => self.monitor,
+            },
+        }
+    }
+}
+
+/// Load the [`WindowSettings`], apply them to the primary window whenever they change,
+/// and record the changes made to the window, like the player resizing it
+pub struct WindowSettingsPlugin;
+
+impl Plugin for WindowSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GameSettingSupportPlugin::<WindowSettings>::default())
+            .add_systems(Startup, capture_window.before(load_config::<WindowSettings>))
+            .add_systems(
+                Update,
+                (
+                    apply_window_settings.run_if(resource_changed::<WindowSettings>),
+                    record_window_changes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Start from the window the game configured, for players without a settings file
+fn capture_window(window: Single<&Window, With<PrimaryWindow>>, mut settings: ResMut<WindowSettings>) {
+    *settings = settings.read_window(&window);
+}
+
+fn apply_window_settings(mut window: Single<&mut Window, With<PrimaryWindow>>, settings: Res<WindowSettings>) {
+    let mode = settings.window_mode();
+    if window.mode != mode {
+        window.mode = mode;
+    }
+    let present_mode = settings.present_mode();
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+    let (width, height) = settings.resolution;
+    if settings.mode == WindowSettingsMode::Windowed
+        && (window.physical_width(), window.physical_height()) != (width, height)
+    {
+        window.resolution.set_physical_resolution(width, height);
+        if let Some(monitor) = settings.monitor {
+            window.position = WindowPosition::Centered(MonitorSelection::Index(monitor));
+        }
+    }
+}
+
+fn record_window_changes(
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut settings: ResMut<WindowSettings>,
+    mut changed: MessageWriter<GameSettingChanged>,
+) {
+    for window in &windows {
+        let recorded = settings.read_window(window);
+        if settings.set_if_neq(recorded) {
+            changed.write(GameSettingChanged);
+        }
+    }
+}
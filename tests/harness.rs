@@ -0,0 +1,343 @@
+use bevy::prelude::Resource;
+use bevy_save_manager::archive::{
+    ArchiveFailed,
+    ExportSave,
+    ImportSave,
+    SaveExported,
+    SaveImported,
+};
+use bevy_save_manager::backend::{
+    MemoryBackend,
+    SaveBackend,
+    SaveStorage,
+};
+use bevy_save_manager::cipher::{
+    seal,
+    PlainCipher,
+    SecretKey,
+};
+use bevy_save_manager::error::SaveError;
+use bevy_save_manager::inspect::{
+    decrypt_save,
+    encrypt_save,
+};
+use bevy_save_manager::paths::SaveDirs;
+use bevy_save_manager::save::{
+    load_world_blocking,
+    measure_save,
+    overwrite_world_blocking,
+    save_world_blocking,
+    EncryptSave,
+    EncryptSavePlugin,
+    GameSaved,
+    LoadFailed,
+    LoadGame,
+    ReEncryptFailed,
+    ReEncryptSaves,
+    SaveConfig,
+    SaveGame,
+    SavesReEncrypted,
+};
+use bevy_save_manager::testing::TestSaveHarness;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+use std::sync::Arc;
+
+#[derive(Resource, Serialize, Deserialize, Default, Clone)]
+struct Progress {
+    level: u32,
+}
+
+impl EncryptSave for Progress {}
+
+fn save(harness: &mut TestSaveHarness) -> u32 {
+    harness.send(SaveGame::new(0)).update();
+    **harness.assert_sent::<GameSaved>()
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let id = save(&mut harness);
+    assert!(!harness.backend().paths().is_empty());
+
+    harness.resource_mut::<Progress>().level = 7;
+    harness.send(LoadGame(id)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 3);
+}
+
+#[test]
+fn apps_keep_their_own_writes() {
+    let mut first = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    let mut second = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    first.resource_mut::<Progress>().level = 1;
+    second.resource_mut::<Progress>().level = 2;
+    let first_id = save(&mut first);
+    let second_id = save(&mut second);
+
+    first.resource_mut::<Progress>().level = 0;
+    second.resource_mut::<Progress>().level = 0;
+    first.send(LoadGame(first_id)).update();
+    second.send(LoadGame(second_id)).update();
+    assert_eq!(first.resource::<Progress>().level, 1);
+    assert_eq!(second.resource::<Progress>().level, 2);
+}
+
+#[test]
+fn blocking_save_needs_overwrite_for_an_existing_slot() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let world = harness.app().world_mut();
+    let id = save_world_blocking::<Progress>(world, 0).unwrap();
+
+    world.resource_mut::<Progress>().level = 7;
+    assert!(matches!(
+        save_world_blocking::<Progress>(world, id),
+        Err(SaveError::SlotOccupied(occupied)) if occupied == id
+    ));
+    load_world_blocking::<Progress>(world, id).unwrap();
+    assert_eq!(world.resource::<Progress>().level, 3);
+
+    world.resource_mut::<Progress>().level = 7;
+    assert_eq!(overwrite_world_blocking::<Progress>(world, id).unwrap(), id);
+    world.resource_mut::<Progress>().level = 0;
+    load_world_blocking::<Progress>(world, id).unwrap();
+    assert_eq!(world.resource::<Progress>().level, 7);
+}
+
+#[test]
+fn plain_file_is_refused_in_an_encrypted_slot() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let id = save(&mut harness);
+
+    let key = SecretKey::from(Progress::ENCR_KEY);
+    let save_config = harness.resource::<SaveConfig>();
+    let path = save_config
+        .save_dir(harness.resource::<SaveDirs>())
+        .join(&save_config.slot(id).unwrap().file);
+    let data = decrypt_save(&harness.backend().read(&path).unwrap(), &key).unwrap();
+    let plain = seal(&PlainCipher, &data, &key, None, None).unwrap();
+    harness.backend().write(&path, &plain).unwrap();
+    harness.send(LoadGame(id)).update();
+    harness.assert_sent::<LoadFailed>();
+}
+
+#[test]
+fn plain_slot_round_trip() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 5;
+    harness.send(SaveGame::new(0).plain()).update();
+    let id = **harness.assert_sent::<GameSaved>();
+
+    harness.resource_mut::<Progress>().level = 0;
+    harness.send(LoadGame(id)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 5);
+}
+
+#[test]
+fn plain_archive_is_refused_by_an_encrypted_game() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.send(SaveGame::new(0).plain()).update();
+    let id = **harness.assert_sent::<GameSaved>();
+
+    let dest = std::env::temp_dir().join(format!("bsm-plain-export-{}.bsma", std::process::id()));
+    harness
+        .send(ExportSave {
+            slot: id,
+            dest: dest.clone(),
+        })
+        .update();
+    harness.assert_sent::<SaveExported>();
+    harness.send(ImportSave { src: dest.clone() }).update();
+    let _ = std::fs::remove_file(&dest);
+    harness.assert_sent::<ArchiveFailed>();
+    harness.assert_not_sent::<SaveImported>();
+}
+
+#[test]
+fn reencrypt_keeps_the_key_when_a_file_fails() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let kept = save(&mut harness);
+    let broken = save(&mut harness);
+
+    let save_config = harness.resource::<SaveConfig>();
+    let path = save_config
+        .save_dir(harness.resource::<SaveDirs>())
+        .join(&save_config.slot(broken).unwrap().file);
+    harness.backend().write(&path, b"not a save").unwrap();
+    harness
+        .send(ReEncryptSaves {
+            old_key: SecretKey::from(Progress::ENCR_KEY),
+            new_key: SecretKey::from("fedcba9876543210"),
+        })
+        .update();
+    harness.assert_sent::<ReEncryptFailed>();
+    harness.assert_not_sent::<SavesReEncrypted>();
+
+    harness.resource_mut::<Progress>().level = 0;
+    harness.send(LoadGame(kept)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 3);
+}
+
+#[test]
+fn interrupted_reencrypt_is_resumed() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let rotated = save(&mut harness);
+    let kept = save(&mut harness);
+
+    // One file was written with the new key before the app exited
+    let old_key = SecretKey::from(Progress::ENCR_KEY);
+    let new_key = SecretKey::from("fedcba9876543210");
+    let save_config = harness.resource::<SaveConfig>();
+    let path = save_config
+        .save_dir(harness.resource::<SaveDirs>())
+        .join(&save_config.slot(rotated).unwrap().file);
+    let data = decrypt_save(&harness.backend().read(&path).unwrap(), &old_key).unwrap();
+    harness
+        .backend()
+        .write(&path, &encrypt_save(&data, &new_key).unwrap())
+        .unwrap();
+
+    harness.send(ReEncryptSaves { old_key, new_key }).update();
+    harness.assert_not_sent::<ReEncryptFailed>();
+    assert_eq!(**harness.assert_sent::<SavesReEncrypted>(), 1);
+
+    for id in [rotated, kept] {
+        harness.resource_mut::<Progress>().level = 0;
+        harness.send(LoadGame(id)).update();
+        harness.assert_not_sent::<LoadFailed>();
+        assert_eq!(harness.resource::<Progress>().level, 3);
+    }
+}
+
+/// Fails the `n`th write, counted from 1
+struct FailingWrite {
+    inner: MemoryBackend,
+    writes: AtomicUsize,
+    n: usize,
+}
+
+impl SaveBackend for FailingWrite {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        if self.writes.fetch_add(1, Ordering::Relaxed) + 1 == self.n {
+            return Err(io::ErrorKind::StorageFull.into());
+        }
+        self.inner.write(path, data)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        self.inner.list(dir)
+    }
+}
+
+#[test]
+fn reencrypt_failing_to_write_changes_the_key_of_the_others() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new());
+    harness.resource_mut::<Progress>().level = 3;
+    let slots = [save(&mut harness), save(&mut harness)];
+
+    let old_key = SecretKey::from(Progress::ENCR_KEY);
+    let new_key = SecretKey::from("fedcba9876543210");
+    let storage = harness.resource::<SaveStorage>().0.clone();
+    let failing = FailingWrite {
+        inner: harness.backend().clone(),
+        writes: AtomicUsize::new(0),
+        n: 2,
+    };
+    harness.app().insert_resource(SaveStorage(Arc::new(failing)));
+    harness
+        .send(ReEncryptSaves {
+            old_key: old_key.clone(),
+            new_key: new_key.clone(),
+        })
+        .update();
+    let failed = harness.assert_sent::<ReEncryptFailed>().slot.unwrap();
+    assert_eq!(**harness.assert_sent::<SavesReEncrypted>(), 1);
+
+    // The file written is read with the new key, the other one once the message is sent again
+    let written = slots.into_iter().find(|id| *id != failed).unwrap();
+    harness.resource_mut::<Progress>().level = 0;
+    harness.send(LoadGame(written)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 3);
+    harness.send(LoadGame(failed)).update();
+    harness.assert_sent::<LoadFailed>();
+
+    harness.app().insert_resource(SaveStorage(storage));
+    harness.send(ReEncryptSaves { old_key, new_key }).update();
+    harness.assert_not_sent::<ReEncryptFailed>();
+    assert_eq!(**harness.assert_sent::<SavesReEncrypted>(), 1);
+    harness.resource_mut::<Progress>().level = 0;
+    harness.send(LoadGame(failed)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 3);
+}
+
+#[test]
+fn delta_slot_is_exported_in_full() {
+    let mut harness = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new().with_delta_autosaves(4));
+    harness.resource_mut::<Progress>().level = 1;
+    let id = save(&mut harness);
+    harness.resource_mut::<Progress>().level = 2;
+    harness.send(SaveGame::auto(id)).update();
+    assert!(harness.resource::<SaveConfig>().slot(id).unwrap().base.is_some());
+
+    let dest = std::env::temp_dir().join(format!("bsm-delta-export-{}.bsma", std::process::id()));
+    harness
+        .send(ExportSave {
+            slot: id,
+            dest: dest.clone(),
+        })
+        .update();
+    harness.assert_sent::<SaveExported>();
+    harness.send(ImportSave { src: dest.clone() }).update();
+    let _ = std::fs::remove_file(&dest);
+    let imported = harness.assert_sent::<SaveImported>().slot;
+
+    harness.resource_mut::<Progress>().level = 0;
+    harness.send(LoadGame(imported)).update();
+    harness.assert_not_sent::<LoadFailed>();
+    assert_eq!(harness.resource::<Progress>().level, 2);
+}
+
+#[test]
+fn measuring_keeps_seeded_file_names() {
+    let mut measured = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new().with_naming_seed(7));
+    let mut unmeasured = TestSaveHarness::new(EncryptSavePlugin::<Progress>::new().with_naming_seed(7));
+    measure_save::<Progress>(measured.app().world(), 0).unwrap();
+    let first = save(&mut measured);
+    let second = save(&mut unmeasured);
+
+    let file = |harness: &TestSaveHarness, id| harness.resource::<SaveConfig>().slot(id).unwrap().file.clone();
+    assert_eq!(file(&measured, first), file(&unmeasured, second));
+}